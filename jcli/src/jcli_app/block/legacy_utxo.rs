@@ -0,0 +1,245 @@
+use jcli_app::block::{Common, Error};
+use jcli_app::utils::error::CustomErrorFiller;
+use jormungandr_lib::{
+    crypto::hash::Hash,
+    interfaces::{Initial, LegacyUTxO, OldAddress, Value},
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use structopt::{clap::arg_enum, StructOpt};
+
+/// the number of entries of a legacy UTxO declaration is encoded on a byte
+const MAX_LEGACY_UTXO_PER_DECLARATION: usize = 254;
+
+arg_enum! {
+    #[derive(StructOpt, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SnapshotFormat {
+        Csv,
+        Json,
+    }
+}
+
+#[derive(StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct ImportLegacyUtxo {
+    /// format of the snapshot of the legacy UTxOs:
+    ///
+    /// * csv: one `address,value` per line, empty lines and lines starting
+    ///   with `#` are ignored;
+    /// * json: a list of `{ "address": ..., "value": ... }` objects.
+    #[structopt(long = "format", default_value = "csv")]
+    format: SnapshotFormat,
+
+    /// hash of the last block of the legacy chain, recorded in the
+    /// `legacy_last_block_hash` field of the genesis file.
+    #[structopt(long = "legacy-last-block-hash")]
+    legacy_last_block_hash: Option<Hash>,
+
+    #[structopt(flatten)]
+    common: Common,
+}
+
+impl ImportLegacyUtxo {
+    pub fn exec(self) -> Result<(), Error> {
+        let snapshot = match self.format {
+            SnapshotFormat::Csv => read_csv(self.common.input.open()?)?,
+            SnapshotFormat::Json => serde_json::from_reader(self.common.input.open()?)
+                .map_err(|source| Error::LegacyUtxoSnapshotJsonInvalid { source })?,
+        };
+        let utxos = aggregate(snapshot)?;
+        if utxos.is_empty() {
+            return Err(Error::LegacyUtxoSnapshotEmpty);
+        }
+        let initial = InitialSection {
+            initial: utxos
+                .chunks(MAX_LEGACY_UTXO_PER_DECLARATION)
+                .map(|chunk| Initial::LegacyFund(chunk.to_vec()))
+                .collect(),
+            legacy_last_block_hash: self.legacy_last_block_hash,
+        };
+
+        let mut output = self.common.open_output()?;
+        let write_error = |source| Error::OutputInvalid {
+            source,
+            path: self.common.output_file.clone().unwrap_or_default(),
+        };
+        writeln!(
+            output,
+            "# {} legacy UTxOs, total value: {}",
+            utxos.len(),
+            total(&utxos)?
+        )
+        .map_err(write_error)?;
+        serde_yaml::to_writer(&mut output, &initial).map_err(|source| {
+            Error::GenesisSerializationFailed {
+                source,
+                filler: CustomErrorFiller,
+            }
+        })?;
+        writeln!(output).map_err(write_error)
+    }
+}
+
+/// the fields of the genesis file generated from the legacy UTxOs
+#[derive(Serialize)]
+struct InitialSection {
+    initial: Vec<Initial>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    legacy_last_block_hash: Option<Hash>,
+}
+
+fn read_csv(reader: impl BufRead) -> Result<Vec<LegacyUTxO>, Error> {
+    let mut utxos = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|source| Error::LegacyUtxoSnapshotReadFailed { source })?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |reason: String| Error::LegacyUtxoSnapshotLineInvalid {
+            line: index + 1,
+            reason,
+        };
+
+        let mut fields = line.split(',').map(str::trim);
+        let (address, value) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(address), Some(value), None) => (address, value),
+            _ => return Err(invalid("expected `address,value'".to_owned())),
+        };
+        utxos.push(LegacyUTxO {
+            address: address
+                .parse()
+                .map_err(|err| invalid(format!("invalid legacy address: {}", err)))?,
+            value: value
+                .parse()
+                .map_err(|err| invalid(format!("invalid value: {}", err)))?,
+        });
+    }
+    Ok(utxos)
+}
+
+/// merge the entries of the same address, keeping the order of the first
+/// entry of every address
+fn aggregate(snapshot: Vec<LegacyUTxO>) -> Result<Vec<LegacyUTxO>, Error> {
+    let mut utxos: Vec<LegacyUTxO> = Vec::with_capacity(snapshot.len());
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+    for utxo in snapshot {
+        if utxo.value.as_ref().0 == 0 {
+            return Err(Error::LegacyUtxoValueZero {
+                address: utxo.address,
+            });
+        }
+        let key = utxo.address.to_string();
+        match indexes.get(&key) {
+            Some(&index) => {
+                let aggregated = &mut utxos[index];
+                aggregated.value = add(aggregated.value, utxo.value, &utxo.address)?;
+            }
+            None => {
+                indexes.insert(key, utxos.len());
+                utxos.push(utxo);
+            }
+        }
+    }
+    Ok(utxos)
+}
+
+fn total(utxos: &[LegacyUTxO]) -> Result<Value, Error> {
+    utxos.iter().try_fold(Value::from(0), |total, utxo| {
+        add(total, utxo.value, &utxo.address)
+    })
+}
+
+fn add(value: Value, other: Value, address: &OldAddress) -> Result<Value, Error> {
+    value
+        .as_ref()
+        .0
+        .checked_add(other.as_ref().0)
+        .map(Value::from)
+        .ok_or_else(|| Error::LegacyUtxoValueOverflow {
+            address: address.clone(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS_1: &str = "Ae2tdPwUPEZ4YjgvykNpoFeYUxoyhNj2kg8KfKWN2FizsSpLUPv68MpTVDo";
+    const ADDRESS_2: &str = "Ae2tdPwUPEZ4YjoAoQAFN3FDUSwxRpVoSQMPJATcPmWeEvoVYRkAUPgr3FJ";
+
+    fn utxo(address: &str, value: u64) -> LegacyUTxO {
+        LegacyUTxO {
+            address: address.parse().unwrap(),
+            value: value.into(),
+        }
+    }
+
+    fn invalid_line(csv: &str) -> usize {
+        match read_csv(csv.as_bytes()) {
+            Err(Error::LegacyUtxoSnapshotLineInvalid { line, .. }) => line,
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("the snapshot should be rejected"),
+        }
+    }
+
+    #[test]
+    fn read_csv_skips_comments_and_empty_lines() {
+        let csv = format!(
+            "# address, value\n{},10\n\n  {} , 20 \n# end\n",
+            ADDRESS_1, ADDRESS_2
+        );
+        let utxos = read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(utxos, vec![utxo(ADDRESS_1, 10), utxo(ADDRESS_2, 20)]);
+    }
+
+    #[test]
+    fn read_csv_rejects_malformed_rows() {
+        assert_eq!(invalid_line(&format!("{}\n", ADDRESS_1)), 1);
+        assert_eq!(
+            invalid_line(&format!("# comment\n{},10,20\n", ADDRESS_1)),
+            2
+        );
+        assert_eq!(
+            invalid_line(&format!("{},10\nnot an address,10\n", ADDRESS_1)),
+            2
+        );
+        assert_eq!(invalid_line(&format!("{},-10\n", ADDRESS_1)), 1);
+        assert_eq!(invalid_line(&format!("{},ten\n", ADDRESS_1)), 1);
+    }
+
+    #[test]
+    fn aggregate_sums_the_duplicate_addresses() {
+        let snapshot = vec![
+            utxo(ADDRESS_1, 10),
+            utxo(ADDRESS_2, 20),
+            utxo(ADDRESS_1, 30),
+        ];
+        let utxos = aggregate(snapshot).unwrap();
+        assert_eq!(utxos, vec![utxo(ADDRESS_1, 40), utxo(ADDRESS_2, 20)]);
+        assert_eq!(total(&utxos).unwrap(), Value::from(60));
+    }
+
+    #[test]
+    fn aggregate_rejects_zero_values() {
+        match aggregate(vec![utxo(ADDRESS_1, 10), utxo(ADDRESS_2, 0)]) {
+            Err(Error::LegacyUtxoValueZero { address }) => {
+                assert_eq!(address.to_string(), ADDRESS_2)
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("the zero value should be rejected"),
+        }
+    }
+
+    #[test]
+    fn aggregate_rejects_value_overflows() {
+        match aggregate(vec![utxo(ADDRESS_1, u64::max_value()), utxo(ADDRESS_1, 1)]) {
+            Err(Error::LegacyUtxoValueOverflow { address }) => {
+                assert_eq!(address.to_string(), ADDRESS_1)
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("the overflow should be rejected"),
+        }
+    }
+}
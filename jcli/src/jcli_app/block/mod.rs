@@ -1,3 +1,5 @@
+mod legacy_utxo;
+
 use chain_core::property::{Block as _, Deserialize, HasFragments, Serialize};
 use chain_impl_mockchain::{
     block::Block,
@@ -6,6 +8,7 @@ use chain_impl_mockchain::{
 use jcli_app::utils::{error::CustomErrorFiller, io};
 use jormungandr_lib::interfaces::{
     block0_configuration_documented_example, Block0Configuration, Block0ConfigurationError,
+    OldAddress,
 };
 use std::io::{BufRead, Write};
 use std::path::PathBuf;
@@ -22,6 +25,12 @@ custom_error! {pub Error
     BlockSerializationFailed { source: std::io::Error, filler: CustomErrorFiller } = "failed to serialize block",
     GenesisSerializationFailed { source: serde_yaml::Error, filler: CustomErrorFiller } = "failed to serialize genesis",
     BuildingGenesisFromBlock0Failed { source: Block0ConfigurationError } = "failed to build genesis from block 0",
+    LegacyUtxoSnapshotReadFailed { source: std::io::Error } = "failed to read the legacy UTxO snapshot",
+    LegacyUtxoSnapshotLineInvalid { line: usize, reason: String } = "invalid legacy UTxO snapshot, line {line}: {reason}",
+    LegacyUtxoSnapshotJsonInvalid { source: serde_json::Error } = "invalid legacy UTxO snapshot",
+    LegacyUtxoSnapshotEmpty = "no UTxO in the legacy UTxO snapshot",
+    LegacyUtxoValueZero { address: OldAddress } = "legacy UTxO of address {address} has no value",
+    LegacyUtxoValueOverflow { address: OldAddress } = "value overflow while aggregating the legacy UTxOs of address {address}",
 }
 
 impl Genesis {
@@ -31,6 +40,7 @@ impl Genesis {
             Genesis::Encode(create_arguments) => encode_block_0(create_arguments),
             Genesis::Decode(info_arguments) => decode_block_0(info_arguments),
             Genesis::Hash(hash_arguments) => print_hash(hash_arguments),
+            Genesis::ImportLegacyUtxo(import_arguments) => import_arguments.exec(),
         }
     }
 }
@@ -92,6 +102,10 @@ pub enum Genesis {
 
     /// print the block hash (aka the block id) of the block 0
     Hash(Input),
+
+    /// create the `initial` section of the genesis file carrying the funds
+    /// of a snapshot of the UTxOs of the legacy chain
+    ImportLegacyUtxo(legacy_utxo::ImportLegacyUtxo),
 }
 
 #[derive(StructOpt)]
@@ -1,7 +1,7 @@
 use bech32::{u5, Bech32, FromBase32, ToBase32};
 use chain_crypto::{
     AsymmetricKey, AsymmetricPublicKey, Curve25519_2HashDH, Ed25519, Ed25519Bip32, Ed25519Extended,
-    SumEd25519_12,
+    SecretKey, SumEd25519_12,
 };
 use hex::FromHexError;
 use jcli_app::utils::io;
@@ -25,6 +25,9 @@ custom_error! { pub Error
     InvalidOutput { source: std::io::Error, path: PathBuf }
         = @{{ let _ = source; format_args!("invalid output file path '{}'", path.display()) }},
     UnknownBech32PrivKeyHrp { hrp: String } = "unrecognized private key bech32 HRP: {hrp}",
+    LegacyKeyLengthInvalid { length: usize }
+        = "invalid legacy private key length, expected 96 or 128 bytes but received {length}",
+    LegacyKeyPublicKeyMismatch = "the public key of the legacy private key does not match its secret",
 }
 
 #[derive(StructOpt, Debug)]
@@ -38,6 +41,22 @@ pub enum Key {
     FromBytes(FromBytes),
     /// get the bytes out of a private key
     ToBytes(ToBytes),
+    /// import the extended private key of a legacy wallet, to sign the
+    /// redemption of its legacy UTxOs
+    FromLegacy(FromLegacy),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct FromLegacy {
+    /// the decrypted extended private key of the legacy wallet in
+    /// hexadecimal: either the 96 bytes of the secret and the chain code,
+    /// or the 128 bytes of the secret, the public key and the chain code.
+    /// If not provided, it will be read from the standard input.
+    #[structopt(name = "INPUT_BYTES")]
+    input_bytes: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    output_file: OutputFile,
 }
 
 #[derive(StructOpt, Debug)]
@@ -133,6 +152,7 @@ impl Key {
             Key::ToPublic(args) => args.exec(),
             Key::ToBytes(args) => args.exec(),
             Key::FromBytes(args) => args.exec(),
+            Key::FromLegacy(args) => args.exec(),
         }
     }
 }
@@ -214,6 +234,42 @@ impl FromBytes {
     }
 }
 
+impl FromLegacy {
+    fn exec(self) -> Result<(), Error> {
+        use chain_crypto::bech32::Bech32 as _;
+        let secret = legacy_secret_key(read_hex(self.input_bytes)?)?;
+        let mut output = self.output_file.open()?;
+        writeln!(output, "{}", secret.to_bech32_str())?;
+        Ok(())
+    }
+}
+
+/// the secret key of a legacy extended private key, its public key is
+/// checked against the secret when it is included
+fn legacy_secret_key(bytes: Vec<u8>) -> Result<SecretKey<Ed25519Bip32>, Error> {
+    let (xprv, public_key) = match bytes.len() {
+        LEGACY_XPRV_SIZE => (bytes, None),
+        LEGACY_XPRV_WITH_PUBLIC_KEY_SIZE => {
+            let mut xprv = bytes[..64].to_vec();
+            xprv.extend_from_slice(&bytes[96..]);
+            (xprv, Some(bytes[64..96].to_vec()))
+        }
+        length => return Err(Error::LegacyKeyLengthInvalid { length }),
+    };
+    let secret: SecretKey<Ed25519Bip32> = SecretKey::from_binary(&xprv)?;
+    if let Some(public_key) = public_key {
+        if &secret.to_public().as_ref()[..32] != public_key.as_slice() {
+            return Err(Error::LegacyKeyPublicKeyMismatch);
+        }
+    }
+    Ok(secret)
+}
+
+/// the extended secret key and the chain code
+const LEGACY_XPRV_SIZE: usize = 96;
+/// the extended secret key, the public key and the chain code
+const LEGACY_XPRV_WITH_PUBLIC_KEY_SIZE: usize = 128;
+
 fn read_hex<P: AsRef<Path>>(path: Option<P>) -> Result<Vec<u8>, Error> {
     hex::decode(read_line(path)?.trim()).map_err(Into::into)
 }
@@ -275,3 +331,67 @@ impl std::str::FromStr for Seed {
         Ok(Seed(bytes))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_crypto::bech32::Bech32 as _;
+
+    /// the 96 bytes of the extended secret and the chain code of a key
+    fn legacy_xprv() -> Vec<u8> {
+        let bech32 = gen_priv_key::<Ed25519Bip32>(Some(Seed([7; 32]))).unwrap();
+        Vec::<u8>::from_base32(bech32.data()).unwrap()
+    }
+
+    /// the 128 bytes form: the public key is inserted before the chain code
+    fn with_public_key(xprv: &[u8], public_key: &[u8]) -> Vec<u8> {
+        let mut bytes = xprv[..64].to_vec();
+        bytes.extend_from_slice(public_key);
+        bytes.extend_from_slice(&xprv[64..]);
+        bytes
+    }
+
+    #[test]
+    fn legacy_key_of_96_bytes() {
+        let xprv = legacy_xprv();
+        assert_eq!(xprv.len(), LEGACY_XPRV_SIZE);
+
+        let secret = legacy_secret_key(xprv.clone()).unwrap();
+        let expected: SecretKey<Ed25519Bip32> = SecretKey::from_binary(&xprv).unwrap();
+        assert_eq!(secret.to_bech32_str(), expected.to_bech32_str());
+    }
+
+    #[test]
+    fn legacy_key_of_128_bytes() {
+        let xprv = legacy_xprv();
+        let expected: SecretKey<Ed25519Bip32> = SecretKey::from_binary(&xprv).unwrap();
+        let bytes = with_public_key(&xprv, &expected.to_public().as_ref()[..32]);
+        assert_eq!(bytes.len(), LEGACY_XPRV_WITH_PUBLIC_KEY_SIZE);
+
+        let secret = legacy_secret_key(bytes).unwrap();
+        assert_eq!(secret.to_bech32_str(), expected.to_bech32_str());
+    }
+
+    #[test]
+    fn legacy_key_with_a_mismatching_public_key() {
+        let xprv = legacy_xprv();
+        let secret: SecretKey<Ed25519Bip32> = SecretKey::from_binary(&xprv).unwrap();
+        let mut public_key = secret.to_public().as_ref()[..32].to_vec();
+        public_key[0] ^= 0xff;
+
+        match legacy_secret_key(with_public_key(&xprv, &public_key)) {
+            Err(Error::LegacyKeyPublicKeyMismatch) => (),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("the mismatching public key is accepted"),
+        }
+    }
+
+    #[test]
+    fn legacy_key_of_an_invalid_length() {
+        match legacy_secret_key(vec![0; 64]) {
+            Err(Error::LegacyKeyLengthInvalid { length }) => assert_eq!(length, 64),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("the key of 64 bytes is accepted"),
+        }
+    }
+}
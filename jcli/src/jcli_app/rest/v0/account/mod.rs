@@ -1,5 +1,5 @@
 use jcli_app::rest::Error;
-use jcli_app::utils::{AccountId, DebugFlag, HostAddr, OutputFormat, RestApiSender};
+use jcli_app::utils::{AccountId, DebugFlag, HostAddr, OutputFormat, RestApiSender, StateAt};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
@@ -13,6 +13,8 @@ pub enum Account {
         debug: DebugFlag,
         #[structopt(flatten)]
         output_format: OutputFormat,
+        #[structopt(flatten)]
+        state_at: StateAt,
         /// An Account ID either in the form of an address of kind account, or an account public key
         #[structopt(parse(try_from_str = "AccountId::try_from_str"))]
         account_id: AccountId,
@@ -25,12 +27,13 @@ impl Account {
             addr,
             debug,
             output_format,
+            state_at,
             account_id,
         } = self;
         let url = addr
             .with_segments(&["v0", "account", &account_id.to_url_arg()])?
             .into_url();
-        let builder = reqwest::Client::new().get(url);
+        let builder = state_at.query(reqwest::Client::new().get(url));
         let response = RestApiSender::new(builder, &debug).send()?;
         response.ok_response()?;
         let state = response.body().json_value()?;
@@ -39,6 +39,9 @@ pub enum Leaders {
 
     /// Leadership log operations
     Logs(GetLogs),
+
+    /// Leadership schedule operations
+    Schedule(GetSchedule),
 }
 
 #[derive(StructOpt)]
@@ -53,6 +56,33 @@ pub enum GetLogs {
         #[structopt(flatten)]
         output_format: OutputFormat,
     },
+    /// Get the outcomes of the leadership events per epoch
+    Summary {
+        #[structopt(flatten)]
+        addr: HostAddr,
+        #[structopt(flatten)]
+        debug: DebugFlag,
+        #[structopt(flatten)]
+        output_format: OutputFormat,
+    },
+}
+
+#[derive(StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub enum GetSchedule {
+    /// Get the slots the leaders are elected for during an epoch
+    Get {
+        #[structopt(flatten)]
+        addr: HostAddr,
+        #[structopt(flatten)]
+        debug: DebugFlag,
+        #[structopt(flatten)]
+        output_format: OutputFormat,
+        /// Epoch of the schedule, either the current or the next one.
+        /// Default is the current epoch
+        #[structopt(short, long)]
+        epoch: Option<u32>,
+    },
 }
 
 impl Leaders {
@@ -70,6 +100,17 @@ impl Leaders {
                 debug,
                 output_format,
             }) => get_logs(addr, debug, output_format),
+            Leaders::Logs(GetLogs::Summary {
+                addr,
+                debug,
+                output_format,
+            }) => get_logs_summary(addr, debug, output_format),
+            Leaders::Schedule(GetSchedule::Get {
+                addr,
+                debug,
+                output_format,
+                epoch,
+            }) => get_schedule(addr, debug, output_format, epoch),
         }
     }
 }
@@ -118,3 +159,38 @@ fn get_logs(addr: HostAddr, debug: DebugFlag, output_format: OutputFormat) -> Re
     println!("{}", formatted);
     Ok(())
 }
+
+fn get_logs_summary(
+    addr: HostAddr,
+    debug: DebugFlag,
+    output_format: OutputFormat,
+) -> Result<(), Error> {
+    let url = addr
+        .with_segments(&["v0", "leaders", "logs", "summary"])?
+        .into_url();
+    let builder = reqwest::Client::new().get(url);
+    let response = RestApiSender::new(builder, &debug).send()?;
+    response.ok_response()?;
+    let summary = response.body().json_value()?;
+    let formatted = output_format.format_json(summary)?;
+    println!("{}", formatted);
+    Ok(())
+}
+
+fn get_schedule(
+    addr: HostAddr,
+    debug: DebugFlag,
+    output_format: OutputFormat,
+    epoch: Option<u32>,
+) -> Result<(), Error> {
+    let url = addr
+        .with_segments(&["v0", "leaders", "schedule"])?
+        .into_url();
+    let builder = reqwest::Client::new().get(url).query(&[("epoch", epoch)]);
+    let response = RestApiSender::new(builder, &debug).send()?;
+    response.ok_response()?;
+    let schedule = response.body().json_value()?;
+    let formatted = output_format.format_json(schedule)?;
+    println!("{}", formatted);
+    Ok(())
+}
@@ -23,6 +23,12 @@ pub enum Message {
         /// If not provided, message will be read from stdin.
         #[structopt(short, long)]
         file: Option<PathBuf>,
+        /// Wait for the message to be processed by the node's message pool
+        /// and print the result of its submission instead of its id
+        #[structopt(long)]
+        wait: bool,
+        #[structopt(flatten)]
+        output_format: OutputFormat,
     },
 
     /// get the node's logs on the message pool. This will provide information
@@ -41,7 +47,13 @@ pub enum Message {
 impl Message {
     pub fn exec(self) -> Result<(), Error> {
         match self {
-            Message::Post { addr, debug, file } => post_message(file, addr, debug),
+            Message::Post {
+                addr,
+                debug,
+                file,
+                wait,
+                output_format,
+            } => post_message(file, addr, debug, wait, output_format),
             Message::Logs {
                 addr,
                 debug,
@@ -62,11 +74,17 @@ fn get_logs(addr: HostAddr, debug: DebugFlag, output_format: OutputFormat) -> Re
     Ok(())
 }
 
-fn post_message(file: Option<PathBuf>, addr: HostAddr, debug: DebugFlag) -> Result<(), Error> {
+fn post_message(
+    file: Option<PathBuf>,
+    addr: HostAddr,
+    debug: DebugFlag,
+    wait: bool,
+    output_format: OutputFormat,
+) -> Result<(), Error> {
     let msg_hex = io::read_line(&file)?;
     let msg_bin = hex::decode(&msg_hex)?;
     let url = addr.with_segments(&["v0", "message"])?.into_url();
-    let builder = reqwest::Client::new().post(url);
+    let builder = reqwest::Client::new().post(url).query(&[("wait", wait)]);
     let fragment = Fragment::deserialize(msg_bin.as_slice().into_buf()).map_err(|e| {
         Error::InputFragmentMalformed {
             source: e,
@@ -77,6 +95,12 @@ fn post_message(file: Option<PathBuf>, addr: HostAddr, debug: DebugFlag) -> Resu
         .with_binary_body(msg_bin)
         .send()?;
     response.ok_response()?;
-    println!("{}", fragment.id());
+    if wait {
+        let submission = response.body().json_value()?;
+        let formatted = output_format.format_json(submission)?;
+        println!("{}", formatted);
+    } else {
+        println!("{}", fragment.id());
+    }
     Ok(())
 }
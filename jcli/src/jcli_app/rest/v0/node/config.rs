@@ -0,0 +1,38 @@
+use jcli_app::rest::Error;
+use jcli_app::utils::{DebugFlag, HostAddr, OutputFormat, RestApiSender};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub enum Config {
+    /// Read the node configuration file again and apply the settings
+    /// which can be modified without a restart
+    Reload {
+        #[structopt(flatten)]
+        addr: HostAddr,
+        #[structopt(flatten)]
+        debug: DebugFlag,
+        #[structopt(flatten)]
+        output_format: OutputFormat,
+    },
+}
+
+impl Config {
+    pub fn exec(self) -> Result<(), Error> {
+        let Config::Reload {
+            addr,
+            debug,
+            output_format,
+        } = self;
+        let url = addr
+            .with_segments(&["v0", "node", "config", "reload"])?
+            .into_url();
+        let builder = reqwest::Client::new().post(url);
+        let response = RestApiSender::new(builder, &debug).send()?;
+        response.ok_response()?;
+        let report = response.body().json_value()?;
+        let formatted = output_format.format_json(report)?;
+        println!("{}", formatted);
+        Ok(())
+    }
+}
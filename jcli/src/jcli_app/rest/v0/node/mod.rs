@@ -1,5 +1,9 @@
+mod config;
+mod services;
 mod stats;
 
+use self::config::Config;
+use self::services::Services;
 use self::stats::Stats;
 use jcli_app::rest::Error;
 use structopt::StructOpt;
@@ -9,12 +13,18 @@ use structopt::StructOpt;
 pub enum Node {
     /// Node information
     Stats(Stats),
+    /// Node configuration
+    Config(Config),
+    /// Health of the node services
+    Services(Services),
 }
 
 impl Node {
     pub fn exec(self) -> Result<(), Error> {
         match self {
             Node::Stats(stats) => stats.exec(),
+            Node::Config(config) => config.exec(),
+            Node::Services(services) => services.exec(),
         }
     }
 }
@@ -1,5 +1,5 @@
 use jcli_app::rest::Error;
-use jcli_app::utils::{DebugFlag, HostAddr, OutputFormat, RestApiSender};
+use jcli_app::utils::{DebugFlag, HostAddr, OutputFormat, RestApiSender, StateAt};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
@@ -13,6 +13,8 @@ pub enum Settings {
         debug: DebugFlag,
         #[structopt(flatten)]
         output_format: OutputFormat,
+        #[structopt(flatten)]
+        state_at: StateAt,
     },
 }
 
@@ -22,9 +24,10 @@ impl Settings {
             addr,
             debug,
             output_format,
+            state_at,
         } = self;
         let url = addr.with_segments(&["v0", "settings"])?.into_url();
-        let builder = reqwest::Client::new().get(url);
+        let builder = state_at.query(reqwest::Client::new().get(url));
         let response = RestApiSender::new(builder, &debug).send()?;
         response.ok_response()?;
         let status = response.body().json_value()?;
@@ -1,11 +1,11 @@
 use jcli_app::rest::Error;
-use jcli_app::utils::{DebugFlag, HostAddr, OutputFormat, RestApiSender};
+use jcli_app::utils::{DebugFlag, HostAddr, OutputFormat, RestApiSender, StateAt};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
 #[structopt(rename_all = "kebab-case")]
 pub enum Utxo {
-    /// Get all UTXOs
+    /// Get all UTXOs, or a single UTXO if its transaction ID and index are given
     Get {
         #[structopt(flatten)]
         addr: HostAddr,
@@ -13,6 +13,27 @@ pub enum Utxo {
         debug: DebugFlag,
         #[structopt(flatten)]
         output_format: OutputFormat,
+        #[structopt(flatten)]
+        state_at: StateAt,
+        /// Hex-encoded ID of the transaction of the UTXO
+        #[structopt(name = "FRAGMENT_ID", requires = "INDEX")]
+        fragment_id: Option<String>,
+        /// Index of the UTXO in the outputs of the transaction
+        #[structopt(name = "INDEX")]
+        index: Option<u8>,
+        /// Get only the UTXOs of the given address. The UTXOs of a legacy
+        /// address are the ones declared in the block 0 and not redeemed yet
+        #[structopt(long)]
+        address: Option<String>,
+        /// Get the UTXOs following the given one, in the form
+        /// `<transaction_id>:<index>`. To get the next page of UTXOs, use
+        /// the last UTXO of the previous page
+        #[structopt(long)]
+        after: Option<String>,
+        /// Maximum number of UTXOs, at most 1000 (100 by default with `--after`,
+        /// all of them without)
+        #[structopt(long)]
+        count: Option<usize>,
     },
 }
 
@@ -22,9 +43,29 @@ impl Utxo {
             addr,
             debug,
             output_format,
+            state_at,
+            fragment_id,
+            index,
+            address,
+            after,
+            count,
         } = self;
-        let url = addr.with_segments(&["v0", "utxo"])?.into_url();
-        let builder = reqwest::Client::new().get(url);
+        let builder = match (fragment_id, index) {
+            (Some(fragment_id), Some(index)) => {
+                let url = addr
+                    .with_segments(&["v0", "utxo", &fragment_id, &index.to_string()])?
+                    .into_url();
+                reqwest::Client::new().get(url)
+            }
+            _ => {
+                let url = addr.with_segments(&["v0", "utxo"])?.into_url();
+                reqwest::Client::new()
+                    .get(url)
+                    .query(&[("address", address), ("after", after)])
+                    .query(&[("count", count)])
+            }
+        };
+        let builder = state_at.query(builder);
         let response = RestApiSender::new(builder, &debug).send()?;
         response.ok_response()?;
         let status = response.body().json_value()?;
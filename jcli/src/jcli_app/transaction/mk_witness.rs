@@ -4,7 +4,7 @@ use chain_crypto::{AsymmetricKey, Ed25519Bip32, SecretKey};
 use chain_impl_mockchain::{
     account::SpendingCounter,
     block::HeaderHash,
-    transaction::{TransactionSignDataHash, Witness},
+    transaction::{TransactionSignDataHash, Witness, WitnessUtxoData},
 };
 use jcli_app::transaction::Error;
 use jcli_app::utils::{
@@ -76,10 +76,8 @@ impl MkWitness {
                 Witness::new_utxo(&self.genesis_block_hash, &self.sign_data_hash, &secret_key)
             }
             WitnessType::OldUTxO => {
-                // TODO unimplemented!()
-                let _secret_key: SecretKey<Ed25519Bip32> = self.secret()?;
-                Err(Error::MakeWitnessLegacyUtxoUnsupported)?;
-                unimplemented!()
+                let secret_key: SecretKey<Ed25519Bip32> = self.secret()?;
+                legacy_utxo_witness(&self.genesis_block_hash, &self.sign_data_hash, &secret_key)
             }
             WitnessType::Account => {
                 let account_spending_counter = self
@@ -122,3 +120,47 @@ impl MkWitness {
         })
     }
 }
+
+/// the witness of a legacy UTxO: the public key of the legacy key is part of
+/// the witness as it cannot be found from the legacy address
+fn legacy_utxo_witness(
+    genesis_block_hash: &HeaderHash,
+    sign_data_hash: &TransactionSignDataHash,
+    secret_key: &SecretKey<Ed25519Bip32>,
+) -> Witness {
+    let signature = secret_key.sign(&WitnessUtxoData::new(genesis_block_hash, sign_data_hash));
+    Witness::OldUtxo(secret_key.to_public(), signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_crypto::Verification;
+    use rand::SeedableRng as _;
+    use rand_chacha::ChaChaRng;
+
+    const GENESIS_BLOCK_HASH: &str =
+        "adbdd5ede31637f6c9bad5c271eec0bc3d0cb9efb86a5b913bb55cba549d0770";
+    const SIGN_DATA_HASH: &str = "6c1e8c07f2e5e1c8a1e4f8d2e29b5b0bd2a1c0c6e7e18cb8f57b70e22a7bd1c4";
+
+    #[test]
+    fn legacy_utxo_witness_is_signed_with_the_legacy_key() {
+        let genesis_block_hash: HeaderHash = GENESIS_BLOCK_HASH.parse().unwrap();
+        let sign_data_hash: TransactionSignDataHash = SIGN_DATA_HASH.parse().unwrap();
+        let secret_key = SecretKey::<Ed25519Bip32>::generate(ChaChaRng::from_seed([3; 32]));
+
+        let witness = legacy_utxo_witness(&genesis_block_hash, &sign_data_hash, &secret_key);
+
+        match witness {
+            Witness::OldUtxo(public_key, signature) => {
+                assert!(public_key == secret_key.to_public());
+                let data = WitnessUtxoData::new(&genesis_block_hash, &sign_data_hash);
+                match signature.verify(&public_key, &data) {
+                    Verification::Success => (),
+                    Verification::Failed => panic!("the legacy witness does not verify"),
+                }
+            }
+            _ => panic!("not a legacy UTxO witness"),
+        }
+    }
+}
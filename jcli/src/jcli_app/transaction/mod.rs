@@ -9,6 +9,7 @@ mod info;
 mod mk_witness;
 mod new;
 mod seal;
+mod simulate;
 mod staging;
 
 use self::staging::StagingKind;
@@ -51,6 +52,10 @@ pub enum Transaction {
     MakeWitness(mk_witness::MkWitness),
     /// get the message format out of a sealed transaction
     ToMessage(common::CommonTransaction),
+    /// apply a sealed transaction on the ledger of the node's blockchain
+    /// tip, without sending it to the node's message pool, and display
+    /// whether it is valid, with its fee, or why it is rejected
+    Simulate(simulate::Simulate),
 }
 
 type StaticStr = &'static str;
@@ -115,8 +120,8 @@ custom_error! { pub Error
     InfoCalculationFailed { source: chain::value::ValueError } = "calculation of info failed",
     FeeCalculationFailed = "fee calculation failed",
     InfoExpectedSingleAccount = "expected a single account, multisig is not supported yet",
-    MakeWitnessLegacyUtxoUnsupported = "making legacy UTxO witness unsupported",
     MakeWitnessAccountCounterMissing = "making account witness requires passing spending counter",
+    SimulationFailed { source: jcli_app::rest::Error } = "transaction simulation failed",
 }
 
 /*
@@ -142,6 +147,7 @@ impl Transaction {
             Transaction::Info(info) => info.exec(),
             Transaction::MakeWitness(mk_witness) => mk_witness.exec(),
             Transaction::ToMessage(common) => display_message(common),
+            Transaction::Simulate(simulate) => simulate.exec(),
         }
     }
 }
@@ -0,0 +1,53 @@
+use chain_core::property::Serialize as _;
+use jcli_app::rest;
+use jcli_app::transaction::{common, Error};
+use jcli_app::utils::{error::CustomErrorFiller, DebugFlag, HostAddr, OutputFormat, RestApiSender};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct Simulate {
+    #[structopt(flatten)]
+    pub common: common::CommonTransaction,
+    #[structopt(flatten)]
+    addr: HostAddr,
+    #[structopt(flatten)]
+    debug: DebugFlag,
+    #[structopt(flatten)]
+    output_format: OutputFormat,
+}
+
+impl Simulate {
+    pub fn exec(self) -> Result<(), Error> {
+        let message = self.common.load()?.message()?;
+        let bytes =
+            message
+                .serialize_as_vec()
+                .map_err(|source| Error::MessageSerializationFailed {
+                    source,
+                    filler: CustomErrorFiller,
+                })?;
+        simulate(bytes, self.addr, self.debug, self.output_format)
+            .map_err(|source| Error::SimulationFailed { source })
+    }
+}
+
+fn simulate(
+    message: Vec<u8>,
+    addr: HostAddr,
+    debug: DebugFlag,
+    output_format: OutputFormat,
+) -> Result<(), rest::Error> {
+    let url = addr
+        .with_segments(&["v0", "fragment", "simulate"])?
+        .into_url();
+    let builder = reqwest::Client::new().post(url);
+    let response = RestApiSender::new(builder, &debug)
+        .with_binary_body(message)
+        .send()?;
+    response.ok_response()?;
+    let simulation = response.body().json_value()?;
+    let formatted = output_format.format_json(simulation)?;
+    println!("{}", formatted);
+    Ok(())
+}
@@ -8,6 +8,7 @@ pub mod key_parser;
 pub mod open_api_verifier;
 pub mod output_format;
 pub mod rest_api;
+mod state_at;
 
 pub use self::account_id::AccountId;
 pub use self::debug_flag::DebugFlag;
@@ -16,6 +17,7 @@ pub use self::host_addr::HostAddr;
 pub use self::open_api_verifier::OpenApiVerifier;
 pub use self::output_format::OutputFormat;
 pub use self::rest_api::{RestApiResponse, RestApiResponseBody, RestApiSender};
+pub use self::state_at::StateAt;
 use bech32::Bech32;
 use structopt::StructOpt;
 
@@ -0,0 +1,23 @@
+use reqwest::RequestBuilder;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct StateAt {
+    /// read the state of the ledger after the given block (hex-encoded ID)
+    /// instead of the tip
+    #[structopt(long = "block", conflicts_with = "chain_length")]
+    block: Option<String>,
+
+    /// read the state of the ledger after the block at the given chain
+    /// length in the chain of the tip, instead of the tip
+    #[structopt(long = "chain-length")]
+    chain_length: Option<u32>,
+}
+
+impl StateAt {
+    pub fn query(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder
+            .query(&[("block", &self.block)])
+            .query(&[("chain_length", self.chain_length)])
+    }
+}
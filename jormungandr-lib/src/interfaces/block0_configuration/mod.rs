@@ -17,6 +17,7 @@ pub use self::kes_update_speed::KESUpdateSpeed;
 pub use self::leader_id::ConsensusLeaderId;
 pub use self::number_of_slots_per_epoch::NumberOfSlotsPerEpoch;
 pub use self::slots_duration::SlotDuration;
+use crate::crypto::hash::Hash;
 use chain_core::property::HasFragments as _;
 use chain_impl_mockchain::{
     block::{Block, BlockBuilder},
@@ -44,6 +45,14 @@ pub struct Block0Configuration {
     /// * initial certificates (delegation, stake pool...)
     #[serde(default)]
     pub initial: Vec<Initial>,
+
+    /// the hash of the last block of the legacy chain the legacy funds
+    /// of the initial fragments have been imported from.
+    ///
+    /// The block 0 has no parameter for it yet: it is kept in the genesis
+    /// file only, it is not part of the block 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_last_block_hash: Option<Hash>,
 }
 
 custom_error! {pub Block0ConfigurationError
@@ -64,6 +73,7 @@ impl Block0Configuration {
         Ok(Block0Configuration {
             blockchain_configuration,
             initial: initial_fragment::try_initials_vec_from_messages(messages)?,
+            legacy_last_block_hash: None,
         })
     }
 
@@ -128,6 +138,7 @@ mod test {
                 initial: std::iter::repeat_with(|| Arbitrary::arbitrary(g))
                     .take(number_initial)
                     .collect(),
+                legacy_last_block_hash: None,
             }
         }
     }
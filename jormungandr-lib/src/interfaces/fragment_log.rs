@@ -39,6 +39,26 @@ pub struct FragmentLog {
     status: FragmentStatus,
 }
 
+/// the result of the submission of a fragment to the fragment pool
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SubmissionStatus {
+    /// the fragment has been added in the pool and propagated to the network
+    Accepted,
+    /// the fragment has already been received, its log has the status of
+    /// its first submission
+    Duplicate,
+    /// the fragment has been rejected and won't be added in a block
+    Rejected { reason: String },
+}
+
+/// the result of the submission of a fragment, given back to the client once
+/// the fragment has been processed by the fragment pool
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FragmentSubmission {
+    fragment_id: Hash,
+    status: SubmissionStatus,
+}
+
 impl FragmentStatus {
     #[inline]
     pub fn is_pending(&self) -> bool {
@@ -124,3 +144,39 @@ impl FragmentLog {
         &self.status
     }
 }
+
+impl SubmissionStatus {
+    #[inline]
+    pub fn is_accepted(&self) -> bool {
+        self == &SubmissionStatus::Accepted
+    }
+
+    #[inline]
+    pub fn is_rejected(&self) -> bool {
+        if let SubmissionStatus::Rejected { .. } = &self {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl FragmentSubmission {
+    #[inline]
+    pub fn new(fragment_id: key::Hash, status: SubmissionStatus) -> Self {
+        FragmentSubmission {
+            fragment_id: fragment_id.into(),
+            status,
+        }
+    }
+
+    #[inline]
+    pub fn fragment_id(&self) -> &Hash {
+        &self.fragment_id
+    }
+
+    #[inline]
+    pub fn status(&self) -> &SubmissionStatus {
+        &self.status
+    }
+}
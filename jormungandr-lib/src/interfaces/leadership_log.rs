@@ -1,6 +1,7 @@
-use crate::{interfaces::BlockDate, time::SystemTime};
+use crate::{crypto::hash::Hash, interfaces::BlockDate, time::SystemTime};
+use chain_impl_mockchain::key;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{collections::BTreeMap, fmt};
 
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
 #[serde(transparent)]
@@ -11,6 +12,22 @@ pub struct EnclaveLeaderId(u32);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LeadershipLogId(EnclaveLeaderId, BlockDate);
 
+/// outcome of a leadership event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeadershipLogStatus {
+    /// the leadership event is yet to be executed or is being executed
+    Pending,
+    /// the block has been created but it is not yet part of the main branch
+    Produced { block_id: Hash },
+    /// the block is part of the main branch of the node
+    Adopted { block_id: Hash },
+    /// a competing branch has been preferred, the block is not part
+    /// of the main branch of the node
+    Orphaned { block_id: Hash },
+    /// the block could not be created
+    Failed { reason: String },
+}
+
 /// provides information regarding events in the leadership schedule
 ///
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +38,38 @@ pub struct LeadershipLog {
     wake_at_time: Option<SystemTime>,
     finished_at_time: Option<SystemTime>,
     enclave_leader_id: EnclaveLeaderId,
+    #[serde(default)]
+    status: LeadershipLogStatus,
+}
+
+/// summary of the outcomes of the leadership events of an epoch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeadershipEpochSummary {
+    epoch: u32,
+    scheduled: u32,
+    pending: u32,
+    produced: u32,
+    adopted: u32,
+    orphaned: u32,
+    failed: u32,
+}
+
+impl LeadershipLogStatus {
+    /// the identifier of the block created for the leadership event, if any
+    pub fn block_id(&self) -> Option<&Hash> {
+        match self {
+            LeadershipLogStatus::Produced { block_id }
+            | LeadershipLogStatus::Adopted { block_id }
+            | LeadershipLogStatus::Orphaned { block_id } => Some(block_id),
+            LeadershipLogStatus::Pending | LeadershipLogStatus::Failed { .. } => None,
+        }
+    }
+}
+
+impl Default for LeadershipLogStatus {
+    fn default() -> Self {
+        LeadershipLogStatus::Pending
+    }
 }
 
 impl EnclaveLeaderId {
@@ -46,6 +95,7 @@ impl LeadershipLog {
             wake_at_time: None,
             finished_at_time: None,
             enclave_leader_id,
+            status: LeadershipLogStatus::Pending,
         }
     }
 
@@ -72,6 +122,9 @@ impl LeadershipLog {
     pub fn enclave_leader_id(&self) -> &EnclaveLeaderId {
         &self.enclave_leader_id
     }
+    pub fn status(&self) -> &LeadershipLogStatus {
+        &self.status
+    }
 
     /// make a leadership event as triggered.
     ///
@@ -101,6 +154,77 @@ impl LeadershipLog {
         debug_assert!(self.finished_at_time.is_none());
         self.finished_at_time = Some(SystemTime::now())
     }
+
+    /// mark the block of the leadership event as created
+    pub fn mark_produced(&mut self, block_id: key::Hash) {
+        self.status = LeadershipLogStatus::Produced {
+            block_id: block_id.into(),
+        }
+    }
+
+    /// mark the leadership event as failed to create a block
+    pub fn mark_failed(&mut self, reason: String) {
+        self.status = LeadershipLogStatus::Failed { reason }
+    }
+
+    /// set the new status, returns `false` if the status was already set
+    pub fn modify(&mut self, new_status: LeadershipLogStatus) -> bool {
+        if self.status == new_status {
+            false
+        } else {
+            self.status = new_status;
+            true
+        }
+    }
+}
+
+impl LeadershipEpochSummary {
+    /// count the outcomes of the given logs, grouped by epoch in
+    /// increasing order.
+    pub fn summarize<'a, I>(logs: I) -> Vec<Self>
+    where
+        I: IntoIterator<Item = &'a LeadershipLog>,
+    {
+        let mut summaries = BTreeMap::new();
+        for log in logs {
+            let epoch = log.scheduled_at_date().as_ref().epoch;
+            let summary = summaries.entry(epoch).or_insert_with(|| LeadershipEpochSummary {
+                epoch,
+                ..LeadershipEpochSummary::default()
+            });
+            summary.scheduled += 1;
+            match log.status() {
+                LeadershipLogStatus::Pending => summary.pending += 1,
+                LeadershipLogStatus::Produced { .. } => summary.produced += 1,
+                LeadershipLogStatus::Adopted { .. } => summary.adopted += 1,
+                LeadershipLogStatus::Orphaned { .. } => summary.orphaned += 1,
+                LeadershipLogStatus::Failed { .. } => summary.failed += 1,
+            }
+        }
+        summaries.into_iter().map(|(_, summary)| summary).collect()
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+    pub fn scheduled(&self) -> u32 {
+        self.scheduled
+    }
+    pub fn pending(&self) -> u32 {
+        self.pending
+    }
+    pub fn produced(&self) -> u32 {
+        self.produced
+    }
+    pub fn adopted(&self) -> u32 {
+        self.adopted
+    }
+    pub fn orphaned(&self) -> u32 {
+        self.orphaned
+    }
+    pub fn failed(&self) -> u32 {
+        self.failed
+    }
 }
 
 impl fmt::Display for EnclaveLeaderId {
@@ -108,3 +232,72 @@ impl fmt::Display for EnclaveLeaderId {
         self.0.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chain_impl_mockchain::block;
+
+    const BLOCK_ID: &str = "9a4b0e1d6c0b1c2a7d2e63ae9e1a3b7e4e2d5c8b7a6f5e4d3c2b1a0f9e8d7c6b";
+
+    fn log(epoch: u32, slot_id: u32, status: LeadershipLogStatus) -> LeadershipLog {
+        let mut log = LeadershipLog::new(
+            EnclaveLeaderId::new().next(),
+            block::BlockDate { epoch, slot_id }.into(),
+            SystemTime::now(),
+        );
+        log.modify(status);
+        log
+    }
+
+    #[test]
+    fn summarize_by_epoch() {
+        let block_id: Hash = BLOCK_ID.parse().unwrap();
+        let logs = vec![
+            log(2, 10, LeadershipLogStatus::Adopted { block_id }),
+            log(1, 4, LeadershipLogStatus::Orphaned { block_id }),
+            log(1, 2, LeadershipLogStatus::Adopted { block_id }),
+            log(2, 12, LeadershipLogStatus::Pending),
+            log(
+                2,
+                11,
+                LeadershipLogStatus::Failed {
+                    reason: "fragment selection failed".to_owned(),
+                },
+            ),
+        ];
+
+        let summaries = LeadershipEpochSummary::summarize(&logs);
+
+        assert_eq!(
+            summaries,
+            vec![
+                LeadershipEpochSummary {
+                    epoch: 1,
+                    scheduled: 2,
+                    adopted: 1,
+                    orphaned: 1,
+                    ..LeadershipEpochSummary::default()
+                },
+                LeadershipEpochSummary {
+                    epoch: 2,
+                    scheduled: 3,
+                    pending: 1,
+                    adopted: 1,
+                    failed: 1,
+                    ..LeadershipEpochSummary::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn status_is_modified_once() {
+        let block_id: Hash = BLOCK_ID.parse().unwrap();
+        let mut log = log(0, 1, LeadershipLogStatus::Produced { block_id });
+
+        assert!(log.modify(LeadershipLogStatus::Adopted { block_id }));
+        assert!(!log.modify(LeadershipLogStatus::Adopted { block_id }));
+        assert_eq!(log.status().block_id(), Some(&block_id));
+    }
+}
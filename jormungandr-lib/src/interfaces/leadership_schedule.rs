@@ -0,0 +1,39 @@
+use crate::{
+    interfaces::{BlockDate, EnclaveLeaderId},
+    time::SystemTime,
+};
+use serde::{Deserialize, Serialize};
+
+/// a slot one of the leaders of the enclave is elected to create
+/// a block for.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderSlot {
+    enclave_leader_id: EnclaveLeaderId,
+    scheduled_at_date: BlockDate,
+    scheduled_at_time: SystemTime,
+}
+
+impl LeaderSlot {
+    pub fn new(
+        enclave_leader_id: EnclaveLeaderId,
+        scheduled_at_date: BlockDate,
+        scheduled_at_time: SystemTime,
+    ) -> Self {
+        LeaderSlot {
+            enclave_leader_id,
+            scheduled_at_date,
+            scheduled_at_time,
+        }
+    }
+
+    pub fn enclave_leader_id(&self) -> &EnclaveLeaderId {
+        &self.enclave_leader_id
+    }
+    pub fn scheduled_at_date(&self) -> &BlockDate {
+        &self.scheduled_at_date
+    }
+    pub fn scheduled_at_time(&self) -> &SystemTime {
+        &self.scheduled_at_time
+    }
+}
@@ -5,6 +5,7 @@ mod blockdate;
 mod certificate;
 mod fragment_log;
 mod leadership_log;
+mod leadership_schedule;
 mod linear_fee;
 mod old_address;
 mod settings;
@@ -20,12 +21,17 @@ pub use self::blockdate::BlockDate;
 pub use self::certificate::{
     Certificate, CertificateFromBech32Error, CertificateFromStrError, CertificateToBech32Error,
 };
-pub use self::fragment_log::{FragmentLog, FragmentOrigin, FragmentStatus};
-pub use self::leadership_log::{EnclaveLeaderId, LeadershipLog, LeadershipLogId};
+pub use self::fragment_log::{
+    FragmentLog, FragmentOrigin, FragmentStatus, FragmentSubmission, SubmissionStatus,
+};
+pub use self::leadership_log::{
+    EnclaveLeaderId, LeadershipEpochSummary, LeadershipLog, LeadershipLogId, LeadershipLogStatus,
+};
+pub use self::leadership_schedule::LeaderSlot;
 pub use self::linear_fee::LinearFeeDef;
 pub use self::old_address::OldAddress;
 pub use self::settings::*;
 pub use self::transaction_output::TransactionOutput;
 pub use self::transaction_witness::TransactionWitness;
-pub use self::utxo_info::UTxOInfo;
+pub use self::utxo_info::{LegacyUTxOInfo, UTxOInfo};
 pub use self::value::Value;
@@ -1,6 +1,6 @@
 use crate::{
     crypto::hash::Hash,
-    interfaces::{Address, Value},
+    interfaces::{Address, OldAddress, Value},
 };
 use chain_impl_mockchain::utxo::Entry;
 use serde::{Deserialize, Serialize};
@@ -23,6 +23,21 @@ pub struct UTxOInfo {
 }
 
 impl UTxOInfo {
+    #[inline]
+    pub fn new(
+        transaction_id: Hash,
+        index_in_transaction: u8,
+        address: Address,
+        associated_fund: Value,
+    ) -> Self {
+        UTxOInfo {
+            transaction_id,
+            index_in_transaction,
+            address,
+            associated_fund,
+        }
+    }
+
     /// the Transaction identifier (its hash) that will be used to reference
     /// to this UTxO as an input in a new transaction.
     ///
@@ -55,6 +70,58 @@ impl UTxOInfo {
     }
 }
 
+/// the information about a UTxO of a legacy address (declared in the
+/// block 0 from the funds of the legacy chain).
+///
+/// Like the [`UTxOInfo`] the `transaction_id` and the `index_in_transaction`
+/// are the ones to use to refer to this UTxO as an input in a new transaction.
+///
+/// [`UTxOInfo`]: ./struct.UTxOInfo.html
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LegacyUTxOInfo {
+    transaction_id: Hash,
+    index_in_transaction: u8,
+    address: OldAddress,
+    associated_fund: Value,
+}
+
+impl LegacyUTxOInfo {
+    #[inline]
+    pub fn new(
+        transaction_id: Hash,
+        index_in_transaction: u8,
+        address: OldAddress,
+        associated_fund: Value,
+    ) -> Self {
+        LegacyUTxOInfo {
+            transaction_id,
+            index_in_transaction,
+            address,
+            associated_fund,
+        }
+    }
+
+    #[inline]
+    pub fn transaction_id(&self) -> &Hash {
+        &self.transaction_id
+    }
+
+    #[inline]
+    pub fn index_in_transaction(&self) -> u8 {
+        self.index_in_transaction
+    }
+
+    #[inline]
+    pub fn address(&self) -> &OldAddress {
+        &self.address
+    }
+
+    #[inline]
+    pub fn associated_fund(&self) -> &Value {
+        &self.associated_fund
+    }
+}
+
 /* ---------------- Conversion --------------------------------------------- */
 
 impl<'a> From<Entry<'a, chain_addr::Address>> for UTxOInfo {
@@ -3,16 +3,22 @@ extern crate error_chain;
 #[macro_use(lazy_static)]
 extern crate lazy_static;
 
+pub mod load;
 pub mod node;
 mod programs;
+pub mod proxy;
 #[macro_use]
 pub mod scenario;
 mod slog;
 pub mod style;
 mod wallet;
 
+pub use self::load::{LoadGenerator, LoadReport};
 pub use self::node::{MemPoolCheck, Node, NodeBlock0, NodeController, Status};
 pub use self::programs::prepare_command;
-pub use self::scenario::{Context, Controller, NodeAlias, Seed, WalletAlias, WalletType};
+pub use self::proxy::{Fault, LinkController, Proxy};
+pub use self::scenario::{
+    Context, Controller, NodeAlias, Report, ScenarioFile, Seed, WalletAlias, WalletType,
+};
 pub use self::slog::{Error as SlogCodecError, SlogCodec};
 pub use self::wallet::Wallet;
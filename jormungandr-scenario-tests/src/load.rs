@@ -0,0 +1,336 @@
+//! load generator submitting transactions to the nodes of a scenario
+//!
+//! The transactions of every wallet are signed before the load starts so the
+//! submission rate is not limited by the signing. A wallet has only one
+//! transaction pending in the mempool at a time (the spending counter of an
+//! account or the change of a UTxO is only known to the ledger once the
+//! transaction is in a block): the next transaction of a wallet is submitted
+//! once the previous one has been added in a block. The throughput is bounded
+//! by the number of wallets, use enough wallets to sustain the target rate.
+//!
+//! Every submitted transaction is tracked through the fragment logs of the
+//! node it has been submitted to, the confirmation latency is measured by the
+//! node: from the reception of the fragment to its addition in a block.
+
+use crate::{node, wallet, NodeController, Wallet};
+use chain_addr::Discrimination;
+use chain_impl_mockchain::{
+    fee::LinearFee,
+    fragment::{Fragment, FragmentId},
+};
+use jormungandr_lib::{
+    crypto::hash::Hash,
+    interfaces::{Address, FragmentLog, FragmentStatus, Value},
+};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::{self, Display, Formatter},
+    time::{Duration, Instant},
+};
+
+error_chain! {
+    links {
+        Node(node::Error, node::ErrorKind);
+        Wallet(wallet::Error, wallet::ErrorKind);
+    }
+
+    errors {
+        NoNodes {
+            description("no node to submit the transactions to"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// the target number of transactions submitted per second
+    pub rate: u32,
+    /// the number of transactions signed for every wallet
+    pub transactions_per_wallet: u32,
+    /// the value sent by every transaction
+    pub value: Value,
+    /// the time given to a transaction to be added in a block
+    pub timeout: Duration,
+    /// the interval between two queries of the fragment logs of the nodes
+    pub poll_interval: Duration,
+}
+
+struct Sender {
+    /// the transactions signed in advance, in the order of submission
+    transactions: VecDeque<Fragment>,
+    /// whether a transaction of the wallet is pending in a mempool
+    pending: bool,
+}
+
+struct InFlight {
+    sender: usize,
+    node: usize,
+    submitted_at: Instant,
+}
+
+/// submits the transactions of the wallets to the nodes at the configured
+/// rate, round robin
+pub struct LoadGenerator {
+    config: Config,
+    nodes: Vec<NodeController>,
+    senders: Vec<Sender>,
+}
+
+/// the statistics of a load
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub submitted: usize,
+    pub confirmed: usize,
+    pub timed_out: usize,
+    /// the number of rejected transactions by reason
+    pub rejections: BTreeMap<String, usize>,
+    /// the transactions not submitted because a previous transaction of
+    /// their wallet has not been added in a block
+    pub abandoned: usize,
+    pub duration: Duration,
+    /// the confirmation latencies, sorted
+    latencies: Vec<Duration>,
+}
+
+impl LoadGenerator {
+    /// sign the transactions of the given wallets, sending the configured
+    /// value to the given address. The UTxOs of the wallets are retrieved
+    /// from the first node.
+    pub fn new(
+        config: Config,
+        block0_hash: &Hash,
+        fees: &LinearFee,
+        wallets: Vec<Wallet>,
+        address: Address,
+        nodes: Vec<NodeController>,
+    ) -> Result<Self> {
+        let utxos = match nodes.first() {
+            None => bail!(ErrorKind::NoNodes),
+            Some(node) => node.utxos()?,
+        };
+
+        let mut senders = Vec::with_capacity(wallets.len());
+        for mut wallet in wallets {
+            wallet.sync_utxos(Discrimination::Test, &utxos);
+
+            let mut transactions = VecDeque::new();
+            for _ in 0..config.transactions_per_wallet {
+                let fragment =
+                    wallet.transaction_to(block0_hash, fees, address.clone(), config.value)?;
+                wallet.confirm_transaction();
+                transactions.push_back(fragment);
+            }
+
+            senders.push(Sender {
+                transactions,
+                pending: false,
+            });
+        }
+
+        Ok(LoadGenerator {
+            config,
+            nodes,
+            senders,
+        })
+    }
+
+    /// submit all the transactions and wait for them to be processed
+    pub fn run(mut self) -> Result<LoadReport> {
+        let interval = Duration::from_secs(1) / self.config.rate.max(1);
+        let mut in_flight = HashMap::new();
+        let mut report = LoadReport::default();
+        let mut next_sender = 0;
+        let mut next_node = 0;
+
+        let started = Instant::now();
+        let mut next_submission = started;
+        let mut next_poll = started + self.config.poll_interval;
+
+        loop {
+            let now = Instant::now();
+            if now >= next_submission {
+                if let Some(index) = self.next_idle_sender(&mut next_sender) {
+                    let sender = &mut self.senders[index];
+                    let fragment = sender
+                        .transactions
+                        .pop_front()
+                        .expect("an idle sender has transactions to submit");
+                    let check = self.nodes[next_node].send_fragment(fragment)?;
+                    sender.pending = true;
+                    in_flight.insert(
+                        check.fragment_id().clone(),
+                        InFlight {
+                            sender: index,
+                            node: next_node,
+                            submitted_at: now,
+                        },
+                    );
+                    report.submitted += 1;
+                    next_node = (next_node + 1) % self.nodes.len();
+                }
+                // do not catch up with the submissions missed while all the
+                // wallets were waiting for their transactions
+                next_submission = (next_submission + interval).max(now);
+            }
+
+            if now >= next_poll {
+                self.poll(&mut in_flight, &mut report)?;
+                next_poll = Instant::now() + self.config.poll_interval;
+            }
+
+            let done = in_flight.is_empty()
+                && self
+                    .senders
+                    .iter()
+                    .all(|sender| sender.transactions.is_empty());
+            if done {
+                break;
+            }
+
+            let wake_up = next_submission.min(next_poll);
+            let now = Instant::now();
+            if wake_up > now {
+                std::thread::sleep(wake_up - now);
+            }
+        }
+
+        report.duration = started.elapsed();
+        report.latencies.sort();
+        Ok(report)
+    }
+
+    /// the next wallet, round robin, with transactions to submit and no
+    /// transaction pending
+    fn next_idle_sender(&self, next_sender: &mut usize) -> Option<usize> {
+        let count = self.senders.len();
+        let index = (0..count)
+            .map(|offset| (*next_sender + offset) % count)
+            .find(|index| {
+                let sender = &self.senders[*index];
+                !sender.pending && !sender.transactions.is_empty()
+            })?;
+        *next_sender = (index + 1) % count;
+        Some(index)
+    }
+
+    fn poll(
+        &mut self,
+        in_flight: &mut HashMap<FragmentId, InFlight>,
+        report: &mut LoadReport,
+    ) -> Result<()> {
+        let timeout = self.config.timeout;
+        let senders = &mut self.senders;
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if !in_flight.values().any(|fragment| fragment.node == index) {
+                continue;
+            }
+            let logs = node.fragment_logs()?;
+
+            in_flight.retain(|fragment_id, fragment| {
+                if fragment.node != index {
+                    return true;
+                }
+                let sender = &mut senders[fragment.sender];
+                match logs.get(fragment_id).map(|log| (log, log.status())) {
+                    Some((log, FragmentStatus::InABlock { .. })) => {
+                        report.confirmed += 1;
+                        report.latencies.push(latency(log));
+                        sender.pending = false;
+                        false
+                    }
+                    Some((_, FragmentStatus::Rejected { reason })) => {
+                        *report.rejections.entry(reason.clone()).or_insert(0) += 1;
+                        report.abandoned += sender.abandon();
+                        false
+                    }
+                    _ if fragment.submitted_at.elapsed() >= timeout => {
+                        report.timed_out += 1;
+                        report.abandoned += sender.abandon();
+                        false
+                    }
+                    _ => true,
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Sender {
+    /// drop the remaining transactions of the wallet, they depend on the
+    /// transaction which did not make it into a block. Returns the number of
+    /// transactions dropped.
+    fn abandon(&mut self) -> usize {
+        let abandoned = self.transactions.len();
+        self.transactions.clear();
+        self.pending = false;
+        abandoned
+    }
+}
+
+/// the time the node took to add the fragment in a block
+fn latency(log: &FragmentLog) -> Duration {
+    log.last_updated_at()
+        .as_ref()
+        .duration_since(*log.received_at().as_ref())
+        .unwrap_or_default()
+}
+
+impl LoadReport {
+    pub fn rejected(&self) -> usize {
+        self.rejections.values().sum()
+    }
+
+    /// the number of transactions added in a block per second
+    pub fn tps(&self) -> f64 {
+        let millis = self.duration.as_millis().max(1) as f64;
+        self.confirmed as f64 * 1000.0 / millis
+    }
+
+    /// the confirmation latency under which are the given percentage of the
+    /// confirmed transactions (nearest rank)
+    pub fn latency_percentile(&self, percentile: u8) -> Option<Duration> {
+        let count = self.latencies.len();
+        if count == 0 {
+            return None;
+        }
+        let rank = (usize::from(percentile.min(100)) * count + 99) / 100;
+        Some(self.latencies[rank.max(1) - 1])
+    }
+}
+
+impl Display for LoadReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "transactions: {} submitted, {} confirmed, {} rejected, {} timed out, {} abandoned",
+            self.submitted,
+            self.confirmed,
+            self.rejected(),
+            self.timed_out,
+            self.abandoned
+        )?;
+        write!(
+            f,
+            "throughput: {:.2} TPS over {}ms",
+            self.tps(),
+            self.duration.as_millis()
+        )?;
+        if let Some(max) = self.latencies.last() {
+            let percentile = |percentile| self.latency_percentile(percentile).unwrap_or_default();
+            write!(
+                f,
+                "\nlatency: p50 {}ms, p90 {}ms, p99 {}ms, max {}ms",
+                percentile(50).as_millis(),
+                percentile(90).as_millis(),
+                percentile(99).as_millis(),
+                max.as_millis()
+            )?;
+        }
+        for (reason, count) in self.rejections.iter() {
+            write!(f, "\nrejected ({}): {}", count, reason)?;
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,13 @@
 #[macro_use]
 extern crate jormungandr_scenario_tests;
 
-use jormungandr_scenario_tests::{prepare_command, style, Context, Seed};
-use std::{path::PathBuf, thread, time::Duration};
+use error_chain::ChainedError as _;
+use jormungandr_scenario_tests::{prepare_command, style, Context, Fault, ScenarioFile, Seed};
+use std::{
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -32,6 +37,11 @@ struct CommandArgs {
     /// to set if to reproduce an existing test
     #[structopt(long = "seed")]
     seed: Option<Seed>,
+
+    /// run the scenario described in the given YAML file instead of the
+    /// built-in scenarios. Can be set multiple times.
+    #[structopt(long = "scenario", parse(from_os_str))]
+    scenarios: Vec<PathBuf>,
 }
 
 fn main() {
@@ -44,6 +54,7 @@ fn main() {
         .unwrap_or_else(|| Seed::generate(rand::rngs::OsRng::new().unwrap()));
     let testing_directory = command_args.testing_directory;
     let generate_documentation = command_args.generate_documentation;
+    let scenarios = command_args.scenarios;
 
     let mut context = Context::new(
         seed,
@@ -55,7 +66,34 @@ fn main() {
 
     introduction(&context);
 
-    scenario_2(context.derive());
+    if scenarios.is_empty() {
+        scenario_2(context.derive());
+        scenario_3(context.derive());
+    } else {
+        let mut success = true;
+        for path in scenarios {
+            let report =
+                ScenarioFile::load(&path).and_then(|scenario| scenario.run(context.derive()));
+            match report {
+                Ok(report) => {
+                    println!("{}", report);
+                    success &= report.is_success();
+                }
+                Err(err) => {
+                    println!(
+                        "{} {:?}: {}",
+                        *style::icons::failure,
+                        path,
+                        style::error.apply_to(err.display_chain())
+                    );
+                    success = false;
+                }
+            }
+        }
+        if !success {
+            std::process::exit(1);
+        }
+    }
 }
 
 fn introduction<R: rand_core::RngCore>(context: &Context<R>) {
@@ -197,3 +235,70 @@ pub fn scenario_2(mut context: Context<ChaChaRng>) {
 
     controller.finalize();
 }
+
+pub fn scenario_3(mut context: Context<ChaChaRng>) {
+    let scenario_settings = prepare_scenario! {
+        "Network partition",
+        &mut context,
+        topology [
+            "Leader1",
+            "Leader2" -> "Leader1",
+            "Passive1" -> "Leader1" -> "Leader2",
+        ]
+        blockchain {
+            consensus = Bft,
+            number_of_slots_per_epoch = 60,
+            slot_duration = 1,
+            leaders = [ "Leader1", "Leader2" ],
+            initials = [
+                account "faucet1" with 1_000_000_000,
+            ],
+        }
+    };
+
+    let mut controller = scenario_settings.build(context).unwrap();
+
+    let leader1 = controller.spawn_node("Leader1", true).unwrap();
+    thread::sleep(Duration::from_secs(1));
+    let leader2 = controller.spawn_node("Leader2", false).unwrap();
+    let passive1 = controller.spawn_node("Passive1", false).unwrap();
+
+    controller.monitor_nodes();
+    thread::sleep(Duration::from_secs(10));
+
+    // isolate the leaders from each other, each side creates its own fork
+    controller.partition(&["Leader1"], &["Leader2", "Passive1"]);
+    thread::sleep(Duration::from_secs(20));
+
+    // reconnect the leaders, with a slow link for the passive node
+    controller.heal();
+    controller
+        .link("Passive1", "Leader1")
+        .unwrap()
+        .set_fault(Fault::Delay(Duration::from_millis(500)));
+
+    // the nodes settle on the same branch once the partition is healed
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        let tips = [
+            leader1.get_tip().unwrap(),
+            leader2.get_tip().unwrap(),
+            passive1.get_tip().unwrap(),
+        ];
+        if tips.iter().all(|tip| *tip == tips[0]) {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "the tips did not converge after the partition: {:?}",
+            tips
+        );
+        thread::sleep(Duration::from_secs(1));
+    }
+
+    leader1.shutdown().unwrap();
+    leader2.shutdown().unwrap();
+    passive1.shutdown().unwrap();
+
+    controller.finalize();
+}
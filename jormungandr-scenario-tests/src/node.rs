@@ -5,7 +5,7 @@ use chain_impl_mockchain::{
     fragment::{Fragment, FragmentId},
 };
 use indicatif::ProgressBar;
-use jormungandr_lib::interfaces::{FragmentLog, FragmentStatus};
+use jormungandr_lib::interfaces::{FragmentLog, FragmentStatus, UTxOInfo};
 use rand_core::RngCore;
 use std::{
     collections::HashMap,
@@ -42,6 +42,9 @@ error_chain! {
         InvalidFragmentLogs {
             description("Fragment logs in an invalid format")
         }
+        InvalidUTxOs {
+            description("UTxOs in an invalid format")
+        }
 
         NodeStopped (status: Status) {
             description("the node is no longer running"),
@@ -58,6 +61,12 @@ pub struct MemPoolCheck {
     fragment_id: FragmentId,
 }
 
+impl MemPoolCheck {
+    pub fn fragment_id(&self) -> &FragmentId {
+        &self.fragment_id
+    }
+}
+
 pub enum NodeBlock0 {
     Hash(HeaderHash),
     File(PathBuf),
@@ -228,6 +237,18 @@ impl NodeController {
         Ok(logs)
     }
 
+    pub fn utxos(&self) -> Result<Vec<UTxOInfo>> {
+        let utxos = self.get("utxo")?.text()?;
+
+        let utxos: Vec<UTxOInfo> =
+            serde_json::from_str(&utxos).chain_err(|| ErrorKind::InvalidUTxOs)?;
+
+        self.progress_bar
+            .log_info(format!("utxos ({})", utxos.len()));
+
+        Ok(utxos)
+    }
+
     pub fn wait_fragment(&self, duration: Duration, check: MemPoolCheck) -> Result<FragmentStatus> {
         loop {
             let logs = self.fragment_logs()?;
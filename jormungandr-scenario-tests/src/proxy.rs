@@ -0,0 +1,220 @@
+//! TCP relay sitting on the links between the nodes of a scenario
+//!
+//! Every trusted peer link of the topology goes through a proxy: the node
+//! is given the address of the proxy instead of the address of its trusted
+//! peer. The proxy relays the connections to the peer and can inject faults
+//! at runtime (drop, delay or throttle the traffic).
+//!
+//! The nodes of the scenarios run on private addresses, they do not connect
+//! to the nodes received in the gossips: all the traffic between two nodes
+//! goes through the links of the topology.
+
+use crate::{scenario::settings::LinkSetting, NodeAlias};
+use bytes::Bytes;
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    codec::{BytesCodec, FramedRead, FramedWrite},
+    net::{TcpListener, TcpStream},
+    prelude::*,
+    sync::oneshot,
+    timer::Delay,
+};
+
+error_chain! {
+    foreign_links {
+        Io(std::io::Error);
+    }
+}
+
+/// the fault injected on a link
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// relay the traffic untouched
+    None,
+    /// close the connections of the link and refuse the new ones
+    Drop,
+    /// delay every chunk of traffic by the given duration
+    Delay(Duration),
+    /// limit the traffic to the given number of bytes per second
+    Throttle(u64),
+}
+
+struct LinkState {
+    fault: Fault,
+    /// dropping a sender closes the corresponding relayed connection
+    connections: Vec<oneshot::Sender<()>>,
+    /// dropping the sender stops the proxy
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+/// relays the connections of a link, to be spawned on the runtime of the
+/// `Controller`.
+///
+/// To inject faults on the link, use the `LinkController`
+pub struct Proxy {
+    link: LinkSetting,
+    listener: TcpListener,
+    shutdown: oneshot::Receiver<()>,
+    state: Arc<Mutex<LinkState>>,
+}
+
+/// inject faults on a running link
+#[derive(Clone)]
+pub struct LinkController {
+    from: NodeAlias,
+    to: NodeAlias,
+    state: Arc<Mutex<LinkState>>,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Fault::None => write!(f, "none"),
+            Fault::Drop => write!(f, "drop"),
+            Fault::Delay(duration) => write!(f, "delay {}ms", duration.as_millis()),
+            Fault::Throttle(rate) => write!(f, "throttle {}B/s", rate),
+        }
+    }
+}
+
+impl Fault {
+    /// the time to hold a chunk of the given size before relaying it
+    fn hold(&self, size: usize) -> Option<Duration> {
+        match self {
+            Fault::None | Fault::Drop => None,
+            Fault::Delay(duration) => Some(*duration),
+            Fault::Throttle(rate) => Some(Duration::from_micros(
+                size as u64 * 1_000_000 / (*rate).max(1),
+            )),
+        }
+    }
+}
+
+impl LinkController {
+    pub fn from(&self) -> &NodeAlias {
+        &self.from
+    }
+
+    pub fn to(&self) -> &NodeAlias {
+        &self.to
+    }
+
+    pub fn fault(&self) -> Fault {
+        self.state.lock().unwrap().fault
+    }
+
+    /// set the fault injected on the link, setting `Fault::Drop` closes the
+    /// connections currently relayed.
+    pub fn set_fault(&self, fault: Fault) {
+        let mut state = self.state.lock().unwrap();
+        state.fault = fault;
+        if fault == Fault::Drop {
+            state.connections.clear();
+        }
+    }
+
+    /// stop the proxy, closing all the connections of the link
+    pub fn shutdown(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.connections.clear();
+        state.shutdown = None;
+    }
+}
+
+impl Proxy {
+    pub fn bind(link: &LinkSetting) -> Result<Self> {
+        let listener = TcpListener::bind(&link.proxy)
+            .chain_err(|| format!("cannot listen on {} for the link {}", link.proxy, link))?;
+        let (shutdown_sender, shutdown) = oneshot::channel();
+
+        Ok(Proxy {
+            link: link.clone(),
+            listener,
+            shutdown,
+            state: Arc::new(Mutex::new(LinkState {
+                fault: Fault::None,
+                connections: Vec::new(),
+                shutdown: Some(shutdown_sender),
+            })),
+        })
+    }
+
+    pub fn controller(&self) -> LinkController {
+        LinkController {
+            from: self.link.from.clone(),
+            to: self.link.to.clone(),
+            state: Arc::clone(&self.state),
+        }
+    }
+
+    /// accept and relay the connections until the proxy is shut down
+    pub fn relay(self) -> impl Future<Item = (), Error = ()> {
+        let target = self.link.target;
+        let state = self.state;
+        let shutdown = self.shutdown.then(|_| Ok(()));
+
+        self.listener
+            .incoming()
+            .map_err(|_| ())
+            .for_each(move |inbound| {
+                let mut link_state = state.lock().unwrap();
+                if link_state.fault == Fault::Drop {
+                    return Ok(());
+                }
+                let (close, closed) = oneshot::channel();
+                link_state.connections.push(close);
+
+                let state = Arc::clone(&state);
+                let connection = TcpStream::connect(&target)
+                    .map_err(|_| ())
+                    .and_then(move |outbound| {
+                        let (inbound_reader, inbound_writer) = inbound.split();
+                        let (outbound_reader, outbound_writer) = outbound.split();
+                        let upstream = relay(inbound_reader, outbound_writer, Arc::clone(&state));
+                        let downstream = relay(outbound_reader, inbound_writer, state);
+                        upstream.select(downstream).then(|_| Ok(()))
+                    })
+                    .select(closed.then(|_| Ok(())))
+                    .then(|_| Ok(()));
+                tokio::spawn(connection);
+                Ok(())
+            })
+            .select(shutdown)
+            .then(|_| Ok(()))
+    }
+}
+
+/// relay the traffic in one direction, applying the fault of the link
+fn relay<R, W>(
+    reader: R,
+    writer: W,
+    state: Arc<Mutex<LinkState>>,
+) -> impl Future<Item = (), Error = ()>
+where
+    R: AsyncRead,
+    W: AsyncWrite,
+{
+    FramedRead::new(reader, BytesCodec::new())
+        .map_err(|_| ())
+        .and_then(move |chunk| {
+            let fault = state.lock().unwrap().fault;
+            let chunk: Bytes = chunk.freeze();
+            if fault == Fault::Drop {
+                return future::Either::A(future::err(()));
+            }
+            match fault.hold(chunk.len()) {
+                None => future::Either::A(future::ok(chunk)),
+                Some(duration) => future::Either::B(
+                    Delay::new(Instant::now() + duration)
+                        .map_err(|_| ())
+                        .map(move |()| chunk),
+                ),
+            }
+        })
+        .forward(FramedWrite::new(writer, BytesCodec::new()).sink_map_err(|_| ()))
+        .map(|_| ())
+}
@@ -36,6 +36,7 @@ pub struct Context<RNG: RngCore + Sized> {
 
     next_available_rest_port_number: Arc<AtomicU16>,
     next_available_grpc_port_number: Arc<AtomicU16>,
+    next_available_proxy_port_number: Arc<AtomicU16>,
 
     testing_directory: TestingDirectory,
     generate_documentation: bool,
@@ -74,6 +75,7 @@ impl Context<ChaChaRng> {
             seed,
             next_available_rest_port_number: Arc::new(AtomicU16::new(8_000)),
             next_available_grpc_port_number: Arc::new(AtomicU16::new(12_000)),
+            next_available_proxy_port_number: Arc::new(AtomicU16::new(16_000)),
             jormungandr,
             jcli,
             testing_directory,
@@ -92,6 +94,7 @@ impl Context<ChaChaRng> {
             seed,
             next_available_rest_port_number: Arc::clone(&self.next_available_rest_port_number),
             next_available_grpc_port_number: Arc::clone(&self.next_available_grpc_port_number),
+            next_available_proxy_port_number: Arc::clone(&self.next_available_proxy_port_number),
             jormungandr: self.jormungandr().clone(),
             jcli: self.jcli().clone(),
             testing_directory: self.testing_directory.clone(),
@@ -144,6 +147,15 @@ impl<RNG: RngCore> Context<RNG> {
             .unwrap()
     }
 
+    pub fn generate_new_proxy_listen_address(&mut self) -> SocketAddr {
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let port_number = self
+            .next_available_proxy_port_number
+            .fetch_add(1, atomic::Ordering::SeqCst);
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port_number)
+    }
+
     /// retrieve the original seed of the pseudo random generator
     #[inline]
     pub fn seed(&self) -> &Seed {
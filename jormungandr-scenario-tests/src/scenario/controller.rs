@@ -1,11 +1,14 @@
 use crate::{
+    load,
     scenario::{settings::Settings, Blockchain, ContextChaCha, ErrorKind, Result, Topology},
-    style, MemPoolCheck, Node, NodeBlock0, NodeController, Wallet,
+    style, Fault, LinkController, LoadGenerator, MemPoolCheck, Node, NodeAlias, NodeBlock0,
+    NodeController, Proxy, Wallet,
 };
 use chain_impl_mockchain::block::HeaderHash;
 use indicatif::{MultiProgress, ProgressBar};
 use jormungandr_lib::interfaces::Value;
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -34,6 +37,8 @@ pub struct Controller {
     progress_bar: Arc<MultiProgress>,
     progress_bar_thread: Option<std::thread::JoinHandle<()>>,
 
+    links: HashMap<(NodeAlias, NodeAlias), LinkController>,
+
     runtime: runtime::Runtime,
 }
 
@@ -128,6 +133,14 @@ impl Controller {
         block0.serialize(file)?;
         let progress_bar = Arc::new(MultiProgress::new());
 
+        let runtime = runtime::Runtime::new()?;
+        let mut links = HashMap::new();
+        for link in settings.links.iter() {
+            let proxy = Proxy::bind(link)?;
+            links.insert((link.from.clone(), link.to.clone()), proxy.controller());
+            runtime.executor().spawn(proxy.relay());
+        }
+
         Ok(Controller {
             settings: settings,
             context,
@@ -135,11 +148,17 @@ impl Controller {
             block0_hash,
             progress_bar,
             progress_bar_thread: None,
-            runtime: runtime::Runtime::new()?,
+            links,
+            runtime,
             working_directory,
         })
     }
 
+    /// whether the wallet has not been taken yet
+    pub fn has_wallet(&self, wallet: &str) -> bool {
+        self.settings.wallets.contains_key(wallet)
+    }
+
     pub fn wallet(&mut self, wallet: &str) -> Result<Wallet> {
         if let Some(wallet) = self.settings.wallets.remove(wallet) {
             Ok(wallet)
@@ -180,6 +199,35 @@ impl Controller {
         Ok(controller)
     }
 
+    /// the link from a node to one of its trusted peers
+    pub fn link(&self, from: &str, to: &str) -> Result<LinkController> {
+        if let Some(link) = self.links.get(&(from.to_owned(), to.to_owned())) {
+            Ok(link.clone())
+        } else {
+            Err(ErrorKind::LinkNotFound(from.to_owned(), to.to_owned()).into())
+        }
+    }
+
+    /// drop the traffic of all the links between the two groups of nodes
+    pub fn partition(&self, group1: &[&str], group2: &[&str]) {
+        for ((from, to), link) in self.links.iter() {
+            let from = from.as_str();
+            let to = to.as_str();
+            if (group1.contains(&from) && group2.contains(&to))
+                || (group2.contains(&from) && group1.contains(&to))
+            {
+                link.set_fault(Fault::Drop);
+            }
+        }
+    }
+
+    /// remove the faults injected on all the links
+    pub fn heal(&self) {
+        for link in self.links.values() {
+            link.set_fault(Fault::None);
+        }
+    }
+
     pub fn monitor_nodes(&mut self) {
         let pb = Arc::clone(&self.progress_bar);
         self.progress_bar_thread = Some(std::thread::spawn(move || {
@@ -188,6 +236,9 @@ impl Controller {
     }
 
     pub fn finalize(self) {
+        for link in self.links.values() {
+            link.shutdown();
+        }
         self.runtime.shutdown_on_idle().wait().unwrap(); //.shutdown_now().wait().unwrap();
         if let Some(thread) = self.progress_bar_thread {
             thread.join().unwrap()
@@ -209,4 +260,27 @@ impl Controller {
 
         Ok(via.send_fragment(fragment)?)
     }
+
+    /// sign the transactions of the load from the given wallets to the
+    /// receiver, to be submitted to the given nodes
+    pub fn load_generator(
+        &self,
+        config: load::Config,
+        senders: Vec<Wallet>,
+        receiver: &Wallet,
+        nodes: Vec<NodeController>,
+    ) -> Result<LoadGenerator> {
+        let block0_hash = &self.block0_hash;
+        let fees = &self.settings.block0.blockchain_configuration.linear_fees;
+        let address = receiver.address(chain_addr::Discrimination::Test);
+
+        Ok(LoadGenerator::new(
+            config,
+            &block0_hash.clone().into(),
+            fees,
+            senders,
+            address,
+            nodes,
+        )?)
+    }
 }
@@ -0,0 +1,847 @@
+//! scenarios described in YAML files
+//!
+//! A scenario file describes the topology, the blockchain parameters and the
+//! wallets of the scenario (the same as `prepare_scenario!`) and the timeline
+//! of the steps to execute:
+//!
+//! ```yaml
+//! title: "simple network example"
+//! topology:
+//!   - alias: node1
+//!   - alias: node2
+//!     trusted_peers: [ node1 ]
+//! blockchain:
+//!   consensus: bft
+//!   number_of_slots_per_epoch: 10
+//!   slot_duration: 1
+//!   leaders: [ node1 ]
+//!   initials:
+//!     - alias: faucet1
+//!       funds: 1000000000
+//!     - alias: faucet2
+//!       funds: 2000000000
+//!       delegates_to: node2
+//! steps:
+//!   - spawn_node: { node: node1, with_block0: true }
+//!   - spawn_node: { node: node2 }
+//!   - wait: 10s
+//!   - send_transaction: { from: faucet1, to: faucet2, via: node1, value: 5000 }
+//!   - wait_fragment: { node: node1, timeout: 30s }
+//!   - assert_tip_equal: { nodes: [ node1, node2 ], timeout: 10s }
+//!   - shutdown: node1
+//!   - shutdown: node2
+//! ```
+//!
+//! The wallets are accounts unless `wallet_type: utxo` is given, the UTxO
+//! wallets cannot delegate their stake (`delegates_to`).
+//!
+//! The steps are executed in order, the execution stops at the first failing
+//! step. The nodes still running at the end of the scenario are shut down.
+//!
+//! A `load` step submits many transactions from the given wallets and reports
+//! the throughput, the confirmation latencies and the rejection reasons:
+//!
+//! ```yaml
+//!   - load:
+//!       from: [ faucet1, faucet2 ]
+//!       to: faucet3
+//!       via: [ node1, node2 ]
+//!       rate: 10
+//!       transactions_per_wallet: 20
+//!       value: 100
+//!       timeout: 60s
+//! ```
+//!
+//! The wallets sending the transactions of a `load` step cannot be used by
+//! the following steps.
+
+use crate::{
+    load,
+    scenario::{
+        Blockchain, ConsensusVersion, ContextChaCha, Controller, ControllerBuilder, ErrorKind,
+        Node as NodeTemplate, Result, ResultExt as _, TopologyBuilder, Value,
+        Wallet as WalletTemplate,
+    },
+    style, MemPoolCheck, NodeAlias, NodeController, Wallet, WalletAlias, WalletType,
+};
+use chain_addr::Discrimination;
+use jormungandr_lib::{
+    interfaces::{FragmentStatus, NumberOfSlotsPerEpoch, SlotDuration},
+    time::Duration,
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    path::Path,
+    time::Instant,
+};
+
+/// interval between two queries of the fragment logs or the tips of the nodes
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioFile {
+    pub title: String,
+    pub topology: Vec<NodeDescription>,
+    pub blockchain: BlockchainDescription,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeDescription {
+    pub alias: NodeAlias,
+    #[serde(default)]
+    pub trusted_peers: Vec<NodeAlias>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockchainDescription {
+    pub consensus: Consensus,
+    pub number_of_slots_per_epoch: u32,
+    pub slot_duration: u8,
+    #[serde(default)]
+    pub leaders: Vec<NodeAlias>,
+    pub initials: Vec<WalletDescription>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Consensus {
+    Bft,
+    GenesisPraos,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WalletDescription {
+    pub alias: WalletAlias,
+    #[serde(default)]
+    pub wallet_type: WalletKind,
+    pub funds: u64,
+    pub delegates_to: Option<NodeAlias>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletKind {
+    Account,
+    Utxo,
+}
+
+/// a step of the timeline of the scenario
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// start the node, with the block 0 file or with its hash only
+    SpawnNode {
+        node: NodeAlias,
+        #[serde(default)]
+        with_block0: bool,
+    },
+    /// do nothing for the given duration
+    Wait(Duration),
+    /// send a transaction between two wallets through the given node
+    SendTransaction {
+        from: WalletAlias,
+        to: WalletAlias,
+        via: NodeAlias,
+        value: u64,
+    },
+    /// wait for the last transaction sent to leave the mempool of the node
+    WaitFragment {
+        node: NodeAlias,
+        timeout: Duration,
+        #[serde(default)]
+        expect: ExpectedStatus,
+    },
+    /// check the nodes all have the same tip, retrying until the timeout
+    /// (if any) while the blocks propagate
+    AssertTipEqual {
+        nodes: Vec<NodeAlias>,
+        #[serde(default)]
+        timeout: Option<Duration>,
+    },
+    /// drop the traffic of the links between the two groups of nodes
+    Partition {
+        group1: Vec<NodeAlias>,
+        group2: Vec<NodeAlias>,
+    },
+    /// remove the faults injected on the links
+    Heal,
+    /// stop the node
+    Shutdown(NodeAlias),
+    /// submit transactions signed in advance from the wallets to the nodes
+    /// at the given rate (per second), reporting the throughput, the
+    /// latencies and the rejections. The wallets cannot be used afterward.
+    Load {
+        from: Vec<WalletAlias>,
+        to: WalletAlias,
+        via: Vec<NodeAlias>,
+        rate: u32,
+        transactions_per_wallet: u32,
+        value: u64,
+        timeout: Duration,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpectedStatus {
+    InABlock,
+    Rejected,
+}
+
+/// the outcome of every step of a scenario
+#[derive(Debug)]
+pub struct Report {
+    pub title: String,
+    pub steps: Vec<StepReport>,
+}
+
+#[derive(Debug)]
+pub struct StepReport {
+    pub step: String,
+    pub duration: std::time::Duration,
+    pub outcome: Outcome,
+    /// what the step has measured, if anything
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Failure(String),
+    Skipped,
+}
+
+/// the state of a running scenario
+struct Execution {
+    controller: Controller,
+    /// whether the progress of the nodes is displayed
+    monitored: bool,
+    nodes: HashMap<NodeAlias, NodeController>,
+    wallets: HashMap<WalletAlias, Wallet>,
+    /// the wallets used by a `load` step, their state is no longer known
+    consumed: HashSet<WalletAlias>,
+    /// the last transaction sent and the wallet it has been sent from
+    pending: Option<(WalletAlias, MemPoolCheck)>,
+}
+
+impl Default for WalletKind {
+    fn default() -> Self {
+        WalletKind::Account
+    }
+}
+
+impl Default for ExpectedStatus {
+    fn default() -> Self {
+        ExpectedStatus::InABlock
+    }
+}
+
+impl From<Consensus> for ConsensusVersion {
+    fn from(consensus: Consensus) -> Self {
+        match consensus {
+            Consensus::Bft => ConsensusVersion::Bft,
+            Consensus::GenesisPraos => ConsensusVersion::GenesisPraos,
+        }
+    }
+}
+
+impl ScenarioFile {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .chain_err(|| format!("cannot open the scenario file {:?}", path))?;
+        let scenario: ScenarioFile = serde_yaml::from_reader(file)
+            .chain_err(|| format!("cannot parse the scenario file {:?}", path))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// check every alias used in the scenario is defined and the wallets
+    /// are supported
+    fn validate(&self) -> Result<()> {
+        let nodes: HashSet<&NodeAlias> = self.topology.iter().map(|node| &node.alias).collect();
+        let wallets: HashSet<&WalletAlias> = self
+            .blockchain
+            .initials
+            .iter()
+            .map(|wallet| &wallet.alias)
+            .collect();
+
+        let check_node = |alias: &NodeAlias| {
+            if nodes.contains(alias) {
+                Ok(())
+            } else {
+                Err(ErrorKind::InvalidScenario(format!(
+                    "undefined node '{}'",
+                    alias
+                )))
+            }
+        };
+        let check_wallet = |alias: &WalletAlias| {
+            if wallets.contains(alias) {
+                Ok(())
+            } else {
+                Err(ErrorKind::InvalidScenario(format!(
+                    "undefined wallet '{}'",
+                    alias
+                )))
+            }
+        };
+
+        for node in self.topology.iter() {
+            node.trusted_peers.iter().try_for_each(check_node)?;
+        }
+        for wallet in self.blockchain.initials.iter() {
+            wallet.delegates_to.iter().try_for_each(check_node)?;
+            if let (WalletKind::Utxo, Some(_)) = (wallet.wallet_type, &wallet.delegates_to) {
+                bail!(ErrorKind::InvalidScenario(format!(
+                    "UTxO wallet '{}' cannot delegate its stake, only the account wallets can",
+                    wallet.alias
+                )));
+            }
+        }
+        for step in self.steps.iter() {
+            match step {
+                Step::SpawnNode { node, .. }
+                | Step::WaitFragment { node, .. }
+                | Step::Shutdown(node) => check_node(node)?,
+                Step::SendTransaction { from, to, via, .. } => {
+                    check_wallet(from)?;
+                    check_wallet(to)?;
+                    check_node(via)?;
+                    if from == to {
+                        bail!(ErrorKind::InvalidScenario(format!(
+                            "wallet '{}' cannot send a transaction to itself",
+                            from
+                        )));
+                    }
+                }
+                Step::AssertTipEqual { nodes, .. } => nodes.iter().try_for_each(check_node)?,
+                Step::Partition { group1, group2 } => group1
+                    .iter()
+                    .chain(group2.iter())
+                    .try_for_each(check_node)?,
+                Step::Load { from, to, via, .. } => {
+                    from.iter().try_for_each(check_wallet)?;
+                    check_wallet(to)?;
+                    via.iter().try_for_each(check_node)?;
+                }
+                Step::Wait(_) | Step::Heal => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn prepare(&self, context: &mut ContextChaCha) -> Result<ControllerBuilder> {
+        let mut builder = ControllerBuilder::new(&self.title);
+
+        let mut topology_builder = TopologyBuilder::new();
+        for description in self.topology.iter() {
+            let mut node = NodeTemplate::new(description.alias.clone());
+            for trusted_peer in description.trusted_peers.iter() {
+                node.add_trusted_peer(trusted_peer.clone());
+            }
+            topology_builder.register_node(node);
+        }
+        builder.set_topology(topology_builder.build());
+
+        let description = &self.blockchain;
+        let mut blockchain = Blockchain::new(
+            description.consensus.into(),
+            NumberOfSlotsPerEpoch::new(description.number_of_slots_per_epoch).ok_or_else(|| {
+                ErrorKind::InvalidScenario("invalid number of slots per epoch".to_owned())
+            })?,
+            SlotDuration::new(description.slot_duration).ok_or_else(|| {
+                ErrorKind::InvalidScenario("invalid slot duration in seconds".to_owned())
+            })?,
+        );
+        for leader in description.leaders.iter() {
+            blockchain.add_leader(leader.clone());
+        }
+        for wallet in description.initials.iter() {
+            let funds = Value(wallet.funds);
+            let mut template = match wallet.wallet_type {
+                WalletKind::Account => WalletTemplate::new_account(wallet.alias.clone(), funds),
+                WalletKind::Utxo => WalletTemplate::new_utxo(wallet.alias.clone(), funds),
+            };
+            *template.delegate_mut() = wallet.delegates_to.clone();
+            blockchain.add_wallet(template);
+        }
+        builder.set_blockchain(blockchain);
+
+        builder.build_settings(context);
+
+        Ok(builder)
+    }
+
+    /// run the scenario, reporting the outcome of every step
+    pub fn run(&self, mut context: ContextChaCha) -> Result<Report> {
+        let controller = self.prepare(&mut context)?.build(context)?;
+
+        let mut execution = Execution {
+            controller,
+            monitored: false,
+            nodes: HashMap::new(),
+            wallets: HashMap::new(),
+            consumed: HashSet::new(),
+            pending: None,
+        };
+        let mut report = Report {
+            title: self.title.clone(),
+            steps: Vec::with_capacity(self.steps.len()),
+        };
+
+        let mut failed = false;
+        for step in self.steps.iter() {
+            let started = Instant::now();
+            let (outcome, details) = if failed {
+                (Outcome::Skipped, None)
+            } else {
+                match execution.execute(step) {
+                    Ok(details) => (Outcome::Success, details),
+                    Err(err) => {
+                        failed = true;
+                        let reasons: Vec<String> = err.iter().map(|e| e.to_string()).collect();
+                        (Outcome::Failure(reasons.join(": ")), None)
+                    }
+                }
+            };
+            report.steps.push(StepReport {
+                step: step.to_string(),
+                duration: started.elapsed(),
+                outcome,
+                details,
+            });
+        }
+
+        execution.finalize();
+
+        Ok(report)
+    }
+}
+
+impl Execution {
+    fn node(&self, alias: &NodeAlias) -> Result<&NodeController> {
+        self.nodes
+            .get(alias)
+            .ok_or_else(|| ErrorKind::StepFailed(format!("node '{}' is not running", alias)).into())
+    }
+
+    /// fails if the wallet cannot be taken, a step taking several wallets
+    /// checks them all before taking any so none is lost on failure
+    fn check_wallet(&self, alias: &WalletAlias) -> Result<()> {
+        if self.consumed.contains(alias) {
+            bail!(ErrorKind::StepFailed(format!(
+                "wallet '{}' has been used by a load step",
+                alias
+            )));
+        }
+        if !self.wallets.contains_key(alias) && !self.controller.has_wallet(alias) {
+            bail!(ErrorKind::WalletNotFound(alias.clone()));
+        }
+        Ok(())
+    }
+
+    fn take_wallet(&mut self, alias: &WalletAlias) -> Result<Wallet> {
+        self.check_wallet(alias)?;
+        match self.wallets.remove(alias) {
+            Some(wallet) => Ok(wallet),
+            None => self.controller.wallet(alias),
+        }
+    }
+
+    /// execute the step, returning what it has measured (if anything)
+    fn execute(&mut self, step: &Step) -> Result<Option<String>> {
+        // display the progress of the nodes once the first nodes have
+        // been spawned, like the scenarios written in Rust
+        let spawning = match step {
+            Step::SpawnNode { .. } => true,
+            _ => false,
+        };
+        if !spawning && !self.monitored && !self.nodes.is_empty() {
+            self.controller.monitor_nodes();
+            self.monitored = true;
+        }
+
+        match step {
+            Step::SpawnNode { node, with_block0 } => {
+                let controller = self.controller.spawn_node(node, *with_block0)?;
+                self.nodes.insert(node.clone(), controller);
+            }
+            Step::Wait(duration) => std::thread::sleep((*duration).into()),
+            Step::SendTransaction {
+                from,
+                to,
+                via,
+                value,
+            } => {
+                self.check_wallet(from)?;
+                self.check_wallet(to)?;
+                let mut sender = self.take_wallet(from)?;
+                let receiver = self.take_wallet(to)?;
+                let result = self.node(via).map(NodeController::clone).and_then(|via| {
+                    if sender.template().wallet_type() == &WalletType::UTxO {
+                        sender.sync_utxos(Discrimination::Test, &via.utxos()?);
+                    }
+                    self.controller
+                        .wallet_send_to(&mut sender, &receiver, &via, (*value).into())
+                });
+                self.wallets.insert(from.clone(), sender);
+                self.wallets.insert(to.clone(), receiver);
+                self.pending = Some((from.clone(), result?));
+            }
+            Step::WaitFragment {
+                node,
+                timeout,
+                expect,
+            } => {
+                let (wallet, check) = self.pending.take().ok_or_else(|| {
+                    ErrorKind::StepFailed("no transaction has been sent".to_owned())
+                })?;
+                let status = self.wait_fragment(node, &check, (*timeout).into())?;
+                let in_a_block = match status {
+                    FragmentStatus::InABlock { .. } => true,
+                    _ => false,
+                };
+                if in_a_block {
+                    if let Some(wallet) = self.wallets.get_mut(&wallet) {
+                        wallet.confirm_transaction();
+                    }
+                }
+                if in_a_block != (*expect == ExpectedStatus::InABlock) {
+                    bail!(ErrorKind::StepFailed(format!(
+                        "fragment '{}' is {:?}",
+                        check.fragment_id(),
+                        status
+                    )));
+                }
+            }
+            Step::AssertTipEqual { nodes, timeout } => {
+                let deadline =
+                    Instant::now() + timeout.map(std::time::Duration::from).unwrap_or_default();
+                loop {
+                    let mut tips = Vec::with_capacity(nodes.len());
+                    for alias in nodes.iter() {
+                        tips.push((alias, self.node(alias)?.get_tip()?));
+                    }
+                    let equal = match tips.first() {
+                        None => true,
+                        Some((_, first)) => tips.iter().all(|(_, tip)| tip == first),
+                    };
+                    if equal {
+                        break;
+                    }
+                    if Instant::now() >= deadline {
+                        let tips = tips
+                            .iter()
+                            .map(|(alias, tip)| format!("{}: {}", alias, tip))
+                            .collect::<Vec<_>>();
+                        bail!(ErrorKind::StepFailed(format!(
+                            "different tips ({})",
+                            tips.join(", ")
+                        )));
+                    }
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+            }
+            Step::Partition { group1, group2 } => {
+                let group1: Vec<&str> = group1.iter().map(String::as_str).collect();
+                let group2: Vec<&str> = group2.iter().map(String::as_str).collect();
+                self.controller.partition(&group1, &group2);
+            }
+            Step::Heal => self.controller.heal(),
+            Step::Load {
+                from,
+                to,
+                via,
+                rate,
+                transactions_per_wallet,
+                value,
+                timeout,
+            } => {
+                let nodes = via
+                    .iter()
+                    .map(|alias| self.node(alias).map(NodeController::clone))
+                    .collect::<Result<Vec<_>>>()?;
+                let senders = from
+                    .iter()
+                    .map(|alias| self.take_wallet(alias))
+                    .collect::<Result<Vec<_>>>()?;
+                let receiver = self.take_wallet(to)?;
+                self.consumed.extend(from.iter().cloned());
+                let config = load::Config {
+                    rate: *rate,
+                    transactions_per_wallet: *transactions_per_wallet,
+                    value: (*value).into(),
+                    timeout: (*timeout).into(),
+                    poll_interval: POLL_INTERVAL,
+                };
+                let generator = self
+                    .controller
+                    .load_generator(config, senders, &receiver, nodes);
+                self.wallets.insert(to.clone(), receiver);
+
+                let report = generator?.run()?;
+                if report.confirmed == 0 {
+                    bail!(ErrorKind::StepFailed(format!(
+                        "none of the {} transactions submitted has been added in a block",
+                        report.submitted
+                    )));
+                }
+                return Ok(Some(report.to_string()));
+            }
+            Step::Shutdown(node) => {
+                let stopped = self.node(node)?.shutdown()?;
+                self.nodes.remove(node);
+                if !stopped {
+                    bail!(ErrorKind::StepFailed(format!(
+                        "node '{}' did not acknowledge the shutdown",
+                        node
+                    )));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn wait_fragment(
+        &self,
+        alias: &NodeAlias,
+        check: &MemPoolCheck,
+        timeout: std::time::Duration,
+    ) -> Result<FragmentStatus> {
+        let node = self.node(alias)?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let logs = node.fragment_logs()?;
+            if let Some(log) = logs.get(check.fragment_id()) {
+                match log.status() {
+                    FragmentStatus::Pending => (),
+                    status => return Ok(status.clone()),
+                }
+            }
+            if Instant::now() >= deadline {
+                bail!(ErrorKind::StepFailed(format!(
+                    "fragment '{}' still not processed after {}",
+                    check.fragment_id(),
+                    Duration::from(timeout)
+                )));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// shut down the nodes still running and wait for the end of the
+    /// scenario
+    fn finalize(self) {
+        for node in self.nodes.values() {
+            let _ = node.shutdown();
+        }
+        self.controller.finalize();
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Step::SpawnNode { node, with_block0 } => {
+                write!(f, "spawn node {}", node)?;
+                if *with_block0 {
+                    write!(f, " with block0")?;
+                }
+                Ok(())
+            }
+            Step::Wait(duration) => write!(f, "wait {}", duration),
+            Step::SendTransaction {
+                from,
+                to,
+                via,
+                value,
+            } => write!(f, "send {} from {} to {} via {}", value, from, to, via),
+            Step::WaitFragment { node, expect, .. } => {
+                write!(f, "wait fragment {:?} on {}", expect, node)
+            }
+            Step::AssertTipEqual { nodes, .. } => {
+                write!(f, "assert tip equal {}", nodes.join(", "))
+            }
+            Step::Partition { group1, group2 } => write!(
+                f,
+                "partition {} from {}",
+                group1.join(", "),
+                group2.join(", ")
+            ),
+            Step::Heal => write!(f, "heal"),
+            Step::Shutdown(node) => write!(f, "shutdown {}", node),
+            Step::Load {
+                from, via, rate, ..
+            } => write!(
+                f,
+                "load {} wallets via {} at {} TPS",
+                from.len(),
+                via.join(", "),
+                rate
+            ),
+        }
+    }
+}
+
+impl Report {
+    pub fn is_success(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| step.outcome == Outcome::Success)
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {}",
+            *style::icons::scenario,
+            style::scenario_title.apply_to(&self.title)
+        )?;
+        for (index, step) in self.steps.iter().enumerate() {
+            let outcome = match &step.outcome {
+                Outcome::Success => format!(
+                    "{} {}",
+                    *style::icons::success,
+                    style::success.apply_to("success")
+                ),
+                Outcome::Failure(reason) => format!(
+                    "{} {}",
+                    *style::icons::failure,
+                    style::error.apply_to(reason)
+                ),
+                Outcome::Skipped => format!("{} skipped", *style::icons::ignored),
+            };
+            writeln!(
+                f,
+                "  {:>3}. {} [{}ms] {}",
+                index + 1,
+                step.step,
+                step.duration.as_millis(),
+                outcome
+            )?;
+            if let Some(details) = &step.details {
+                for line in details.lines() {
+                    writeln!(f, "       {}", line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = r#"
+title: "minimal"
+topology:
+  - alias: node1
+  - alias: node2
+    trusted_peers: [ node1 ]
+blockchain:
+  consensus: bft
+  number_of_slots_per_epoch: 10
+  slot_duration: 1
+  leaders: [ node1 ]
+  initials:
+    - alias: faucet1
+      funds: 1000000
+    - alias: faucet2
+      wallet_type: utxo
+      funds: 1000
+steps:
+  - spawn_node: { node: node1, with_block0: true }
+  - spawn_node: { node: node2 }
+  - wait: 1s
+  - send_transaction: { from: faucet1, to: faucet2, via: node1, value: 100 }
+  - wait_fragment: { node: node1, timeout: 10s }
+  - heal
+  - assert_tip_equal: { nodes: [ node1, node2 ], timeout: 10s }
+  - shutdown: node2
+"#;
+
+    fn parse(yaml: &str) -> ScenarioFile {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn invalid_scenario(scenario: &ScenarioFile) -> String {
+        match scenario.validate().map_err(|error| error.0) {
+            Err(ErrorKind::InvalidScenario(reason)) => reason,
+            Err(error) => panic!("unexpected error: {}", error),
+            Ok(()) => panic!("the scenario is valid"),
+        }
+    }
+
+    #[test]
+    fn minimal_scenario() {
+        let scenario = parse(MINIMAL);
+
+        scenario.validate().unwrap();
+        assert_eq!(scenario.title, "minimal");
+        assert_eq!(scenario.topology[1].trusted_peers, vec!["node1".to_owned()]);
+        assert_eq!(scenario.steps.len(), 8);
+        match &scenario.steps[3] {
+            Step::SendTransaction {
+                from,
+                to,
+                via,
+                value,
+            } => {
+                assert_eq!(from, "faucet1");
+                assert_eq!(to, "faucet2");
+                assert_eq!(via, "node1");
+                assert_eq!(*value, 100);
+            }
+            step => panic!("unexpected step: {:?}", step),
+        }
+    }
+
+    #[test]
+    fn malformed_scenario() {
+        let unknown_field = MINIMAL.replace("trusted_peers", "peers");
+        assert!(serde_yaml::from_str::<ScenarioFile>(&unknown_field).is_err());
+
+        let unknown_step = MINIMAL.replace("- heal", "- reboot");
+        assert!(serde_yaml::from_str::<ScenarioFile>(&unknown_step).is_err());
+    }
+
+    #[test]
+    fn undefined_aliases() {
+        let scenario = parse(&MINIMAL.replace("via: node1", "via: node3"));
+        assert_eq!(invalid_scenario(&scenario), "undefined node 'node3'");
+
+        let scenario = parse(&MINIMAL.replace("to: faucet2", "to: faucet3"));
+        assert_eq!(invalid_scenario(&scenario), "undefined wallet 'faucet3'");
+    }
+
+    #[test]
+    fn transaction_to_the_sender() {
+        let scenario = parse(&MINIMAL.replace("to: faucet2", "to: faucet1"));
+        assert_eq!(
+            invalid_scenario(&scenario),
+            "wallet 'faucet1' cannot send a transaction to itself"
+        );
+    }
+
+    #[test]
+    fn delegating_utxo_wallet() {
+        let scenario = parse(&MINIMAL.replace(
+            "wallet_type: utxo",
+            "wallet_type: utxo\n      delegates_to: node1",
+        ));
+        assert!(invalid_scenario(&scenario).contains("cannot delegate"));
+    }
+}
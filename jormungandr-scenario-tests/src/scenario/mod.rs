@@ -1,6 +1,7 @@
 mod blockchain;
 mod context;
 mod controller;
+mod file;
 pub mod settings;
 mod topology;
 mod wallet;
@@ -9,6 +10,7 @@ pub use self::{
     blockchain::Blockchain,
     context::{Context, ContextChaCha, Seed},
     controller::{Controller, ControllerBuilder},
+    file::{Outcome, Report, ScenarioFile, Step, StepReport},
     topology::{Node, NodeAlias, Topology, TopologyBuilder},
     wallet::{Wallet, WalletAlias, WalletType},
 };
@@ -20,7 +22,9 @@ pub use jormungandr_lib::interfaces::{NumberOfSlotsPerEpoch, SlotDuration};
 
 error_chain! {
     links {
+        Load(crate::load::Error, crate::load::ErrorKind);
         Node(crate::node::Error, crate::node::ErrorKind);
+        Proxy(crate::proxy::Error, crate::proxy::ErrorKind);
         Wallet(crate::wallet::Error, crate::wallet::ErrorKind);
     }
 
@@ -28,6 +32,7 @@ error_chain! {
         Io(std::io::Error);
         Reqwest(reqwest::Error);
         BlockFormatError(chain_core::mempack::ReadError);
+        Yaml(serde_yaml::Error);
     }
 
     errors {
@@ -35,6 +40,18 @@ error_chain! {
             description("Node not found"),
             display("No node with alias {}", node),
         }
+        LinkNotFound(from: String, to: String) {
+            description("Link not found"),
+            display("No link from node {} to its trusted peer {}", from, to),
+        }
+        InvalidScenario(reason: String) {
+            description("Invalid scenario"),
+            display("Invalid scenario: {}", reason),
+        }
+        StepFailed(reason: String) {
+            description("Scenario step failed"),
+            display("{}", reason),
+        }
         WalletNotFound(wallet: String) {
             description("Wallet was not found"),
             display("Wallet '{}' was not found. Used before or never initialize", wallet)
@@ -22,9 +22,28 @@ pub struct Settings {
 
     pub wallets: HashMap<WalletAlias, Wallet>,
 
+    pub links: Vec<LinkSetting>,
+
     pub block0: Block0Configuration,
 }
 
+/// a trusted peer link of the topology, relayed through a proxy
+#[derive(Debug, Clone)]
+pub struct LinkSetting {
+    /// the node connecting to its trusted peer
+    pub from: NodeAlias,
+
+    /// the trusted peer
+    pub to: NodeAlias,
+
+    /// the address the proxy listens on, given to the node `from` as the
+    /// address of its trusted peer
+    pub proxy: SocketAddr,
+
+    /// the public address of the trusted peer
+    pub target: SocketAddr,
+}
+
 /// contains all the data to start or interact with a node
 #[derive(Debug, Clone)]
 pub struct NodeSetting {
@@ -106,6 +125,7 @@ impl Settings {
                 })
                 .collect(),
             wallets: HashMap::new(),
+            links: Vec::new(),
             block0: Block0Configuration {
                 blockchain_configuration: BlockchainConfiguration::new(
                     chain_addr::Discrimination::Test,
@@ -113,10 +133,11 @@ impl Settings {
                     LinearFee::new(1, 2, 3),
                 ),
                 initial: Vec::new(),
+                legacy_last_block_hash: None,
             },
         };
 
-        settings.populate_trusted_peers();
+        settings.populate_trusted_peers(context);
         settings.populate_block0_blockchain_configuration(&blockchain, context);
         settings.populate_block0_blockchain_initials(blockchain.wallets(), context);
 
@@ -280,15 +301,31 @@ impl Settings {
         // TODO blockchain_configuration.bft_slots_ratio = ;
     }
 
-    fn populate_trusted_peers(&mut self) {
+    fn populate_trusted_peers<RNG>(&mut self, context: &mut Context<RNG>)
+    where
+        RNG: RngCore + CryptoRng,
+    {
         let nodes = self.nodes.clone();
-        for (_alias, node) in self.nodes.iter_mut() {
+        for (alias, node) in self.nodes.iter_mut() {
             let mut trusted_peers = Vec::new();
 
             for trusted_peer in node.node_topology.trusted_peers() {
-                let trusted_peer = nodes.get(trusted_peer).unwrap();
+                let link = LinkSetting {
+                    from: alias.clone(),
+                    to: trusted_peer.clone(),
+                    proxy: context.generate_new_proxy_listen_address(),
+                    target: nodes
+                        .get(trusted_peer)
+                        .unwrap()
+                        .config
+                        .p2p
+                        .public_address
+                        .to_socketaddr()
+                        .unwrap(),
+                };
 
-                trusted_peers.push(trusted_peer.config.p2p.make_trusted_peer_setting());
+                trusted_peers.push(link.make_trusted_peer_setting());
+                self.links.push(link);
             }
 
             node.config.p2p.trusted_peers = trusted_peers;
@@ -429,8 +466,18 @@ impl P2pConfig {
             trusted_peers: Vec::new(),
         }
     }
+}
 
+impl LinkSetting {
     fn make_trusted_peer_setting(&self) -> poldercast::Address {
-        self.public_address.clone()
+        format!("/ip4/{}/tcp/{}", self.proxy.ip(), self.proxy.port())
+            .parse()
+            .unwrap()
+    }
+}
+
+impl std::fmt::Display for LinkSetting {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.from, self.to)
     }
 }
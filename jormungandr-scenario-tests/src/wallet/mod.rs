@@ -6,7 +6,7 @@ use chain_addr::Discrimination;
 use chain_impl_mockchain::{fee::LinearFee, fragment::Fragment, transaction::AccountIdentifier};
 use jormungandr_lib::{
     crypto::hash::Hash,
-    interfaces::{Address, Value},
+    interfaces::{Address, UTxOInfo, Value},
 };
 use rand_core::{CryptoRng, RngCore};
 use std::path::Path;
@@ -42,6 +42,11 @@ error_chain! {
             description("Transaction has already more inputs than needed"),
             display("The transaction has {} value extra than necessary", value),
         }
+
+        NotEnoughFunds(value: Value) {
+            description("No UTxO with enough funds for the transaction"),
+            display("No UTxO of the wallet has the {} needed by the transaction", value),
+        }
     }
 }
 
@@ -69,7 +74,7 @@ impl Wallet {
 
         match &self.inner {
             Inner::Account(account) => account.save_to(file),
-            Inner::UTxO(_utxo) => unimplemented!(),
+            Inner::UTxO(utxo) => utxo.save_to(file),
         }
     }
 
@@ -96,14 +101,26 @@ impl Wallet {
     pub fn address(&self, discrimination: Discrimination) -> Address {
         match &self.inner {
             Inner::Account(account) => account.address(discrimination),
-            Inner::UTxO(_utxo) => unimplemented!(),
+            Inner::UTxO(utxo) => utxo.address(discrimination),
         }
     }
 
     pub fn stake_key(&self) -> Option<AccountIdentifier> {
         match &self.inner {
             Inner::Account(account) => Some(account.stake_key()),
-            Inner::UTxO(_utxo) => unimplemented!(),
+            Inner::UTxO(_utxo) => None,
+        }
+    }
+
+    /// update the UTxOs of the wallet from the UTxOs of the ledger (the
+    /// accounts have nothing to update)
+    pub fn sync_utxos<'a, I>(&mut self, discrimination: Discrimination, utxos: I)
+    where
+        I: IntoIterator<Item = &'a UTxOInfo>,
+    {
+        match &mut self.inner {
+            Inner::Account(_account) => (),
+            Inner::UTxO(utxo) => utxo.sync_utxos(discrimination, utxos),
         }
     }
 
@@ -114,13 +131,17 @@ impl Wallet {
     pub fn confirm_transaction(&mut self) {
         match &mut self.inner {
             Inner::Account(account) => account.increment_counter(),
-            Inner::UTxO(_utxo) => unimplemented!(),
+            Inner::UTxO(utxo) => utxo.confirm_transaction(),
         }
     }
 
     /// simple function to create a transaction with only one output to the given
     /// address and of the given Value.
     ///
+    /// The transaction is not applied to the wallet until it is confirmed with
+    /// `confirm_transaction`: confirming it right away allows to sign the next
+    /// transactions of the wallet in advance.
+    ///
     pub fn transaction_to(
         &mut self,
         block0_hash: &Hash,
@@ -138,12 +159,16 @@ impl Wallet {
             Inner::Account(account) => account
                 .add_input(&mut txbuilder, fees)
                 .chain_err(|| "Cannot get inputs from the account")?,
-            Inner::UTxO(_utxo) => unimplemented!(),
+            Inner::UTxO(utxo) => utxo
+                .add_input(&mut txbuilder, fees)
+                .chain_err(|| "Cannot get inputs from the UTxOs")?,
         };
 
         let (_, tx) = txbuilder
             .seal_with_output_policy(fees, output_policy)
             .chain_err(|| "Cannot finalize the transaction")?;
+        // the output of the change follows the output to the given address
+        let change = tx.outputs.get(1).map(|output| output.value);
         let mut finalizer = TransactionFinalizer::new(tx.replace_extra(None));
 
         let sign_data = finalizer.get_tx_sign_data_hash();
@@ -152,15 +177,24 @@ impl Wallet {
             Inner::Account(account) => account
                 .mk_witness(block0_hash, &sign_data)
                 .chain_err(|| "Cannot create witness from account")?,
-            Inner::UTxO(_utxo) => unimplemented!(),
+            Inner::UTxO(utxo) => utxo
+                .mk_witness(block0_hash, &sign_data)
+                .chain_err(|| "Cannot create witness from UTxO")?,
         };
 
         finalizer
             .set_witness(0, witness)
             .chain_err(|| "Cannot add witness")?;
 
-        finalizer
+        let fragment = finalizer
             .to_fragment()
-            .chain_err(|| "Cannot generate the finalized transaction")
+            .chain_err(|| "Cannot generate the finalized transaction")?;
+
+        if let (Inner::UTxO(utxo), Some(value)) = (&mut self.inner, change) {
+            use chain_core::property::Fragment as _;
+            utxo.set_change(fragment.id(), 1, value);
+        }
+
+        Ok(fragment)
     }
 }
@@ -1,11 +1,25 @@
-use jormungandr_lib::{crypto::key, interfaces::UTxOInfo};
+use crate::wallet::{ErrorKind, Result, ResultExt};
+use chain_addr::Discrimination;
+use chain_impl_mockchain::{
+    fee::{FeeAlgorithm, LinearFee},
+    fragment::FragmentId,
+    transaction::{
+        Balance, Input, InputEnum, Transaction, TransactionSignDataHash, UtxoPointer, Witness,
+    },
+    txbuilder::{self, TransactionBuilder},
+    value::Value,
+};
+use jormungandr_lib::{
+    crypto::{hash::Hash, key},
+    interfaces::{Address, UTxOInfo},
+};
 use rand_chacha::ChaChaRng;
 use rand_core::{CryptoRng, RngCore, SeedableRng};
 
 pub type SpendingKey = key::SigningKey<chain_crypto::Ed25519>;
 pub type Identifier = key::Identifier<chain_crypto::Ed25519>;
 
-/// wallet for an account
+/// wallet for UTxOs
 #[derive(Debug, Clone)]
 pub struct Wallet {
     /// this is the root seed of the wallet, everytime we will require
@@ -21,6 +35,11 @@ pub struct Wallet {
     /// utxos with the index in the `signing_keys` so we can later
     /// sign the witness for the next transaction,
     utxos: Vec<(usize, UTxOInfo)>,
+
+    /// the UTxO spent by the last transaction built (its index in `utxos`)
+    /// and the change it sends back to the wallet, applied to the `utxos`
+    /// once the transaction is confirmed
+    last_transaction: Option<(usize, Option<UTxOInfo>)>,
 }
 
 impl Wallet {
@@ -45,6 +64,136 @@ impl Wallet {
     pub fn signing_keys<'a>(&'a self) -> impl Iterator<Item = &'a SpendingKey> {
         self.signing_keys.iter()
     }
+
+    pub fn save_to<W: std::io::Write>(&self, mut w: W) -> std::io::Result<()> {
+        for signing_key in self.signing_keys() {
+            writeln!(w, "{}", signing_key.to_bech32_str())?;
+        }
+        Ok(())
+    }
+
+    /// the address of the first signing key, the change of the transactions
+    /// goes back to the address of the UTxO they spend
+    pub fn address(&self, discrimination: Discrimination) -> Address {
+        self.address_of(0, discrimination)
+    }
+
+    fn address_of(&self, key_index: usize, discrimination: Discrimination) -> Address {
+        self.signing_keys[key_index]
+            .identifier()
+            .to_single_address(discrimination)
+            .into()
+    }
+
+    /// replace the UTxOs of the wallet with the ones of the given list
+    /// associated to its signing keys (i.e. the UTxOs of the ledger)
+    pub fn sync_utxos<'a, I>(&mut self, discrimination: Discrimination, utxos: I)
+    where
+        I: IntoIterator<Item = &'a UTxOInfo>,
+    {
+        let addresses: Vec<Address> = (0..self.signing_keys.len())
+            .map(|key_index| self.address_of(key_index, discrimination))
+            .collect();
+
+        self.utxos = utxos
+            .into_iter()
+            .filter_map(|utxo| {
+                addresses
+                    .iter()
+                    .position(|address| address == utxo.address())
+                    .map(|key_index| (key_index, utxo.clone()))
+            })
+            .collect();
+        self.last_transaction = None;
+    }
+
+    /// apply the last transaction built: its input is spent and its change
+    /// (if any) is a new UTxO of the wallet
+    pub fn confirm_transaction(&mut self) {
+        if let Some((spent, change)) = self.last_transaction.take() {
+            let (key_index, _) = self.utxos.remove(spent);
+            if let Some(change) = change {
+                self.utxos.push((key_index, change));
+            }
+        }
+    }
+
+    /// register the change output of the last transaction built
+    pub fn set_change(&mut self, fragment_id: FragmentId, index_in_transaction: u8, value: Value) {
+        if let Some((spent, change)) = &mut self.last_transaction {
+            let address = self.utxos[*spent].1.address().clone();
+            *change = Some(UTxOInfo::new(
+                fragment_id.into(),
+                index_in_transaction,
+                address,
+                value.into(),
+            ));
+        }
+    }
+
+    pub fn mk_witness(
+        &self,
+        block0_hash: &Hash,
+        signing_data: &TransactionSignDataHash,
+    ) -> Result<Witness> {
+        let (spent, _) = self
+            .last_transaction
+            .as_ref()
+            .ok_or(ErrorKind::CannotMakeWitness)?;
+        let (key_index, _) = &self.utxos[*spent];
+
+        Ok(Witness::new_utxo(
+            &block0_hash.clone().into_hash(),
+            signing_data,
+            self.signing_keys[*key_index].as_ref(),
+        ))
+    }
+
+    /// add as input the first UTxO covering the balance of the transaction,
+    /// the fee of the input and the fee of the change output
+    pub fn add_input<Extra: Clone>(
+        &mut self,
+        txbuilder: &mut TransactionBuilder<Extra>,
+        fees: &LinearFee,
+    ) -> Result<txbuilder::OutputPolicy>
+    where
+        LinearFee: FeeAlgorithm<Transaction<chain_addr::Address, Extra>>,
+    {
+        let balance = txbuilder
+            .get_balance(fees)
+            .chain_err(|| ErrorKind::CannotComputeBalance)?;
+        let value = match balance {
+            Balance::Negative(value) => value,
+            Balance::Zero => bail!(ErrorKind::TransactionAlreadyBalanced),
+            Balance::Positive(value) => {
+                bail!(ErrorKind::TransactionAlreadyExtraValue(value.into()))
+            }
+        };
+
+        // we are going to add an input and the output of the change
+        let value = (value + Value(fees.coefficient * 2))
+            .chain_err(|| ErrorKind::CannotAddCostOfExtraInput(fees.coefficient))?;
+
+        let spent = self
+            .utxos
+            .iter()
+            .position(|(_, utxo)| Value::from(*utxo.associated_fund()) >= value)
+            .ok_or(ErrorKind::NotEnoughFunds(value.into()))?;
+        let utxo = &self.utxos[spent].1;
+
+        let input = Input::from_enum(InputEnum::UtxoInput(UtxoPointer {
+            transaction_id: utxo.transaction_id().clone().into_hash(),
+            output_index: utxo.index_in_transaction(),
+            value: (*utxo.associated_fund()).into(),
+        }));
+
+        txbuilder.add_input(&input);
+
+        let change = utxo.address().clone().into();
+        self.last_transaction = Some((spent, None));
+
+        Ok(txbuilder::OutputPolicy::One(change))
+    }
 }
 
 impl From<[u8; 32]> for Wallet {
@@ -54,6 +203,7 @@ impl From<[u8; 32]> for Wallet {
             seed: seed.clone(),
             rng: ChaChaRng::from_seed(seed),
             utxos: Vec::new(),
+            last_transaction: None,
         };
 
         wallet.generate_new_signing_key();
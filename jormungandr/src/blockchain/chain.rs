@@ -100,11 +100,34 @@ error_chain! {
         CannotApplyBlock {
             description("Block cannot be applied on top of the previous block's ledger state"),
         }
+
+        BlockRejected (reason: String) {
+            description("Block, or its parent, has already been rejected"),
+            display("The block, or its parent, has already been rejected: {}", reason),
+        }
+
+        ReplayTooDeep (max_depth: usize) {
+            description("too many blocks to replay to recover the state of the block"),
+            display("The state of the block is not recovered, more than {} blocks would have to be replayed", max_depth),
+        }
     }
 }
 
+/// the description of the error followed by the descriptions of its causes
+pub(super) fn error_reason(error: &Error) -> String {
+    error
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>()
+        .join(": ")
+}
+
 pub const MAIN_BRANCH_TAG: &str = "HEAD";
 
+/// maximum number of blocks replayed to recover the `Ref` of a block which
+/// is no longer in the cache, see `Blockchain::get_ref_or_replay`
+pub const MAX_REPLAY_DEPTH: usize = 2160;
+
 /// blockchain object, can be safely shared across multiple threads. However it is better not
 /// to as some operations may require a mutex.
 ///
@@ -232,35 +255,35 @@ impl Blockchain {
     /// once the `Ref` is in hand, it means we have the Leadership schedule associated
     /// to this block and the `Ledger` state after this block.
     ///
-    /// If the future returns `None` it means we don't know about this block locally
-    /// and it might be necessary to contacts the network to retrieve a missing
-    /// branch
+    /// If the block is in the storage but not in the cache (after a restart of
+    /// the node or once the `Ref` has expired from the cache) the `Ref` is loaded
+    /// by replaying the blocks from the storage, see `get_ref_or_replay`.
     ///
-    /// TODO: the case where the block is in storage but not yet in the cache
-    ///       is not implemented
+    /// If the future returns `None` it means we don't know about this block locally,
+    /// or that the blocks to replay have been pruned from the storage, and it might
+    /// be necessary to contacts the network to retrieve a missing branch
     pub fn get_ref(
         &mut self,
         header_hash: HeaderHash,
     ) -> impl Future<Item = Option<Arc<Ref>>, Error = Error> {
         let get_ref_cache_future = self.ref_cache.get(header_hash.clone());
-        let block_exists_future = self.storage.block_exists(header_hash);
+        let block_exists_future = self.storage.block_exists(header_hash.clone());
+        let mut self1 = self.clone();
 
         get_ref_cache_future
             .map_err(|_: Infallible| unreachable!())
-            .and_then(|maybe_ref| {
+            .and_then(move |maybe_ref| {
                 if maybe_ref.is_none() {
                     future::Either::A(
                         block_exists_future
                             .map_err(|e| {
                                 Error::with_chain(e, "cannot check if the block is in the storage")
                             })
-                            .and_then(|block_exists| {
+                            .and_then(move |block_exists| {
                                 if block_exists {
-                                    unimplemented!(
-                                        "method to load a Ref from the storage is not yet there"
-                                    )
+                                    future::Either::A(self1.load_ref(header_hash))
                                 } else {
-                                    future::ok(None)
+                                    future::Either::B(future::ok(None))
                                 }
                             }),
                     )
@@ -270,6 +293,118 @@ impl Blockchain {
             })
     }
 
+    /// load the `Ref` of a block of the storage which is not in the cache.
+    ///
+    /// The future is boxed as replaying a block gets the `Ref` of its parent.
+    fn load_ref(
+        &mut self,
+        header_hash: HeaderHash,
+    ) -> Box<dyn Future<Item = Option<Arc<Ref>>, Error = Error> + Send> {
+        Box::new(
+            self.get_ref_or_replay(header_hash, MAX_REPLAY_DEPTH)
+                .or_else(|error| match error.kind() {
+                    // the bodies of the blocks have been pruned from the
+                    // storage or there are too many of them to replay, the
+                    // blocks will be fetched from the network
+                    ErrorKind::MissingParentBlockFromStorage(_) | ErrorKind::ReplayTooDeep(_) => {
+                        Ok(None)
+                    }
+                    _ => Err(error),
+                }),
+        )
+    }
+
+    /// get `Ref` of the given header hash, replaying the blocks from the
+    /// storage if the `Ref` is not in the cache anymore.
+    ///
+    /// The blocks are replayed from the nearest ancestor still in the cache,
+    /// or from the block0, and the replayed `Ref`s are added in the cache.
+    /// Replaying a long branch is expensive: it fails if more than
+    /// `max_depth` blocks would have to be replayed, or if the bodies of the
+    /// blocks have been pruned from the storage.
+    ///
+    /// If the future returns `None` the block is not in the storage.
+    pub fn get_ref_or_replay(
+        &mut self,
+        header_hash: HeaderHash,
+        max_depth: usize,
+    ) -> impl Future<Item = Option<Arc<Ref>>, Error = Error> {
+        let ref_cache = self.ref_cache.clone();
+        let storage = self.storage.clone();
+        let mut self1 = self.clone();
+        let self2 = self.clone();
+
+        // walk back to the nearest cached ancestor, collecting the blocks
+        // to replay on top of it
+        future::loop_fn(
+            (header_hash, Vec::new()),
+            move |(header_hash, mut blocks): (HeaderHash, Vec<Block>)| {
+                let storage = storage.clone();
+                ref_cache
+                    .get(header_hash.clone())
+                    .map_err(|_: Infallible| unreachable!())
+                    .and_then(move |maybe_ref| match maybe_ref {
+                        Some(reference) => future::Either::A(future::ok(future::Loop::Break((
+                            Some(reference),
+                            blocks,
+                        )))),
+                        None => future::Either::B(
+                            storage
+                                .get(header_hash)
+                                .map_err(|e| {
+                                    Error::with_chain(e, "cannot get the block from the storage")
+                                })
+                                .and_then(move |maybe_block| match maybe_block {
+                                    None => Ok(future::Loop::Break((None, blocks))),
+                                    Some(_) if blocks.len() == max_depth => {
+                                        Err(ErrorKind::ReplayTooDeep(max_depth).into())
+                                    }
+                                    Some(block) => {
+                                        let parent_hash = block.header.block_parent_hash().clone();
+                                        let is_block0 = u32::from(block.header.chain_length()) == 0;
+                                        blocks.push(block);
+                                        if is_block0 {
+                                            Ok(future::Loop::Break((None, blocks)))
+                                        } else {
+                                            Ok(future::Loop::Continue((parent_hash, blocks)))
+                                        }
+                                    }
+                                }),
+                        ),
+                    })
+            },
+        )
+        .and_then(move |(maybe_ref, mut blocks)| {
+            // without a cached ancestor, the walk stopped at the block0 or
+            // at a block whose parent is missing from the storage
+            let ancestor = match maybe_ref {
+                Some(reference) => future::Either::A(future::ok(reference)),
+                None => match blocks.pop() {
+                    None => return future::Either::A(future::ok(None)),
+                    Some(block) => {
+                        if u32::from(block.header.chain_length()) == 0 {
+                            future::Either::B(self1.create_block0_reference(block))
+                        } else {
+                            future::Either::A(future::err(
+                                ErrorKind::MissingParentBlockFromStorage(block.header).into(),
+                            ))
+                        }
+                    }
+                },
+            };
+            future::Either::B(ancestor.and_then(move |ancestor| {
+                stream::iter_ok::<_, Error>(blocks.into_iter().rev())
+                    .fold((ancestor, self2), |(_, mut self2), block| {
+                        let returned = self2.clone();
+                        self2
+                            .replay_block(block)
+                            .map(move |reference| (reference, returned))
+                    })
+                    .map(|(reference, _)| Some(reference))
+            }))
+        })
+    }
+
     /// load the header's parent `Ref`.
     fn load_header_parent(
         &mut self,
@@ -336,13 +471,18 @@ impl Blockchain {
 
                     if header.block_date() <= parent_ref.block_date() {
                         return future::err(
-                            "block is not valid, date is set before parent's".into(),
+                            ErrorKind::BlockHeaderVerificationFailed(
+                                "date is set before parent's".to_owned(),
+                            )
+                            .into(),
                         );
                     }
                     if header.chain_length() != parent_ref.chain_length().next() {
                         return future::err(
-                            "block is not valid, chain length is not monotonically increasing"
-                                .into(),
+                            ErrorKind::BlockHeaderVerificationFailed(
+                                "chain length is not monotonically increasing".to_owned(),
+                            )
+                            .into(),
                         );
                     }
 
@@ -499,6 +639,37 @@ impl Blockchain {
             })
     }
 
+    /// check the given block, already present in the storage, against its
+    /// parent's state and apply it on the blockchain without storing it again.
+    ///
+    /// The parent of the block must have been applied already.
+    pub fn replay_block(&mut self, block: Block) -> impl Future<Item = Arc<Ref>, Error = Error> {
+        let header = block.header.clone();
+
+        let mut self1 = self.clone();
+        let mut self2 = self.clone();
+
+        self.pre_check_header(header, true)
+            .and_then(move |pre_checked_header: PreCheckedHeader| match pre_checked_header {
+                PreCheckedHeader::HeaderWithCache { header, parent_ref } => {
+                    future::Either::A(self1.post_check_header(header, parent_ref))
+                }
+                PreCheckedHeader::AlreadyPresent {
+                    header,
+                    cached_reference: _cached_reference,
+                } => unreachable!(
+                    "block already present, this should not happen. {:#?}",
+                    header
+                ),
+                PreCheckedHeader::MissingParent { header } => future::Either::B(future::err(
+                    ErrorKind::MissingParentBlockFromStorage(header).into(),
+                )),
+            })
+            .and_then(move |post_checked_header: PostCheckedHeader| {
+                self2.apply_block(post_checked_header, &block)
+            })
+    }
+
     /// Apply the given block0 in the blockchain (updating the RefCache and the other objects)
     ///
     /// This function returns the created block0 branch. Having it will
@@ -511,14 +682,30 @@ impl Blockchain {
     ///
     /// * the block0 does build an invalid `Ledger`: `ErrorKind::Block0InitialLedgerError`;
     ///
-    fn apply_block0(&mut self, block0: Block) -> impl Future<Item = Branch, Error = Error> {
+    pub(super) fn apply_block0(&mut self, block0: Block) -> impl Future<Item = Branch, Error = Error> {
+        let mut branches = self.branches.clone();
+
+        self.create_block0_reference(block0)
+            .map(Branch::new)
+            .and_then(move |branch| {
+                branches
+                    .add(branch.clone())
+                    .map(|()| branch)
+                    .map_err(|_: Infallible| unreachable!())
+            })
+    }
+
+    /// create the `Ref` of the block0 and add it in the `RefCache`
+    fn create_block0_reference(
+        &mut self,
+        block0: Block,
+    ) -> impl Future<Item = Arc<Ref>, Error = Error> {
         let block0_header = block0.header.clone();
         let block0_id = block0_header.hash();
         let block0_id_1 = block0_header.hash();
         let block0_date = block0_header.block_date().clone();
 
         let mut self1 = self.clone();
-        let mut branches = self.branches.clone();
 
         let time_frame = {
             use crate::blockcfg::Block0DataSource as _;
@@ -569,13 +756,6 @@ impl Blockchain {
                     )
                     .map_err(|_: Infallible| unreachable!())
             })
-            .map(Branch::new)
-            .and_then(move |branch| {
-                branches
-                    .add(branch.clone())
-                    .map(|()| branch)
-                    .map_err(|_: Infallible| unreachable!())
-            })
     }
 
     /// function to do the initial application of the block0 in the `Blockchain` and its
@@ -627,6 +807,49 @@ impl Blockchain {
             })
     }
 
+    /// apply the block0 (already in the storage) and reset the `HEAD` of the
+    /// storage to the block0.
+    ///
+    /// This is used when the blocks between the block0 and the `HEAD` of the
+    /// storage cannot be replayed (their bodies have been pruned from the
+    /// storage): the blockchain will need to be recovered from the network.
+    ///
+    /// # Errors
+    ///
+    /// The resulted future may fail if
+    ///
+    /// * the block0 is not already in the storage: `ErrorKind::Block0NotAlreadyInStorage`;
+    /// * the block0 does build a valid `Ledger`: `ErrorKind::Block0InitialLedgerError`;
+    /// * other errors while interacting with the storage (IO errors)
+    ///
+    pub fn reload_from_block0(
+        &mut self,
+        block0: Block,
+    ) -> impl Future<Item = Branch, Error = Error> {
+        let block0_id = block0.header.hash();
+
+        let mut self1 = self.clone();
+        let mut storage = self.storage.clone();
+
+        self.storage
+            .block_exists(block0_id.clone())
+            .map_err(|e| Error::with_chain(e, "Cannot check if block0 is in storage"))
+            .and_then(|existence| {
+                if !existence {
+                    future::err(ErrorKind::Block0NotAlreadyInStorage.into())
+                } else {
+                    future::ok(())
+                }
+            })
+            .and_then(move |()| self1.apply_block0(block0))
+            .and_then(move |block0_branch| {
+                storage
+                    .put_tag(MAIN_BRANCH_TAG.to_owned(), block0_id)
+                    .map(|()| block0_branch)
+                    .map_err(|e| Error::with_chain(e, "Cannot reset the HEAD tag to block0"))
+            })
+    }
+
     /// returns a future that will propagate the initial states and leadership
     /// from the block0 to the `Head` of the storage (the last known block which
     /// made consensus).
@@ -699,37 +922,15 @@ impl Blockchain {
                                 Error::with_chain(e, "Error while iterating between bloc0 and HEAD")
                             })
                             .fold((branch, self4), move |(branch, mut self4), block: Block| {
-                                let header = block.header.clone();
-
-                                let mut self5 = self4.clone();
-                                let mut self6 = self4.clone();
                                 let returned = self4.clone();
 
-                                self4
-                                    .pre_check_header(header, true)
-                                    .and_then(move |pre_checked_header: PreCheckedHeader| {
-                                        match pre_checked_header {
-                                            PreCheckedHeader::HeaderWithCache {
-                                                header,
-                                                parent_ref,
-                                            } => future::Either::A(self5.post_check_header(header, parent_ref)),
-                                            PreCheckedHeader::AlreadyPresent { header, cached_reference: _cached_reference } => {
-                                                unreachable!("block already present, this should not happen. {:#?}", header)
-                                            },
-                                            PreCheckedHeader::MissingParent { header } =>
-                                                future::Either::B(future::err(ErrorKind::MissingParentBlockFromStorage(header).into())),
-                                        }
-                                    })
-                                    .and_then(move |post_checked_header: PostCheckedHeader| {
-                                        self6.apply_block(post_checked_header, &block)
-                                    })
-                                    .and_then(move |new_ref| {
-                                        branch
-                                            .clone()
-                                            .update_ref(new_ref)
-                                            .map(move |_old_ref| (branch, returned))
-                                            .map_err(|_: Infallible| unreachable!())
-                                    })
+                                self4.replay_block(block).and_then(move |new_ref| {
+                                    branch
+                                        .clone()
+                                        .update_ref(new_ref)
+                                        .map(move |_old_ref| (branch, returned))
+                                        .map_err(|_: Infallible| unreachable!())
+                                })
                             })
                             .map(|(branch, _)| branch)
                     })
@@ -746,3 +947,103 @@ impl Blockchain {
             .map(Checkpoints::new_from)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    //! the test blocks are not valid on a ledger: the state of the block0
+    //! cannot be created, the tests stop before any block is replayed.
+
+    use super::*;
+    use crate::blockcfg::{BlockBuilder, BlockDate};
+    use chain_core::property::ChainLength as _;
+    use chain_crypto::Ed25519;
+    use chain_storage::{memory::MemoryBlockStore, store::BlockStore};
+    use jormungandr_lib::crypto::key::SigningKey;
+    use rand::rngs::OsRng;
+
+    /// the block0 followed by `length` blocks
+    fn chain(length: u32) -> Vec<Block> {
+        let key = SigningKey::<Ed25519>::generate(OsRng::new().unwrap()).into_secret_key();
+        let mut blocks = vec![BlockBuilder::new().make_genesis_block()];
+        for slot_id in 1..=length {
+            let parent = &blocks.last().unwrap().header;
+            let mut builder = BlockBuilder::new();
+            builder
+                .date(BlockDate { epoch: 0, slot_id })
+                .parent(parent.hash())
+                .chain_length(parent.chain_length().next());
+            blocks.push(builder.make_bft_block(&key));
+        }
+        blocks
+    }
+
+    /// a blockchain with the given blocks in the storage and none in the
+    /// cache
+    fn blockchain(blocks: &[Block]) -> Blockchain {
+        let mut store = MemoryBlockStore::new();
+        for block in blocks {
+            store.put_block(block).unwrap();
+        }
+        Blockchain::new(Box::new(store), Duration::from_secs(60))
+    }
+
+    #[test]
+    fn replay_of_a_block_not_in_the_storage() {
+        let blocks = chain(4);
+        let mut blockchain = blockchain(&blocks[..4]);
+
+        let reference = blockchain
+            .get_ref_or_replay(blocks[4].header.hash(), 10)
+            .wait()
+            .unwrap();
+
+        assert!(reference.is_none());
+    }
+
+    #[test]
+    fn replay_within_the_max_depth() {
+        let blocks = chain(5);
+        let mut blockchain = blockchain(&blocks);
+
+        // the blocks 2, 1 and the block0 are walked through, the replay
+        // starts with the state of the block0
+        let error = blockchain
+            .get_ref_or_replay(blocks[2].header.hash(), 3)
+            .wait()
+            .unwrap_err();
+
+        match error.kind() {
+            ErrorKind::Block0InitialLedgerError => (),
+            _ => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn replay_beyond_the_max_depth() {
+        let blocks = chain(5);
+        let mut blockchain = blockchain(&blocks);
+
+        let error = blockchain
+            .get_ref_or_replay(blocks[3].header.hash(), 3)
+            .wait()
+            .unwrap_err();
+
+        match error.kind() {
+            ErrorKind::ReplayTooDeep(3) => (),
+            _ => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn ref_beyond_the_max_depth_is_not_loaded() {
+        let blocks = chain(MAX_REPLAY_DEPTH as u32 + 1);
+        let mut blockchain = blockchain(&blocks);
+
+        let reference = blockchain
+            .get_ref(blocks.last().unwrap().header.hash())
+            .wait()
+            .unwrap();
+
+        assert!(reference.is_none());
+    }
+}
@@ -0,0 +1,559 @@
+//! offline verification of the blocks in the storage
+//!
+//! The chain is walked back from the `HEAD` tag to the block0 following the
+//! parent links of the stored blocks. It is then replayed from the block0 up to
+//! the `HEAD`: every header is checked again and every block is applied again on
+//! the ledger. The replay stops at the first invalid block, the previous block
+//! is then the last consistent block of the storage.
+//!
+//! If the bodies of the old blocks have been pruned (see [`PRUNED_TAG`]) the
+//! ledger cannot be rebuilt, only the links between the blocks and the headers
+//! still available in the storage are checked.
+//!
+//! [`PRUNED_TAG`]: ../constant.PRUNED_TAG.html
+
+use super::chain::error_reason;
+use crate::{
+    blockcfg::{Block, HeaderHash},
+    blockchain::{Blockchain, Error, ErrorKind, MAIN_BRANCH_TAG, PRUNED_TAG},
+};
+use std::fmt;
+use tokio::prelude::*;
+
+/// problem found in the storage
+#[derive(Debug, Clone)]
+pub enum Issue {
+    /// the `HEAD` tag is missing from the storage
+    NoHead,
+    /// a block is missing from the storage, `child` is the block
+    /// referencing it as parent (`None` if the missing block is the `HEAD`)
+    MissingBlock {
+        block: HeaderHash,
+        child: Option<HeaderHash>,
+    },
+    /// following the parent links leads to another block0
+    UnexpectedBlock0 { block: HeaderHash },
+    /// the block could not be read from the storage
+    UnreadableBlock { block: HeaderHash, reason: String },
+    /// the block is in the storage but it is not valid
+    InvalidBlock { block: HeaderHash, reason: String },
+}
+
+/// result of the verification of the storage
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// the block pointed by the `HEAD` tag
+    pub head: Option<HeaderHash>,
+    /// the number of blocks (not including the block0) checked successfully
+    pub checked: u64,
+    /// the most recent block of the `HEAD` chain which has been checked
+    /// successfully, the `HEAD` can be safely reset to this block
+    pub last_consistent: HeaderHash,
+    /// set if the block bodies have been pruned from the storage, the
+    /// blocks have not been applied on the ledger
+    pub headers_only: bool,
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    pub fn is_consistent(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// verify the chain of blocks from the `HEAD` of the storage down to the
+/// given block0.
+///
+/// The given `blockchain` must have been freshly created on top of the
+/// storage: the verification applies the blocks on it. The storage is not
+/// modified.
+///
+/// # Errors
+///
+/// Issues in the stored blocks are not errors, they are listed in the
+/// returned `Report`. The function fails if:
+///
+/// * the block0 is not already in the storage: `ErrorKind::Block0NotAlreadyInStorage`;
+/// * the block0 does build a valid `Ledger`: `ErrorKind::Block0InitialLedgerError`;
+/// * other errors while interacting with the storage (IO errors)
+///
+pub fn verify(blockchain: &mut Blockchain, block0: Block) -> Result<Report, Error> {
+    let block0_id = block0.header.hash();
+    let storage = blockchain.storage().clone();
+
+    if !storage.block_exists(block0_id.clone()).wait()? {
+        return Err(ErrorKind::Block0NotAlreadyInStorage.into());
+    }
+
+    let mut report = Report {
+        head: None,
+        checked: 0,
+        last_consistent: block0_id.clone(),
+        headers_only: storage.get_tag(PRUNED_TAG.to_owned()).wait()?.is_some(),
+        issues: Vec::new(),
+    };
+
+    let head = match storage.get_tag(MAIN_BRANCH_TAG.to_owned()).wait()? {
+        None => {
+            report.issues.push(Issue::NoHead);
+            return Ok(report);
+        }
+        Some(head) => head,
+    };
+    report.head = Some(head.clone());
+
+    // walk back the parent links from the HEAD to the block0
+    let mut chain = Vec::new();
+    let mut current = head;
+    let mut child = None;
+    loop {
+        let block_info = match storage.get_block_info(current.clone()).wait()? {
+            None => {
+                report.issues.push(Issue::MissingBlock {
+                    block: current,
+                    child,
+                });
+                return Ok(report);
+            }
+            Some(block_info) => block_info,
+        };
+
+        if block_info.depth == 0 {
+            if current != block0_id {
+                report
+                    .issues
+                    .push(Issue::UnexpectedBlock0 { block: current });
+                return Ok(report);
+            }
+            break;
+        }
+
+        let parent = block_info.parent_id();
+        chain.push(current.clone());
+        child = Some(current);
+        current = parent;
+    }
+
+    // replay the chain from the block0 up to the HEAD
+    if !report.headers_only {
+        blockchain.apply_block0(block0).wait()?;
+    }
+
+    let mut parent = block0_id;
+    for block_id in chain.into_iter().rev() {
+        match storage.get(block_id.clone()).wait() {
+            Err(error) => {
+                report.issues.push(Issue::UnreadableBlock {
+                    block: block_id,
+                    reason: error.to_string(),
+                });
+                break;
+            }
+            Ok(None) if report.headers_only => {
+                // the body of the block has been pruned, the link to its
+                // parent has already been checked
+            }
+            Ok(None) => {
+                report.issues.push(Issue::UnreadableBlock {
+                    block: block_id,
+                    reason: "the block body is missing from the storage".to_owned(),
+                });
+                break;
+            }
+            Ok(Some(block)) => {
+                if let Err(reason) = check_stored_header(&block, &block_id, &parent) {
+                    report.issues.push(Issue::InvalidBlock {
+                        block: block_id,
+                        reason,
+                    });
+                    break;
+                }
+
+                if !report.headers_only {
+                    if let Err(error) = blockchain.replay_block(block).wait() {
+                        report.issues.push(Issue::InvalidBlock {
+                            block: block_id,
+                            reason: error_reason(&error),
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+
+        report.checked += 1;
+        report.last_consistent = block_id.clone();
+        parent = block_id;
+    }
+
+    Ok(report)
+}
+
+/// reset the `HEAD` of the storage to the last consistent block
+/// found by the verification.
+pub fn repair(blockchain: &Blockchain, report: &Report) -> Result<(), Error> {
+    blockchain
+        .storage()
+        .clone()
+        .put_tag(MAIN_BRANCH_TAG.to_owned(), report.last_consistent.clone())
+        .wait()
+        .map_err(|e| Error::with_chain(e, "Cannot reset the HEAD tag"))
+}
+
+fn check_stored_header(
+    block: &Block,
+    block_id: &HeaderHash,
+    parent: &HeaderHash,
+) -> Result<(), String> {
+    let stored_id = block.header.hash();
+    if stored_id != *block_id {
+        return Err(format!("the stored block's hash is {}", stored_id));
+    }
+    let stored_parent = block.header.block_parent_hash();
+    if stored_parent != parent {
+        return Err(format!(
+            "the stored block's parent is {}, expected {}",
+            stored_parent, parent
+        ));
+    }
+    Ok(())
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::NoHead => write!(f, "the {} tag is missing", MAIN_BRANCH_TAG),
+            Issue::MissingBlock { block, child: None } => {
+                write!(f, "block {} is missing", block)
+            }
+            Issue::MissingBlock {
+                block,
+                child: Some(child),
+            } => write!(f, "block {} (parent of {}) is missing", block, child),
+            Issue::UnexpectedBlock0 { block } => {
+                write!(f, "the chain leads to another block0: {}", block)
+            }
+            Issue::UnreadableBlock { block, reason } => {
+                write!(f, "block {} cannot be read: {}", block, reason)
+            }
+            Issue::InvalidBlock { block, reason } => {
+                write!(f, "block {} is not valid: {}", block, reason)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.head {
+            Some(head) => writeln!(f, "head: {}", head)?,
+            None => writeln!(f, "head: none")?,
+        }
+        writeln!(f, "blocks checked: {}", self.checked)?;
+        if self.headers_only {
+            writeln!(
+                f,
+                "block bodies have been pruned, blocks have not been applied on the ledger"
+            )?;
+        }
+        writeln!(f, "last consistent block: {}", self.last_consistent)?;
+        if self.issues.is_empty() {
+            write!(f, "no issue found")
+        } else {
+            write!(f, "issues found: {}", self.issues.len())?;
+            for issue in self.issues.iter() {
+                write!(f, "\n  * {}", issue)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! the test blocks are not valid on a ledger: the storage is flagged as
+    //! pruned so only the links between the blocks are verified, except in
+    //! the archive mode tests which start from a valid block0.
+
+    use super::*;
+    use crate::{
+        blockcfg::{BlockBuilder, BlockDate},
+        start_up::NodeBlockStore,
+    };
+    use chain_addr::Discrimination;
+    use chain_core::property::ChainLength as _;
+    use chain_crypto::{Ed25519, SecretKey};
+    use chain_impl_mockchain::{
+        block::ConsensusVersion, fee::LinearFee, leadership::bft::LeaderId,
+    };
+    use chain_storage::{
+        error::Error as StorageError,
+        memory::MemoryBlockStore,
+        store::{BlockInfo, BlockStore},
+    };
+    use jormungandr_lib::{
+        crypto::key::SigningKey,
+        interfaces::{Block0Configuration, BlockchainConfiguration, ConsensusLeaderId},
+    };
+    use rand::rngs::OsRng;
+    use std::time::Duration;
+
+    /// memory store losing a block, or returning the wrong block for a hash
+    struct DamagedStore {
+        inner: MemoryBlockStore<Block>,
+        missing: Option<HeaderHash>,
+        replaced: Option<(HeaderHash, Block)>,
+    }
+
+    impl BlockStore for DamagedStore {
+        type Block = Block;
+
+        fn put_block_internal(
+            &mut self,
+            block: &Block,
+            block_info: BlockInfo<HeaderHash>,
+        ) -> Result<(), StorageError> {
+            self.inner.put_block_internal(block, block_info)
+        }
+
+        fn get_block(
+            &self,
+            block_hash: &HeaderHash,
+        ) -> Result<(Block, BlockInfo<HeaderHash>), StorageError> {
+            if self.missing.as_ref() == Some(block_hash) {
+                return Err(StorageError::BlockNotFound);
+            }
+            let (block, block_info) = self.inner.get_block(block_hash)?;
+            match &self.replaced {
+                Some((replaced, other)) if replaced == block_hash => {
+                    Ok((other.clone(), block_info))
+                }
+                _ => Ok((block, block_info)),
+            }
+        }
+
+        fn get_block_info(
+            &self,
+            block_hash: &HeaderHash,
+        ) -> Result<BlockInfo<HeaderHash>, StorageError> {
+            if self.missing.as_ref() == Some(block_hash) {
+                return Err(StorageError::BlockNotFound);
+            }
+            self.inner.get_block_info(block_hash)
+        }
+
+        fn block_exists(&self, block_hash: &HeaderHash) -> Result<bool, StorageError> {
+            if self.missing.as_ref() == Some(block_hash) {
+                return Ok(false);
+            }
+            self.inner.block_exists(block_hash)
+        }
+
+        fn put_tag(&mut self, tag_name: &str, block_hash: &HeaderHash) -> Result<(), StorageError> {
+            self.inner.put_tag(tag_name, block_hash)
+        }
+
+        fn get_tag(&self, tag_name: &str) -> Result<Option<HeaderHash>, StorageError> {
+            self.inner.get_tag(tag_name)
+        }
+    }
+
+    impl NodeBlockStore for DamagedStore {}
+
+    fn leader_key() -> SecretKey<Ed25519> {
+        SigningKey::<Ed25519>::generate(OsRng::new().unwrap()).into_secret_key()
+    }
+
+    fn child_of(parent: &Block, slot_id: u32, key: &SecretKey<Ed25519>) -> Block {
+        let mut builder = BlockBuilder::new();
+        builder
+            .date(BlockDate { epoch: 0, slot_id })
+            .parent(parent.header.hash())
+            .chain_length(parent.header.chain_length().next());
+        builder.make_bft_block(key)
+    }
+
+    /// the block0 followed by `length` blocks
+    fn chain(length: u32) -> Vec<Block> {
+        let key = leader_key();
+        let mut blocks = vec![BlockBuilder::new().make_genesis_block()];
+        for slot_id in 1..=length {
+            let block = child_of(blocks.last().unwrap(), slot_id, &key);
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    /// a block0 building a valid ledger, with the given key as the only
+    /// BFT leader
+    fn valid_block0(leader: &SecretKey<Ed25519>) -> Block {
+        let mut blockchain_configuration = BlockchainConfiguration::new(
+            Discrimination::Test,
+            ConsensusVersion::Bft,
+            LinearFee::new(0, 0, 0),
+        );
+        blockchain_configuration.consensus_leader_ids =
+            vec![ConsensusLeaderId(LeaderId::from(leader.to_public()))];
+        Block0Configuration {
+            blockchain_configuration,
+            initial: Vec::new(),
+            legacy_last_block_hash: None,
+        }
+        .to_block()
+    }
+
+    /// a blockchain keeping the bodies of all the blocks
+    fn archive_blockchain(blocks: &[Block]) -> Blockchain {
+        let mut store = MemoryBlockStore::new();
+        for block in blocks {
+            store.put_block(block).unwrap();
+        }
+        let head = blocks.last().unwrap().header.hash();
+        store.put_tag(MAIN_BRANCH_TAG, &head).unwrap();
+        Blockchain::new(Box::new(store), Duration::from_secs(60))
+    }
+
+    fn blockchain(blocks: &[Block], damage: impl FnOnce(&mut DamagedStore)) -> Blockchain {
+        let mut store = DamagedStore {
+            inner: MemoryBlockStore::new(),
+            missing: None,
+            replaced: None,
+        };
+        for block in blocks {
+            store.put_block(block).unwrap();
+        }
+        let head = blocks.last().unwrap().header.hash();
+        store.put_tag(MAIN_BRANCH_TAG, &head).unwrap();
+        store.put_tag(PRUNED_TAG, &blocks[0].header.hash()).unwrap();
+        damage(&mut store);
+        Blockchain::new(Box::new(store), Duration::from_secs(60))
+    }
+
+    fn head(blockchain: &Blockchain) -> Option<HeaderHash> {
+        blockchain
+            .storage()
+            .get_tag(MAIN_BRANCH_TAG.to_owned())
+            .wait()
+            .unwrap()
+    }
+
+    #[test]
+    fn consistent_chain() {
+        let blocks = chain(3);
+        let mut blockchain = blockchain(&blocks, |_| ());
+
+        let report = verify(&mut blockchain, blocks[0].clone()).unwrap();
+
+        assert!(report.is_consistent(), "{}", report);
+        assert!(report.headers_only);
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.last_consistent, blocks[3].header.hash());
+    }
+
+    #[test]
+    fn missing_head_tag() {
+        let block0 = BlockBuilder::new().make_genesis_block();
+        let mut store = MemoryBlockStore::new();
+        store.put_block(&block0).unwrap();
+        let mut blockchain = Blockchain::new(Box::new(store), Duration::from_secs(60));
+
+        let report = verify(&mut blockchain, block0).unwrap();
+
+        match report.issues.as_slice() {
+            [Issue::NoHead] => (),
+            issues => panic!("unexpected issues: {:?}", issues),
+        }
+    }
+
+    #[test]
+    fn missing_block() {
+        let blocks = chain(3);
+        let missing = blocks[2].header.hash();
+        let mut blockchain = blockchain(&blocks, |store| store.missing = Some(missing.clone()));
+
+        let report = verify(&mut blockchain, blocks[0].clone()).unwrap();
+
+        match report.issues.as_slice() {
+            [Issue::MissingBlock {
+                block,
+                child: Some(child),
+            }] => {
+                assert_eq!(*block, missing);
+                assert_eq!(*child, blocks[3].header.hash());
+            }
+            issues => panic!("unexpected issues: {:?}", issues),
+        }
+        assert_eq!(report.checked, 0);
+        assert_eq!(report.last_consistent, blocks[0].header.hash());
+
+        repair(&blockchain, &report).unwrap();
+        assert_eq!(head(&blockchain), Some(blocks[0].header.hash()));
+    }
+
+    #[test]
+    fn broken_parent_link() {
+        let blocks = chain(3);
+        // a block built on the block0 is stored in place of the block 2,
+        // it does not link to the block 1
+        let replaced = blocks[2].header.hash();
+        let other = child_of(&blocks[0], 2, &leader_key());
+        let mut blockchain = blockchain(&blocks, |store| {
+            store.replaced = Some((replaced.clone(), other))
+        });
+
+        let report = verify(&mut blockchain, blocks[0].clone()).unwrap();
+
+        match report.issues.as_slice() {
+            [Issue::InvalidBlock { block, .. }] => assert_eq!(*block, replaced),
+            issues => panic!("unexpected issues: {:?}", issues),
+        }
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.last_consistent, blocks[1].header.hash());
+
+        repair(&blockchain, &report).unwrap();
+        assert_eq!(head(&blockchain), Some(blocks[1].header.hash()));
+    }
+
+    #[test]
+    fn block_rejected_by_the_ledger() {
+        let leader = leader_key();
+        let block0 = valid_block0(&leader);
+        let block1 = child_of(&block0, 1, &leader);
+        // the block 2 is not signed by the BFT leader
+        let block2 = child_of(&block1, 2, &leader_key());
+        let block3 = child_of(&block2, 3, &leader);
+        let blocks = vec![block0, block1, block2, block3];
+        let mut blockchain = archive_blockchain(&blocks);
+
+        let report = verify(&mut blockchain, blocks[0].clone()).unwrap();
+
+        assert!(!report.headers_only);
+        match report.issues.as_slice() {
+            [Issue::InvalidBlock { block, .. }] => assert_eq!(*block, blocks[2].header.hash()),
+            issues => panic!("unexpected issues: {:?}", issues),
+        }
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.last_consistent, blocks[1].header.hash());
+
+        repair(&blockchain, &report).unwrap();
+        assert_eq!(head(&blockchain), Some(blocks[1].header.hash()));
+    }
+
+    #[test]
+    fn consistent_archive() {
+        let leader = leader_key();
+        let mut blocks = vec![valid_block0(&leader)];
+        for slot_id in 1..=3 {
+            let block = child_of(blocks.last().unwrap(), slot_id, &leader);
+            blocks.push(block);
+        }
+        let mut blockchain = archive_blockchain(&blocks);
+
+        let report = verify(&mut blockchain, blocks[0].clone()).unwrap();
+
+        assert!(report.is_consistent(), "{}", report);
+        assert!(!report.headers_only);
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.last_consistent, blocks[3].header.hash());
+    }
+}
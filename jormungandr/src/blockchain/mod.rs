@@ -2,22 +2,27 @@ mod branch;
 mod chain;
 mod chain_selection;
 mod checkpoints;
+pub mod integrity;
 mod multiverse;
 mod process;
+mod pruned_store;
 mod reference;
 mod reference_cache;
+mod rejected;
 mod storage;
 mod tip;
 
 pub use self::{
     branch::{Branch, Branches},
-    chain::{Blockchain, Error, ErrorKind, PreCheckedHeader, MAIN_BRANCH_TAG},
+    chain::{Blockchain, Error, ErrorKind, PreCheckedHeader, MAIN_BRANCH_TAG, MAX_REPLAY_DEPTH},
     chain_selection::{compare_against, ComparisonResult},
     checkpoints::Checkpoints,
     multiverse::Multiverse,
     process::handle_input,
+    pruned_store::{PrunedBlockStore, PRUNED_TAG},
     reference::Ref,
     reference_cache::RefCache,
+    rejected::{RejectedHeaders, REJECTED_HEADERS_CAPACITY, REJECTED_HEADERS_FILE},
     storage::Storage,
     tip::Tip,
 };
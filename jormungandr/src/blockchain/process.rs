@@ -1,11 +1,14 @@
 use super::{
-    compare_against, Blockchain, ComparisonResult, Error, ErrorKind, PreCheckedHeader, Ref, Tip,
+    chain::error_reason,
+    compare_against,
+    rejected::{self, RejectedHeaders},
+    Blockchain, ComparisonResult, Error, ErrorKind, PreCheckedHeader, Ref, Storage, Tip,
     MAIN_BRANCH_TAG,
 };
 use crate::{
     blockcfg::{Block, Epoch, Header, HeaderHash},
     intercom::{self, BlockMsg, ExplorerMsg, NetworkMsg, PropagateMsg, TransactionMsg},
-    leadership::NewEpochToSchedule,
+    leadership::{Logs as LeadershipLogs, NewEpochToSchedule},
     network::p2p::topology::NodeId,
     stats_counter::StatsCounter,
     utils::{
@@ -14,6 +17,7 @@ use crate::{
     },
 };
 use chain_core::property::{Block as _, Fragment as _, HasHeader as _, Header as _};
+use jormungandr_lib::interfaces::LeadershipLogStatus;
 
 use futures::future::Either;
 use slog::Logger;
@@ -26,6 +30,8 @@ pub fn handle_input(
     blockchain: &mut Blockchain,
     blockchain_tip: &mut Tip,
     stats_counter: &StatsCounter,
+    leadership_logs: &LeadershipLogs,
+    rejected_headers: &RejectedHeaders,
     new_epoch_announcements: &mut Sender<NewEpochToSchedule>,
     network_msg_box: &mut MessageBox<NetworkMsg>,
     tx_msg_box: &mut MessageBox<TransactionMsg>,
@@ -74,6 +80,7 @@ pub fn handle_input(
                 logger.clone(),
                 blockchain.clone(),
                 blockchain_tip.clone(),
+                leadership_logs.clone(),
                 new_block_ref.clone(),
             )
             .wait()
@@ -91,24 +98,39 @@ pub fn handle_input(
             stats_counter.add_block_recv_cnt(1);
         }
         BlockMsg::AnnouncedBlock(header, node_id) => {
+            let logger = info.logger().new(o!(
+                "hash" => header.hash().to_string(),
+                "node_id" => node_id.to_string()));
             let future = process_block_announcement(
                 blockchain.clone(),
                 blockchain_tip.clone(),
+                rejected_headers.clone(),
+                stats_counter.clone(),
                 header,
                 node_id,
                 network_msg_box.clone(),
-                info.logger().clone(),
+                logger.clone(),
+            );
+            future.wait().unwrap_or_else(
+                |err| warn!(logger, "block announcement discarded"; "reason" => err.to_string()),
             );
-            future.wait().unwrap();
         }
-        BlockMsg::NetworkBlock(block, reply) => {
+        BlockMsg::NetworkBlock(block, node_id, reply) => {
             let fragment_ids = block.fragments().map(|f| f.id()).collect::<Vec<_>>();
             let logger = info.logger().new(o!(
                 "hash" => block.header.hash().to_string(),
                 "parent" => block.header.parent_id().to_string(),
                 "date" => block.header.block_date().to_string()));
 
-            let future = process_network_block(blockchain.clone(), block.clone(), logger.clone());
+            let future = process_network_block(
+                blockchain.clone(),
+                rejected_headers.clone(),
+                stats_counter.clone(),
+                block.clone(),
+                node_id,
+                network_msg_box.clone(),
+                logger.clone(),
+            );
             match future.wait() {
                 Err(e) => {
                     reply.reply_error(network_block_error_into_reply(e));
@@ -122,6 +144,7 @@ pub fn handle_input(
                             logger.clone(),
                             blockchain.clone(),
                             blockchain_tip.clone(),
+                            leadership_logs.clone(),
                             new_block_ref.clone(),
                         )
                         .wait()
@@ -150,6 +173,7 @@ pub fn handle_input(
             }
         }
         BlockMsg::ChainHeaders(stream) => unimplemented!(),
+        BlockMsg::Flush(reply) => reply.reply_ok(()),
     };
 
     Ok(())
@@ -164,16 +188,22 @@ pub fn handle_input(
 /// If the current tip is not the one being updated we will then trigger
 /// chain selection after updating that other branch as it may be possible that
 /// this branch just became more interesting for the current consensus algorithm.
+///
+/// The outcome of the blocks created by the leadership events is then updated
+/// against the tip of the main branch.
 pub fn process_new_ref(
     logger: Logger,
     mut blockchain: Blockchain,
     mut tip: Tip,
+    leadership_logs: LeadershipLogs,
     candidate: Arc<Ref>,
 ) -> impl Future<Item = (), Error = Error> {
     use tokio::prelude::future::Either::*;
 
     let candidate_hash = candidate.hash();
     let mut storage = blockchain.storage().clone();
+    let storage_logs = storage.clone();
+    let tip_logs = tip.clone();
 
     tip.clone()
         .get_ref()
@@ -208,6 +238,79 @@ pub fn process_new_ref(
                 B(future::ok(()))
             }
         })
+        .and_then(move |()| {
+            tip_logs
+                .get_ref()
+                .map_err(|_: std::convert::Infallible| unreachable!())
+                .and_then(move |tip_ref| {
+                    update_leadership_logs(storage_logs, leadership_logs, tip_ref.hash())
+                })
+        })
+}
+
+/// update the status of the blocks created by the leadership events:
+/// adopted if the block is in the main branch, orphaned otherwise.
+///
+/// The blocks not yet in the storage are left untouched.
+fn update_leadership_logs(
+    storage: Storage,
+    leadership_logs: LeadershipLogs,
+    tip_hash: HeaderHash,
+) -> impl Future<Item = (), Error = Error> {
+    let leadership_logs_update = leadership_logs.clone();
+
+    leadership_logs
+        .produced_blocks()
+        .map_err(|()| unreachable!())
+        .and_then(move |produced_blocks| {
+            stream::iter_ok::<_, Error>(produced_blocks)
+                .and_then(move |(leadership_log_id, block_id)| {
+                    produced_block_status(storage.clone(), block_id, tip_hash.clone())
+                        .map(move |status| status.map(|status| (leadership_log_id, status)))
+                })
+                .filter_map(identity)
+                .collect()
+        })
+        .and_then(move |statuses| {
+            leadership_logs_update
+                .update_statuses(statuses)
+                .map_err(|()| unreachable!())
+        })
+}
+
+fn produced_block_status(
+    storage: Storage,
+    block_id: HeaderHash,
+    tip_hash: HeaderHash,
+) -> impl Future<Item = Option<LeadershipLogStatus>, Error = Error> {
+    if block_id == tip_hash {
+        return Either::A(future::ok(Some(LeadershipLogStatus::Adopted {
+            block_id: block_id.into(),
+        })));
+    }
+
+    let storage_ancestor = storage.clone();
+    Either::B(
+        storage
+            .block_exists(block_id.clone())
+            .and_then(move |exists| {
+                if exists {
+                    Either::A(storage_ancestor.is_ancestor(block_id.clone(), tip_hash).map(
+                        move |distance| match distance {
+                            Some(_) => Some(LeadershipLogStatus::Adopted {
+                                block_id: block_id.into(),
+                            }),
+                            None => Some(LeadershipLogStatus::Orphaned {
+                                block_id: block_id.into(),
+                            }),
+                        },
+                    ))
+                } else {
+                    Either::B(future::ok(None))
+                }
+            })
+            .map_err(|e| Error::with_chain(e, "Cannot check the leadership block in the storage")),
+    )
 }
 
 pub fn handle_end_of_epoch(
@@ -276,95 +379,237 @@ pub fn process_leadership_block(
         })
 }
 
+/// process the header announced by a peer, fetching the block (or the
+/// missing blocks) if needed.
+///
+/// The peer is reported as misbehaving to the network task if the block
+/// has already been rejected or if its header is not valid.
 pub fn process_block_announcement(
     mut blockchain: Blockchain,
     blockchain_tip: Tip,
+    rejected_headers: RejectedHeaders,
+    stats_counter: StatsCounter,
     header: Header,
     node_id: NodeId,
     mut network_msg_box: MessageBox<NetworkMsg>,
     logger: Logger,
 ) -> impl Future<Item = (), Error = Error> {
-    blockchain
-        .pre_check_header(header, false)
-        .and_then(move |pre_checked| match pre_checked {
-            PreCheckedHeader::AlreadyPresent { .. } => {
-                debug!(logger, "block is already present");
-                Either::A(future::ok(()))
-            }
-            PreCheckedHeader::MissingParent { header, .. } => {
-                debug!(logger, "block is missing a locally stored parent");
-                let to = header.hash();
-                Either::B(
-                    blockchain
-                        .get_checkpoints(blockchain_tip.branch().clone())
-                        .map(move |from| {
-                            network_msg_box
-                                .try_send(NetworkMsg::PullHeaders { node_id, from, to })
-                                .unwrap_or_else(move |err| {
-                                    error!(
-                                        logger,
-                                        "cannot send PullHeaders request to network: {}", err
-                                    )
-                                });
-                        }),
+    let block_id = header.hash();
+    let flag_msg_box = network_msg_box.clone();
+    let flag_logger = logger.clone();
+    let rejection_logger = logger.clone();
+
+    check_not_rejected(
+        rejected_headers.clone(),
+        stats_counter.clone(),
+        header,
+        logger.clone(),
+    )
+    .and_then(move |header| {
+        blockchain
+            .pre_check_header(header, false)
+            .and_then(move |pre_checked| match pre_checked {
+                PreCheckedHeader::AlreadyPresent { .. } => {
+                    debug!(logger, "block is already present");
+                    Either::A(future::ok(()))
+                }
+                PreCheckedHeader::MissingParent { header, .. } => {
+                    debug!(logger, "block is missing a locally stored parent");
+                    let to = header.hash();
+                    Either::B(
+                        blockchain
+                            .get_checkpoints(blockchain_tip.branch().clone())
+                            .map(move |from| {
+                                network_msg_box
+                                    .try_send(NetworkMsg::PullHeaders { node_id, from, to })
+                                    .unwrap_or_else(move |err| {
+                                        error!(
+                                            logger,
+                                            "cannot send PullHeaders request to network: {}", err
+                                        )
+                                    });
+                            }),
+                    )
+                }
+                PreCheckedHeader::HeaderWithCache {
+                    header,
+                    parent_ref: _,
+                } => {
+                    debug!(
+                        logger,
+                        "Announced block has a locally stored parent, fetch it"
+                    );
+                    network_msg_box
+                        .try_send(NetworkMsg::GetNextBlock(node_id, header.hash()))
+                        .unwrap_or_else(move |err| {
+                            error!(
+                                logger,
+                                "cannot send GetNextBlock request to network: {}", err
+                            )
+                        });
+                    Either::A(future::ok(()))
+                }
+            })
+            .or_else(move |error| {
+                register_rejection(
+                    rejected_headers,
+                    stats_counter,
+                    block_id,
+                    error,
+                    rejection_logger,
                 )
-            }
-            PreCheckedHeader::HeaderWithCache {
-                header,
-                parent_ref: _,
-            } => {
-                debug!(
+            })
+    })
+    .map_err(move |error| flag_misbehaving_peer(error, node_id, flag_msg_box, &flag_logger))
+}
+
+/// report the peer as misbehaving to the network task if the error is due
+/// to the block it sent being invalid or already rejected, the error is then
+/// returned as is.
+fn flag_misbehaving_peer(
+    error: Error,
+    node_id: NodeId,
+    mut network_msg_box: MessageBox<NetworkMsg>,
+    logger: &Logger,
+) -> Error {
+    let misbehaving = match error.kind() {
+        ErrorKind::BlockRejected(_) => true,
+        _ => rejected::is_invalid_block(&error),
+    };
+    if misbehaving {
+        network_msg_box
+            .try_send(NetworkMsg::MisbehavingPeer(node_id))
+            .unwrap_or_else(|err| {
+                error!(
                     logger,
-                    "Announced block has a locally stored parent, fetch it"
-                );
-                network_msg_box
-                    .try_send(NetworkMsg::GetNextBlock(node_id, header.hash()))
-                    .unwrap_or_else(move |err| {
-                        error!(
-                            logger,
-                            "cannot send GetNextBlock request to network: {}", err
-                        )
-                    });
-                Either::A(future::ok(()))
-            }
-        })
+                    "cannot send MisbehavingPeer notification to network: {}", err
+                )
+            });
+    }
+    error
 }
 
+/// process a block received from a peer, the peer is reported as
+/// misbehaving to the network task if the block has already been rejected
+/// or if it is not valid.
 pub fn process_network_block(
     mut blockchain: Blockchain,
+    rejected_headers: RejectedHeaders,
+    stats_counter: StatsCounter,
     block: Block,
+    node_id: Option<NodeId>,
+    network_msg_box: MessageBox<NetworkMsg>,
     logger: Logger,
 ) -> impl Future<Item = Option<Arc<Ref>>, Error = Error> {
     let mut end_blockchain = blockchain.clone();
     let header = block.header();
-    blockchain
-        .pre_check_header(header, false)
-        .and_then(move |pre_checked| match pre_checked {
-            PreCheckedHeader::AlreadyPresent { .. } => {
-                debug!(logger, "block is already present");
-                Either::A(future::ok(None))
-            }
-            PreCheckedHeader::MissingParent { header, .. } => {
-                debug!(logger, "block is missing a locally stored parent");
-                Either::A(future::err(
-                    ErrorKind::MissingParentBlockFromStorage(header).into(),
-                ))
-            }
-            PreCheckedHeader::HeaderWithCache { header, parent_ref } => {
-                let post_check_and_apply = blockchain
-                    .post_check_header(header, parent_ref)
-                    .and_then(move |post_checked| {
-                        end_blockchain.apply_and_store_block(post_checked, block)
-                    })
-                    .map(move |block_ref| {
-                        info!(logger, "block successfully applied");
-                        Some(block_ref)
-                    });
-                Either::B(post_check_and_apply)
+    let block_id = header.hash();
+    let rejection_logger = logger.clone();
+    let flag_logger = logger.clone();
+
+    check_not_rejected(
+        rejected_headers.clone(),
+        stats_counter.clone(),
+        header,
+        logger.clone(),
+    )
+    .and_then(move |header| {
+        blockchain
+            .pre_check_header(header, false)
+            .and_then(move |pre_checked| match pre_checked {
+                PreCheckedHeader::AlreadyPresent { .. } => {
+                    debug!(logger, "block is already present");
+                    Either::A(future::ok(None))
+                }
+                PreCheckedHeader::MissingParent { header, .. } => {
+                    debug!(logger, "block is missing a locally stored parent");
+                    Either::A(future::err(
+                        ErrorKind::MissingParentBlockFromStorage(header).into(),
+                    ))
+                }
+                PreCheckedHeader::HeaderWithCache { header, parent_ref } => {
+                    let post_check_and_apply = blockchain
+                        .post_check_header(header, parent_ref)
+                        .and_then(move |post_checked| {
+                            end_blockchain.apply_and_store_block(post_checked, block)
+                        })
+                        .map(move |block_ref| {
+                            info!(logger, "block successfully applied");
+                            Some(block_ref)
+                        });
+                    Either::B(post_check_and_apply)
+                }
+            })
+            .or_else(move |error| {
+                register_rejection(
+                    rejected_headers,
+                    stats_counter,
+                    block_id,
+                    error,
+                    rejection_logger,
+                )
+            })
+    })
+    .map_err(move |error| match node_id {
+        Some(node_id) => flag_misbehaving_peer(error, node_id, network_msg_box, &flag_logger),
+        None => error,
+    })
+}
+
+/// fail with `ErrorKind::BlockRejected` if the block, or its parent, has
+/// already been rejected.
+///
+/// The block itself is not registered as rejected: it has not been validated
+/// and a peer could otherwise evict the blocks which actually failed the
+/// validation from the cache by sending descendants of a rejected block.
+fn check_not_rejected(
+    rejected_headers: RejectedHeaders,
+    stats_counter: StatsCounter,
+    header: Header,
+    logger: Logger,
+) -> impl Future<Item = Header, Error = Error> {
+    rejected_headers
+        .check(&header)
+        .map_err(|()| unreachable!())
+        .and_then(move |rejection| match rejection {
+            None => Either::A(future::ok(header)),
+            Some(reason) => {
+                debug!(logger, "block already rejected"; "reason" => reason.as_str());
+                stats_counter.add_block_rejected_replay_cnt(1);
+                Either::B(future::err(ErrorKind::BlockRejected(reason).into()))
             }
         })
 }
 
+/// register the block as rejected if the error is due to the block
+/// being invalid, the error is then returned as is.
+fn register_rejection<T>(
+    rejected_headers: RejectedHeaders,
+    stats_counter: StatsCounter,
+    block_id: HeaderHash,
+    error: Error,
+    logger: Logger,
+) -> impl Future<Item = T, Error = Error> {
+    if !rejected::is_invalid_block(&error) {
+        return Either::A(future::err(error));
+    }
+
+    stats_counter.add_block_rejected_cnt(1);
+    let reason = error_reason(&error);
+    debug!(
+        logger,
+        "block rejected";
+        "block_id" => block_id.to_string(),
+        "reason" => reason.as_str(),
+    );
+    Either::B(
+        rejected_headers
+            .reject(block_id, reason)
+            .map_err(|()| unreachable!())
+            .and_then(move |()| Err(error)),
+    )
+}
+
 fn network_block_error_into_reply(err: Error) -> intercom::Error {
     use super::ErrorKind::*;
 
@@ -374,6 +619,7 @@ fn network_block_error_into_reply(err: Error) -> intercom::Error {
         Block0(e) => intercom::Error::failed(e),
         MissingParentBlockFromStorage(_) => intercom::Error::failed_precondition(err.to_string()),
         BlockHeaderVerificationFailed(_) => intercom::Error::invalid_argument(err.to_string()),
+        BlockRejected(_) => intercom::Error::invalid_argument(err.to_string()),
         _ => intercom::Error::failed(err.to_string()),
     }
 }
@@ -0,0 +1,206 @@
+//! block store keeping only the most recent block bodies
+//!
+//! The headers (and the associated `BlockInfo`) of every block are kept in
+//! the wrapped store so the structure of the chain can still be walked
+//! (ancestors, checkpoints...). The bodies of the blocks are kept in a
+//! separate directory, one sub-directory per epoch, and the sub-directories
+//! of the epochs falling out of the retention window are removed every
+//! time the store sees a block of a new epoch.
+//!
+//! ```text
+//! <storage>/bodies/<epoch>/<block hash>
+//! ```
+//!
+//! The block0 is always kept in full in the wrapped store.
+
+use crate::{
+    blockcfg::{Block, Epoch, Header, HeaderHash},
+    start_up::NodeBlockStore,
+};
+use chain_core::property::{Deserialize as _, Serialize as _};
+use chain_impl_mockchain::block::Contents;
+use chain_storage::{
+    error::Error as StorageError,
+    store::{BlockInfo, BlockStore},
+};
+use std::{fs, io, path::PathBuf, str::FromStr as _};
+
+/// tag pointing to the most recent block whose body has been pruned.
+///
+/// If the tag is present in the storage it is not possible to replay
+/// the blockchain from the block0 up to the `HEAD` from the local storage
+/// only.
+pub const PRUNED_TAG: &str = "PRUNED";
+
+pub struct PrunedBlockStore<S> {
+    inner: S,
+
+    /// directory where the bodies of the blocks are stored
+    bodies: PathBuf,
+
+    /// number of epochs to keep the block bodies of (including the current one)
+    keep_epochs: u32,
+
+    /// the most recent epoch seen by the store, bodies are pruned
+    /// every time we see a block of a more recent epoch
+    last_epoch: Option<Epoch>,
+}
+
+impl<S> PrunedBlockStore<S>
+where
+    S: BlockStore<Block = Block>,
+{
+    /// wrap the given store, the bodies of the blocks will be stored in
+    /// `bodies`. At least the current epoch's bodies will be kept.
+    pub fn new(inner: S, bodies: PathBuf, keep_epochs: u32) -> io::Result<Self> {
+        fs::create_dir_all(&bodies)?;
+        Ok(PrunedBlockStore {
+            inner,
+            bodies,
+            keep_epochs: std::cmp::max(1, keep_epochs),
+            last_epoch: None,
+        })
+    }
+
+    fn epoch_dir(&self, epoch: Epoch) -> PathBuf {
+        self.bodies.join(epoch.to_string())
+    }
+
+    fn body_path(&self, epoch: Epoch, block_hash: &HeaderHash) -> PathBuf {
+        self.epoch_dir(epoch).join(block_hash.to_string())
+    }
+
+    fn put_body(&self, block: &Block) -> Result<(), StorageError> {
+        let epoch = block.header.block_date().epoch;
+        fs::create_dir_all(self.epoch_dir(epoch)).map_err(backend_error)?;
+        let bytes = block.serialize_as_vec().map_err(backend_error)?;
+        fs::write(self.body_path(epoch, &block.header.hash()), bytes).map_err(backend_error)
+    }
+
+    fn get_body(&self, stub: &Block) -> Result<Block, StorageError> {
+        let epoch = stub.header.block_date().epoch;
+        let file = match fs::File::open(self.body_path(epoch, &stub.header.hash())) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(StorageError::BlockNotFound)
+            }
+            Err(err) => return Err(backend_error(err)),
+            Ok(file) => file,
+        };
+        Block::deserialize(io::BufReader::new(file)).map_err(backend_error)
+    }
+
+    /// remove the bodies of all the epochs out of the retention window
+    /// and update the `PRUNED_TAG` accordingly.
+    fn prune(&mut self, current_epoch: Epoch) -> Result<(), StorageError> {
+        let oldest_kept = (current_epoch + 1).saturating_sub(self.keep_epochs);
+        let mut last_pruned: Option<BlockInfo<HeaderHash>> = None;
+
+        for entry in fs::read_dir(&self.bodies).map_err(backend_error)? {
+            let entry = entry.map_err(backend_error)?;
+            let epoch = match entry.file_name().to_str().map(str::parse::<Epoch>) {
+                Some(Ok(epoch)) => epoch,
+                _ => continue,
+            };
+            if epoch >= oldest_kept {
+                continue;
+            }
+
+            for body in fs::read_dir(entry.path()).map_err(backend_error)? {
+                let body = body.map_err(backend_error)?;
+                let hash = match body.file_name().to_str().map(HeaderHash::from_str) {
+                    Some(Ok(hash)) => hash,
+                    _ => continue,
+                };
+                let info = self.inner.get_block_info(&hash)?;
+                if last_pruned.as_ref().map_or(true, |last| last.depth < info.depth) {
+                    last_pruned = Some(info);
+                }
+            }
+
+            fs::remove_dir_all(entry.path()).map_err(backend_error)?;
+        }
+
+        if let Some(info) = last_pruned {
+            self.inner.put_tag(PRUNED_TAG, &info.block_hash)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> BlockStore for PrunedBlockStore<S>
+where
+    S: BlockStore<Block = Block>,
+{
+    type Block = Block;
+
+    fn put_block_internal(
+        &mut self,
+        block: &Block,
+        block_info: BlockInfo<HeaderHash>,
+    ) -> Result<(), StorageError> {
+        if block_info.depth == 0 {
+            return self.inner.put_block_internal(block, block_info);
+        }
+
+        let epoch = block.header.block_date().epoch;
+        if self.last_epoch.map_or(true, |last_epoch| last_epoch < epoch) {
+            self.prune(epoch)?;
+            self.last_epoch = Some(epoch);
+        }
+
+        self.put_body(block)?;
+
+        let stub = Block {
+            header: block.header.clone(),
+            contents: Contents::empty(),
+        };
+        self.inner.put_block_internal(&stub, block_info)
+    }
+
+    fn get_block(
+        &self,
+        block_hash: &HeaderHash,
+    ) -> Result<(Block, BlockInfo<HeaderHash>), StorageError> {
+        let (stub, block_info) = self.inner.get_block(block_hash)?;
+        if block_info.depth == 0 {
+            Ok((stub, block_info))
+        } else {
+            self.get_body(&stub).map(|block| (block, block_info))
+        }
+    }
+
+    fn get_block_info(&self, block_hash: &HeaderHash) -> Result<BlockInfo<HeaderHash>, StorageError> {
+        self.inner.get_block_info(block_hash)
+    }
+
+    fn block_exists(&self, block_hash: &HeaderHash) -> Result<bool, StorageError> {
+        self.inner.block_exists(block_hash)
+    }
+
+    fn put_tag(&mut self, tag_name: &str, block_hash: &HeaderHash) -> Result<(), StorageError> {
+        self.inner.put_tag(tag_name, block_hash)
+    }
+
+    fn get_tag(&self, tag_name: &str) -> Result<Option<HeaderHash>, StorageError> {
+        self.inner.get_tag(tag_name)
+    }
+}
+
+impl<S> NodeBlockStore for PrunedBlockStore<S>
+where
+    S: BlockStore<Block = Block>,
+{
+    /// the header is read from the stub kept in the wrapped store
+    fn get_header(&self, block_hash: &HeaderHash) -> Result<Header, StorageError> {
+        self.inner
+            .get_block(block_hash)
+            .map(|(stub, _)| stub.header)
+    }
+}
+
+fn backend_error<E>(error: E) -> StorageError
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    StorageError::BackendError(Box::new(error))
+}
@@ -0,0 +1,227 @@
+//! bounded cache of the blocks rejected by the validation
+//!
+//! An adversary may propose the same invalid blocks (or forks built on top
+//! of them) again and again. Keeping the hashes of the rejected blocks allows
+//! to drop them, and the blocks built directly on top of them, without
+//! loading their parent and checking them again. Only the direct children
+//! are matched: the further descendants cannot be applied anyway since their
+//! parent is never stored.
+//!
+//! The cache is saved in the storage directory so it is kept across restarts
+//! of the node. The newly rejected blocks are written every
+//! [`REJECTED_HEADERS_FLUSH_INTERVAL`] and when the node shuts down:
+//!
+//! ```text
+//! <storage>/rejected_headers
+//! ```
+//!
+//! with one `<block hash> <reason>` line per rejected block, the most recently
+//! rejected last.
+//!
+//! [`REJECTED_HEADERS_FLUSH_INTERVAL`]: ./constant.REJECTED_HEADERS_FLUSH_INTERVAL.html
+
+use super::{Error, ErrorKind};
+use crate::{
+    blockcfg::{Header, HeaderHash},
+    utils::fs::write_atomically,
+};
+use linked_hash_map::LinkedHashMap;
+use slog::Logger;
+use std::{
+    fs, io,
+    path::PathBuf,
+    str::FromStr as _,
+    time::{Duration, Instant},
+};
+use tokio::{
+    prelude::*,
+    sync::lock::{Lock, LockGuard},
+    timer::Interval,
+};
+
+/// name of the file, in the storage directory, the rejected blocks are saved in
+pub const REJECTED_HEADERS_FILE: &str = "rejected_headers";
+
+/// maximum number of rejected blocks to keep, the oldest rejected
+/// blocks are forgotten first.
+pub const REJECTED_HEADERS_CAPACITY: usize = 4096;
+
+/// interval between 2 writes of the newly rejected blocks in the file
+pub const REJECTED_HEADERS_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct RejectedHeaders {
+    inner: Lock<internal::RejectedHeaders>,
+}
+
+impl RejectedHeaders {
+    /// create a cache keeping at most `capacity` rejected blocks.
+    ///
+    /// If a `path` is given, the rejected blocks previously saved are loaded
+    /// from the file (if it exists) and the cache is saved to this file when
+    /// it is flushed.
+    pub fn load(capacity: usize, path: Option<PathBuf>) -> io::Result<Self> {
+        let mut inner = internal::RejectedHeaders::new(capacity, path);
+        inner.load()?;
+        Ok(RejectedHeaders {
+            inner: Lock::new(inner),
+        })
+    }
+
+    /// returns the reason of the rejection if the block, or its parent,
+    /// has already been rejected.
+    pub fn check(&self, header: &Header) -> impl Future<Item = Option<String>, Error = ()> {
+        let block_id = header.hash();
+        let parent_id = header.block_parent_hash().clone();
+        self.inner().map(move |guard| {
+            guard
+                .get(&block_id)
+                .or_else(|| guard.get(&parent_id))
+                .map(str::to_owned)
+        })
+    }
+
+    /// register the block as rejected, the cache is saved at the next
+    /// flush. Nothing is done if the block was already rejected.
+    pub fn reject(
+        &self,
+        block_id: HeaderHash,
+        reason: String,
+    ) -> impl Future<Item = (), Error = ()> {
+        self.inner()
+            .map(move |mut guard| guard.insert(block_id, reason))
+    }
+
+    /// save the cache if blocks have been rejected since the last flush
+    pub fn flush(&self) -> impl Future<Item = (), Error = io::Error> {
+        self.inner().and_then(|mut guard| guard.flush())
+    }
+
+    /// flush the cache every `REJECTED_HEADERS_FLUSH_INTERVAL`
+    pub fn flush_periodically(self, logger: Logger) -> impl Future<Item = (), Error = ()> {
+        let error_logger = logger.clone();
+        Interval::new(
+            Instant::now() + REJECTED_HEADERS_FLUSH_INTERVAL,
+            REJECTED_HEADERS_FLUSH_INTERVAL,
+        )
+        .map_err(move |error| {
+            error!(error_logger, "cannot run the periodic save of the rejected blocks" ; "reason" => error.to_string());
+        })
+        .for_each(move |_instant| {
+            let logger = logger.clone();
+            self.flush().then(move |result| {
+                if let Err(error) = result {
+                    warn!(logger, "cannot save the rejected blocks" ; "reason" => error.to_string());
+                }
+                Ok(())
+            })
+        })
+    }
+
+    fn inner<E>(&self) -> impl Future<Item = LockGuard<internal::RejectedHeaders>, Error = E> {
+        let mut lock = self.inner.clone();
+        future::poll_fn(move || Ok(lock.poll_lock()))
+    }
+}
+
+/// tells if the error is due to the block being invalid (and not, for
+/// example, to a missing parent or a storage failure): only these blocks
+/// are added to the cache.
+pub fn is_invalid_block(error: &Error) -> bool {
+    match error.kind() {
+        ErrorKind::BlockHeaderVerificationFailed(_) | ErrorKind::CannotApplyBlock => true,
+        _ => false,
+    }
+}
+
+mod internal {
+    use super::*;
+
+    pub struct RejectedHeaders {
+        entries: LinkedHashMap<HeaderHash, String>,
+        capacity: usize,
+        path: Option<PathBuf>,
+        /// set if blocks have been rejected since the last save
+        dirty: bool,
+    }
+
+    impl RejectedHeaders {
+        pub fn new(capacity: usize, path: Option<PathBuf>) -> Self {
+            RejectedHeaders {
+                entries: LinkedHashMap::new(),
+                capacity: std::cmp::max(1, capacity),
+                path,
+                dirty: false,
+            }
+        }
+
+        pub fn get(&self, block_id: &HeaderHash) -> Option<&str> {
+            self.entries.get(block_id).map(String::as_str)
+        }
+
+        pub fn insert(&mut self, block_id: HeaderHash, reason: String) {
+            if self.entries.contains_key(&block_id) {
+                return;
+            }
+            // the reasons are saved one per line
+            let reason = reason.replace('\n', " ");
+            self.entries.insert(block_id, reason);
+            while self.entries.len() > self.capacity {
+                self.entries.pop_front();
+            }
+            self.dirty = true;
+        }
+
+        pub fn flush(&mut self) -> io::Result<()> {
+            if self.dirty {
+                self.save()?;
+                self.dirty = false;
+            }
+            Ok(())
+        }
+
+        pub fn load(&mut self) -> io::Result<()> {
+            let content = match &self.path {
+                None => return Ok(()),
+                Some(path) => match fs::read_to_string(path) {
+                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+                    Err(err) => return Err(err),
+                    Ok(content) => content,
+                },
+            };
+
+            for line in content.lines() {
+                let mut fields = line.splitn(2, ' ');
+                let block_id = fields.next().map(HeaderHash::from_str);
+                let reason = fields.next().unwrap_or_default();
+                match block_id {
+                    Some(Ok(block_id)) => {
+                        self.entries.insert(block_id, reason.to_owned());
+                    }
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("invalid rejected block entry: '{}'", line),
+                        ))
+                    }
+                }
+            }
+            while self.entries.len() > self.capacity {
+                self.entries.pop_front();
+            }
+            Ok(())
+        }
+
+        fn save(&self) -> io::Result<()> {
+            let path = match &self.path {
+                None => return Ok(()),
+                Some(path) => path,
+            };
+            let mut content = String::new();
+            for (block_id, reason) in self.entries.iter() {
+                content.push_str(&format!("{} {}\n", block_id, reason));
+            }
+            write_atomically(path, content.as_bytes())
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    blockcfg::{Block, HeaderHash},
+    blockcfg::{Block, Header, HeaderHash},
     start_up::NodeStorage,
 };
 use chain_storage::{
@@ -80,6 +80,23 @@ impl Storage {
         })
     }
 
+    /// get the header of a block, it is available even if the body of the
+    /// block has been pruned
+    pub fn get_header(
+        &self,
+        header_hash: HeaderHash,
+    ) -> impl Future<Item = Option<Header>, Error = StorageError> {
+        let mut inner = self.inner.clone();
+
+        future::poll_fn(move || Ok(inner.poll_lock())).and_then(move |guard| {
+            match guard.get_header(&header_hash) {
+                Err(StorageError::BlockNotFound) => future::ok(None),
+                Err(error) => future::err(error),
+                Ok(header) => future::ok(Some(header)),
+            }
+        })
+    }
+
     pub fn get_with_info(
         &self,
         header_hash: HeaderHash,
@@ -95,6 +112,21 @@ impl Storage {
         })
     }
 
+    pub fn get_block_info(
+        &self,
+        header_hash: HeaderHash,
+    ) -> impl Future<Item = Option<BlockInfo<HeaderHash>>, Error = StorageError> {
+        let mut inner = self.inner.clone();
+
+        future::poll_fn(move || Ok(inner.poll_lock())).and_then(move |guard| {
+            match guard.get_block_info(&header_hash) {
+                Err(StorageError::BlockNotFound) => future::ok(None),
+                Err(error) => future::err(error),
+                Ok(block_info) => future::ok(Some(block_info)),
+            }
+        })
+    }
+
     pub fn block_exists(
         &self,
         header_hash: HeaderHash,
@@ -110,6 +142,44 @@ impl Storage {
         })
     }
 
+    /// returns the distance between the two blocks if `ancestor` is
+    /// an ancestor of `descendant`
+    pub fn is_ancestor(
+        &self,
+        ancestor: HeaderHash,
+        descendant: HeaderHash,
+    ) -> impl Future<Item = Option<u64>, Error = StorageError> {
+        let mut inner = self.inner.clone();
+
+        future::poll_fn(move || Ok(inner.poll_lock())).and_then(move |guard| {
+            match guard.is_ancestor(&ancestor, &descendant) {
+                Err(error) => future::err(error),
+                Ok(distance) => future::ok(distance),
+            }
+        })
+    }
+
+    /// returns the hash of the ancestor of the given block at the given
+    /// depth (the chain length of the ancestor), `None` if the given block
+    /// is not deeper than `depth`
+    pub fn get_ancestor_at_depth(
+        &self,
+        header_hash: HeaderHash,
+        depth: u64,
+    ) -> impl Future<Item = Option<HeaderHash>, Error = StorageError> {
+        let mut inner = self.inner.clone();
+
+        future::poll_fn(move || Ok(inner.poll_lock())).and_then(move |store| {
+            let info = store.get_block_info(&header_hash)?;
+            if info.depth < depth {
+                return Ok(None);
+            }
+            let ancestor =
+                for_path_to_nth_ancestor(&*store, &header_hash, info.depth - depth, |_| {})?;
+            Ok(Some(ancestor.block_hash))
+        })
+    }
+
     pub fn put_block(&mut self, block: Block) -> impl Future<Item = (), Error = StorageError> {
         let mut inner = self.inner.clone();
 
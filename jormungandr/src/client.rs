@@ -1,9 +1,9 @@
 use crate::blockcfg::{Block, Header, HeaderHash};
 use crate::blockchain::{Storage, Tip};
 use crate::intercom::{do_stream_reply, ClientMsg, Error, ReplyStreamHandle};
+use crate::start_up::NodeStorage;
 use crate::utils::task::{Input, ThreadServiceInfo};
-use chain_core::property::HasHeader;
-use chain_storage::store;
+use chain_storage::{error::Error as StorageError, store};
 use tokio::prelude::*;
 
 pub struct TaskData {
@@ -60,7 +60,7 @@ fn handle_get_block_tip(blockchain_tip: &Tip) -> Result<Header, Error> {
     Ok(blockchain_tip.header().clone())
 }
 
-const MAX_HEADERS: usize = 2000;
+pub const MAX_HEADERS: usize = 2000;
 
 fn find_latest_checkpoint(
     checkpoints: &[HeaderHash],
@@ -108,8 +108,8 @@ fn handle_get_headers_range(
         match x {
             Err(err) => return Err(Error::from(err)),
             Ok(info) => {
-                let (block, _) = storage.get_block(&info.block_hash)?;
-                if let Err(_) = reply.send(block.header()) {
+                let header = get_stored_header(&storage, &info.block_hash)?;
+                if let Err(_) = reply.send(header) {
                     break;
                 }
                 header_count += 1;
@@ -136,7 +136,7 @@ fn handle_get_blocks_range(
 
     for x in store::iterate_range(&*storage, &from, &to)? {
         let info = x?;
-        let (blk, _) = storage.get_block(&info.block_hash)?;
+        let blk = get_stored_block(&storage, &info.block_hash)?;
         if let Err(_) = reply.send(blk) {
             break;
         }
@@ -156,7 +156,7 @@ fn handle_get_blocks(
                 break;
             }
         } else {
-            // TODO: reply this hash was not found?
+            return Err(block_not_available(storage, id)?);
         }
     }
 
@@ -169,18 +169,64 @@ fn handle_get_headers(
     reply: &mut ReplyStreamHandle<Header>,
 ) -> Result<(), Error> {
     for id in ids.into_iter() {
-        if let Some(blk) = storage.get(id).wait()? {
-            if let Err(_) = reply.send(blk.header()) {
+        if let Some(header) = storage.get_header(id).wait()? {
+            if let Err(_) = reply.send(header) {
                 break;
             }
         } else {
-            // TODO: reply this hash was not found?
+            return Err(not_found(&id));
         }
     }
 
     Ok(())
 }
 
+/// get a block the storage knows about (found by iterating on the storage),
+/// fails if the body of the block has been pruned
+fn get_stored_block(storage: &NodeStorage, block_id: &HeaderHash) -> Result<Block, Error> {
+    match storage.get_block(block_id) {
+        Ok((block, _)) => Ok(block),
+        Err(StorageError::BlockNotFound) => Err(pruned(block_id)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// get the header of a block the storage knows about (found by iterating
+/// on the storage), the headers are kept when the bodies are pruned
+fn get_stored_header(storage: &NodeStorage, block_id: &HeaderHash) -> Result<Header, Error> {
+    match storage.get_header(block_id) {
+        Ok(header) => Ok(header),
+        Err(StorageError::BlockNotFound) => Err(not_found(block_id)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// the error to reply for a block which cannot be read from the storage,
+/// tells the block apart from the blocks whose body has been pruned
+fn block_not_available(storage: &Storage, block_id: HeaderHash) -> Result<Error, Error> {
+    if storage.block_exists(block_id.clone()).wait()? {
+        Ok(pruned(&block_id))
+    } else {
+        Ok(not_found(&block_id))
+    }
+}
+
+fn not_found(block_id: &HeaderHash) -> Error {
+    Error::not_found(format!(
+        "block {} is not available in the storage",
+        block_id
+    ))
+}
+
+/// the body of the block is not available anymore, only its header can
+/// be served
+fn pruned(block_id: &HeaderHash) -> Error {
+    Error::not_found(format!(
+        "the body of block {} has been pruned from the storage of the node",
+        block_id
+    ))
+}
+
 fn handle_pull_blocks_to_tip(
     storage: &Storage,
     block0_hash: &HeaderHash,
@@ -195,7 +241,7 @@ fn handle_pull_blocks_to_tip(
     let storage = storage.get_inner().wait().unwrap();
     for x in store::iterate_range(&*storage, &from, &tip.hash())? {
         let info = x?;
-        let (blk, _) = storage.get_block(&info.block_hash)?;
+        let blk = get_stored_block(&storage, &info.block_hash)?;
         if let Err(_) = reply.send(blk) {
             break;
         }
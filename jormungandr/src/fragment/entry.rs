@@ -2,6 +2,7 @@ use crate::{
     blockcfg::{Value, ValueError},
     fragment::{Fragment, FragmentId},
 };
+use jormungandr_lib::interfaces::FragmentOrigin;
 use std::time::SystemTime;
 
 pub struct PoolEntry {
@@ -14,6 +15,8 @@ pub struct PoolEntry {
     fragment_size: usize,
     /// time when the entry was added to the pool
     received_at: SystemTime,
+    /// where the fragment has been received from
+    origin: FragmentOrigin,
     /// the fee of the accumulated descendant fragments
     /// does not include the fee of this entry
     descendants_fee: Value,
@@ -29,7 +32,13 @@ pub struct PoolEntry {
 }
 
 impl PoolEntry {
-    pub fn new(fragment: &Fragment) -> Self {
+    pub fn new(fragment: &Fragment, origin: FragmentOrigin) -> Self {
+        Self::restored(fragment, origin, SystemTime::now())
+    }
+
+    /// entry of a fragment received at the given time, before a restart
+    /// of the node
+    pub fn restored(fragment: &Fragment, origin: FragmentOrigin, received_at: SystemTime) -> Self {
         let raw = fragment.to_raw();
         let fragment_size = raw.size_bytes_plus_size();
         let fragment_ref = raw.id();
@@ -43,7 +52,8 @@ impl PoolEntry {
             fragment_ref: fragment_ref,
             fragment_fee: fragment_fee,
             fragment_size: fragment_size,
-            received_at: SystemTime::now(),
+            received_at,
+            origin,
 
             // when this entry is added in the pool, it has no
             // descendant
@@ -74,6 +84,10 @@ impl PoolEntry {
         &self.received_at
     }
     #[inline]
+    pub fn origin(&self) -> FragmentOrigin {
+        self.origin
+    }
+    #[inline]
     pub fn with_descendants_fee(&self) -> Result<Value, ValueError> {
         self.descendants_fee + self.fragment_fee
     }
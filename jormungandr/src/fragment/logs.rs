@@ -67,6 +67,12 @@ impl Logs {
         self.run_on_inner(move |inner| inner.remove(&fragment_id.into()))
     }
 
+    /// set the time to live of the logs, the logs already registered keep
+    /// their current expiration time until they are modified.
+    pub fn set_ttl(&self, ttl: Duration) -> impl Future<Item = (), Error = ()> {
+        self.run_on_inner(move |inner| inner.set_ttl(ttl))
+    }
+
     pub fn poll_purge(&mut self) -> impl Future<Item = (), Error = timer::Error> {
         self.inner()
             .and_then(move |mut guard| future::poll_fn(move || guard.poll_purge()))
@@ -119,6 +125,10 @@ pub(super) mod internal {
             }
         }
 
+        pub fn set_ttl(&mut self, ttl: Duration) {
+            self.ttl = ttl;
+        }
+
         pub fn exists(&self, fragment_id: &Hash) -> bool {
             self.entries.contains_key(fragment_id)
         }
@@ -1,5 +1,6 @@
 mod entry;
 mod logs;
+pub mod persistence;
 mod pool;
 mod process;
 pub mod selection;
@@ -0,0 +1,201 @@
+//! saving and loading of the fragments pending in the pool
+//!
+//! If `mempool.persistent` is set in the node configuration, the fragments
+//! pending in the pool are saved in the storage directory when the node
+//! shuts down, and every `mempool.persistence_interval` if it is set:
+//!
+//! ```text
+//! <storage>/mempool
+//! ```
+//!
+//! The fragments are saved in the order they have been received, along with
+//! the time they have been received at and where they have been received from.
+//! They are loaded back in the pool when the node starts.
+
+use crate::fragment::{Fragment, PoolEntry};
+use chain_core::property::{Deserialize as _, Serialize as _};
+use jormungandr_lib::{interfaces::FragmentOrigin, time::SystemTime};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{self, Duration},
+};
+
+/// name of the file, in the storage directory, the pending fragments are
+/// saved in
+pub const MEMPOOL_FILE: &str = "mempool";
+
+custom_error! {pub Error
+    Io { source: io::Error } = "cannot access the mempool file",
+    Encoding { source: bincode::ErrorKind } = "cannot encode the pending fragments",
+    Decoding { source: bincode::ErrorKind } = "cannot decode the mempool file",
+    InvalidFragment { source: io::Error } = "cannot decode a fragment of the mempool file",
+}
+
+impl From<bincode::Error> for Error {
+    fn from(source: bincode::Error) -> Self {
+        Error::Encoding { source: *source }
+    }
+}
+
+/// where and how often the pending fragments are saved
+pub struct Settings {
+    pub path: PathBuf,
+    /// interval between 2 saves of the pending fragments, they are only
+    /// saved when the node shuts down if it is not set
+    pub interval: Option<Duration>,
+}
+
+/// a pending fragment, as saved in the mempool file
+#[derive(Serialize, Deserialize)]
+struct PersistedFragment {
+    received_at: SystemTime,
+    origin: FragmentOrigin,
+    /// the fragment in its binary format
+    fragment: Vec<u8>,
+}
+
+/// a pending fragment loaded from the mempool file
+pub struct SavedFragment {
+    pub received_at: time::SystemTime,
+    pub origin: FragmentOrigin,
+    pub fragment: Fragment,
+}
+
+/// save the given pending fragments in the file, returns the number of
+/// fragments saved
+pub fn save<'a>(
+    path: &Path,
+    fragments: impl IntoIterator<Item = (&'a PoolEntry, &'a Fragment)>,
+) -> Result<usize, Error> {
+    let fragments = fragments
+        .into_iter()
+        .map(|(entry, fragment)| {
+            Ok(PersistedFragment {
+                received_at: (*entry.received_at()).into(),
+                origin: entry.origin(),
+                fragment: fragment.serialize_as_vec()?,
+            })
+        })
+        .collect::<Result<Vec<_>, io::Error>>()?;
+    let content = bincode::serialize(&fragments)?;
+    crate::utils::fs::write_atomically(path, &content)?;
+    Ok(fragments.len())
+}
+
+/// load the fragments saved in the file, in the order they have been
+/// received. No fragment is returned if the file does not exist.
+pub fn load(path: &Path) -> Result<Vec<SavedFragment>, Error> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => return Err(Error::Io { source }),
+    };
+    let fragments: Vec<PersistedFragment> =
+        bincode::deserialize(&content).map_err(|source| Error::Decoding { source: *source })?;
+    fragments
+        .into_iter()
+        .map(|persisted| {
+            Ok(SavedFragment {
+                received_at: persisted.received_at.into(),
+                origin: persisted.origin,
+                fragment: Fragment::deserialize(persisted.fragment.as_slice())
+                    .map_err(|source| Error::InvalidFragment { source })?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockcfg::{ConfigParam, ConfigParams};
+    use chain_core::property::Fragment as _;
+    use std::{env, process};
+
+    fn fragment(slots_per_epoch: u32) -> Fragment {
+        let mut params = ConfigParams::new();
+        params.push(ConfigParam::SlotsPerEpoch(slots_per_epoch));
+        Fragment::Initial(params)
+    }
+
+    /// a path in the temporary directory that is removed at the end of the test
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!("jormungandr-{}-{}", name, process::id()));
+            let _ = fs::remove_file(&path);
+            TempPath(path)
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = TempPath::new("mempool-save-and-load");
+        let fragments = vec![fragment(10), fragment(20)];
+        let received_at = time::SystemTime::now() - Duration::from_secs(60);
+        let entries = vec![
+            PoolEntry::restored(&fragments[0], FragmentOrigin::Network, received_at),
+            PoolEntry::new(&fragments[1], FragmentOrigin::Rest),
+        ];
+
+        let saved = save(&path.0, entries.iter().zip(fragments.iter())).unwrap();
+        assert_eq!(saved, 2);
+
+        let loaded = load(&path.0).unwrap();
+        assert_eq!(loaded.len(), 2);
+        for ((loaded, entry), fragment) in loaded.iter().zip(&entries).zip(&fragments) {
+            assert_eq!(loaded.fragment.id(), fragment.id());
+            assert_eq!(&loaded.received_at, entry.received_at());
+            assert_eq!(loaded.origin, entry.origin());
+        }
+    }
+
+    #[test]
+    fn save_nothing() {
+        let path = TempPath::new("mempool-save-nothing");
+        let nothing: Vec<(&PoolEntry, &Fragment)> = Vec::new();
+        assert_eq!(save(&path.0, nothing).unwrap(), 0);
+        assert!(load(&path.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn load_missing_file() {
+        let path = TempPath::new("mempool-load-missing-file");
+        assert!(load(&path.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn load_corrupt_file() {
+        let path = TempPath::new("mempool-load-corrupt-file");
+        fs::write(&path.0, b"not a mempool file").unwrap();
+        match load(&path.0) {
+            Err(Error::Decoding { .. }) => (),
+            Err(error) => panic!("unexpected error: {}", error),
+            Ok(_) => panic!("corrupt mempool file loaded"),
+        }
+    }
+
+    #[test]
+    fn load_invalid_fragment() {
+        let path = TempPath::new("mempool-load-invalid-fragment");
+        let persisted = vec![PersistedFragment {
+            received_at: time::SystemTime::now().into(),
+            origin: FragmentOrigin::Rest,
+            fragment: vec![0xff; 4],
+        }];
+        fs::write(&path.0, bincode::serialize(&persisted).unwrap()).unwrap();
+        match load(&path.0) {
+            Err(Error::InvalidFragment { .. }) => (),
+            Err(error) => panic!("unexpected error: {}", error),
+            Ok(_) => panic!("invalid fragment loaded"),
+        }
+    }
+}
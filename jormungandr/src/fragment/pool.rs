@@ -1,14 +1,28 @@
 use crate::{
-    blockcfg::{HeaderContentEvalContext, Ledger, LedgerParameters},
-    fragment::{selection::FragmentSelectionAlgorithm, Fragment, FragmentId, Logs},
+    blockcfg::{HeaderContentEvalContext, HeaderHash, Ledger, LedgerParameters},
+    blockchain::{Ref, Tip},
+    fragment::{
+        persistence::{self, SavedFragment},
+        selection::{ledger_error_reason, FragmentSelectionAlgorithm},
+        Fragment, FragmentId, Logs,
+    },
     intercom::{NetworkMsg, PropagateMsg},
     utils::async_msg::MessageBox,
 };
-use chain_core::property::Fragment as _;
-use chain_impl_mockchain::transaction::AuthenticatedTransaction;
-use jormungandr_lib::interfaces::{BlockDate, FragmentLog, FragmentOrigin, FragmentStatus};
+use chain_core::property::{ChainLength as _, Fragment as _};
+use chain_crypto::{Ed25519, PublicKey, Verification};
+use chain_impl_mockchain::{
+    account::{self, SpendingCounter},
+    transaction::{
+        AccountIdentifier, AuthenticatedTransaction, Input, InputEnum, TransactionId,
+        TransactionIndex, TransactionSignDataHash, UtxoPointer, Witness, WitnessAccountData,
+    },
+};
+use jormungandr_lib::interfaces::{
+    BlockDate, FragmentLog, FragmentOrigin, FragmentStatus, FragmentSubmission, SubmissionStatus,
+};
 use slog::Logger;
-use std::time::Duration;
+use std::{collections::HashMap, convert::Infallible, path::PathBuf, time::Duration};
 use tokio::{
     prelude::{
         future::{
@@ -26,14 +40,21 @@ pub struct Pool {
     logs: Logs,
     pool: Lock<internal::Pool>,
     network_msg_box: MessageBox<NetworkMsg>,
+    tip: Tip,
 }
 
 impl Pool {
-    pub fn new(ttl: Duration, logs: Logs, network_msg_box: MessageBox<NetworkMsg>) -> Self {
+    pub fn new(
+        ttl: Duration,
+        logs: Logs,
+        network_msg_box: MessageBox<NetworkMsg>,
+        tip: Tip,
+    ) -> Self {
         Pool {
             logs,
             pool: Lock::new(internal::Pool::new(ttl)),
             network_msg_box,
+            tip,
         }
     }
 
@@ -41,38 +62,79 @@ impl Pool {
         &self.logs
     }
 
-    /// Returns number of registered fragments
+    /// Returns the result of the submission of every fragment, in the order
+    /// of the given fragments
+    ///
+    /// The fragments spending an input already spent by a pending fragment,
+    /// or that cannot be applied on the ledger of the tip with the pending
+    /// fragments, are not registered nor propagated, they are logged as
+    /// rejected.
     pub fn insert_and_propagate_all(
         &mut self,
         origin: FragmentOrigin,
-        mut fragments: Vec<Fragment>,
+        fragments: Vec<Fragment>,
         logger: Logger,
-    ) -> impl Future<Item = usize, Error = ()> {
-        fragments.retain(is_fragment_valid);
+    ) -> impl Future<Item = Vec<FragmentSubmission>, Error = ()> {
         if fragments.is_empty() {
-            return A(future::ok(0));
+            return A(future::ok(Vec::new()));
         }
         let mut pool_lock = self.pool.clone();
         let mut logs = self.logs.clone();
         let mut network_msg_box = self.network_msg_box.clone();
         let fragment_ids = fragments.iter().map(Fragment::id).collect::<Vec<_>>();
         let fragments_exist_in_logs = self.logs.exist_all(fragment_ids);
-        B(
-            fragments_exist_in_logs.and_then(move |fragments_exist_in_logs| {
+        let tip = self.tip.get_ref().map_err(|_: Infallible| unreachable!());
+        B(fragments_exist_in_logs
+            .join(tip)
+            .and_then(move |(fragments_exist_in_logs, tip)| {
                 future::poll_fn(move || Ok(pool_lock.poll_lock())).and_then(move |mut pool| {
-                    let new_fragments = fragments
+                    let mut new_fragments = Vec::new();
+                    let mut fragment_logs = Vec::new();
+                    let submissions = fragments
                         .into_iter()
                         .zip(fragments_exist_in_logs)
-                        .filter(|(_, exists_in_logs)| !exists_in_logs)
-                        .map(|(fragment, _)| fragment);
-                    let new_fragments = pool.insert_all(new_fragments);
-                    let count = new_fragments.len();
-                    let fragment_logs = new_fragments
-                        .iter()
-                        .map(move |fragment| FragmentLog::new(fragment.id().into(), origin))
+                        .map(|(fragment, exists_in_logs)| {
+                            let fragment_id = fragment.id();
+                            let status = if exists_in_logs {
+                                SubmissionStatus::Duplicate
+                            } else {
+                                let inserted = if is_fragment_valid(&fragment) {
+                                    pool.insert(fragment, origin, &tip)
+                                } else {
+                                    Err(Rejection::Unbalanced)
+                                };
+                                match inserted {
+                                    Ok(Some(fragment)) => {
+                                        fragment_logs
+                                            .push(FragmentLog::new(fragment_id.into(), origin));
+                                        new_fragments.push(fragment);
+                                        SubmissionStatus::Accepted
+                                    }
+                                    Ok(None) => SubmissionStatus::Duplicate,
+                                    Err(rejection) => {
+                                        debug!(
+                                            logger,
+                                            "fragment rejected";
+                                            "fragment_id" => fragment_id.to_string(),
+                                            "reason" => rejection.to_string(),
+                                        );
+                                        let reason = rejection.to_string();
+                                        let mut log = FragmentLog::new(fragment_id.into(), origin);
+                                        log.modify(FragmentStatus::Rejected {
+                                            reason: reason.clone(),
+                                        });
+                                        fragment_logs.push(log);
+                                        SubmissionStatus::Rejected { reason }
+                                    }
+                                }
+                            };
+                            FragmentSubmission::new(fragment_id.into(), status)
+                        })
                         .collect::<Vec<_>>();
                     stream::iter_ok(new_fragments)
-                        .map(|fragment| NetworkMsg::Propagate(PropagateMsg::Fragment(fragment)))
+                        .map(move |fragment| {
+                            NetworkMsg::Propagate(PropagateMsg::Fragment(origin, fragment))
+                        })
                         .fold(network_msg_box, |network_msg_box, fragment_msg| {
                             network_msg_box.send(fragment_msg)
                         })
@@ -80,10 +142,9 @@ impl Pool {
                             error!(logger, "cannot propagate fragment to network: {}", err)
                         })
                         .and_then(move |_| logs.insert_all(fragment_logs))
-                        .map(move |_| count)
+                        .map(move |_| submissions)
                 })
-            }),
-        )
+            }))
     }
 
     pub fn remove_added_to_block(
@@ -103,6 +164,66 @@ impl Pool {
             })
     }
 
+    /// set the time to live of the fragments, the fragments already in the
+    /// pool keep their current expiration time.
+    pub fn set_ttl(&self, ttl: Duration) -> impl Future<Item = (), Error = ()> {
+        let mut lock = self.pool.clone();
+        future::poll_fn(move || Ok(lock.poll_lock())).map(move |mut pool| pool.set_ttl(ttl))
+    }
+
+    /// register the fragments saved before a restart of the node, returns
+    /// the number of fragments restored
+    ///
+    /// The fragments are checked again against the ledger of the tip, the
+    /// expired and no longer valid fragments are discarded and logged as
+    /// rejected. The restored fragments are not propagated again.
+    pub fn restore(
+        &mut self,
+        fragments: Vec<SavedFragment>,
+        logger: Logger,
+    ) -> impl Future<Item = usize, Error = ()> {
+        let mut pool_lock = self.pool.clone();
+        let mut logs = self.logs.clone();
+        self.tip
+            .get_ref()
+            .map_err(|_: Infallible| unreachable!())
+            .and_then(move |tip| {
+                future::poll_fn(move || Ok(pool_lock.poll_lock())).and_then(move |mut pool| {
+                    let mut restored = 0;
+                    let mut fragment_logs = Vec::new();
+                    for saved in fragments {
+                        let fragment_id = saved.fragment.id();
+                        let mut log = FragmentLog::new(fragment_id.into(), saved.origin);
+                        match pool.restore(saved, &tip) {
+                            Ok(Some(_)) => restored += 1,
+                            Ok(None) => continue,
+                            Err(rejection) => {
+                                info!(
+                                    logger,
+                                    "saved fragment discarded";
+                                    "fragment_id" => fragment_id.to_string(),
+                                    "reason" => rejection.to_string(),
+                                );
+                                log.modify(FragmentStatus::Rejected {
+                                    reason: rejection.to_string(),
+                                });
+                            }
+                        }
+                        fragment_logs.push(log);
+                    }
+                    logs.insert_all(fragment_logs).map(move |_| restored)
+                })
+            })
+    }
+
+    /// save the pending fragments in the given file, returns the number of
+    /// fragments saved
+    pub fn save(&self, path: PathBuf) -> impl Future<Item = usize, Error = persistence::Error> {
+        let mut lock = self.pool.clone();
+        future::poll_fn(move || Ok(lock.poll_lock()))
+            .and_then(move |pool| persistence::save(&path, pool.pending()))
+    }
+
     pub fn poll_purge(&mut self) -> impl Future<Item = (), Error = timer::Error> {
         let mut lock = self.pool.clone();
         let purge_logs = self.logs.poll_purge();
@@ -145,12 +266,181 @@ fn is_transaction_valid<A, E>(tx: &AuthenticatedTransaction<A, E>) -> bool {
     tx.transaction.verify_possibly_balanced().is_ok()
 }
 
+custom_error! {pub Rejection
+    Unbalanced = "the inputs and outputs of the transaction are not balanced",
+    Expired = "the fragment has expired while the node was stopped",
+    DoubleSpend { source: DoubleSpend } = "{source}",
+    Ledger { reason: String } = "{reason}",
+}
+
+custom_error! {pub DoubleSpend
+    Utxo { transaction_id: TransactionId, output_index: TransactionIndex, fragment_id: FragmentId }
+        = "input {transaction_id}:{output_index} is already spent by the pending fragment {fragment_id}",
+    Account { counter: u32, fragment_id: FragmentId }
+        = "spending counter {counter} of the account is already used by the pending fragment {fragment_id}",
+    MultisigAccount { fragment_id: FragmentId }
+        = "multisig account is already spent by the pending fragment {fragment_id}, the spending counter is not updated yet",
+}
+
+/// input of a fragment as indexed in the pool
+///
+/// An account input is indexed with the spending counter its witness is
+/// signed with, so two pending fragments signed with the same counter of an
+/// account conflict. The counters of the multisig accounts are not tracked,
+/// such an account can be spent by only one pending fragment until it is
+/// added in a block.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum SpentInput {
+    Utxo(UtxoPointer),
+    AccountCounter(AccountIdentifier, u32),
+    MultisigAccount(AccountIdentifier),
+}
+
+impl SpentInput {
+    fn double_spend(&self, fragment_id: FragmentId) -> DoubleSpend {
+        match self {
+            SpentInput::Utxo(pointer) => DoubleSpend::Utxo {
+                transaction_id: pointer.transaction_id,
+                output_index: pointer.output_index,
+                fragment_id,
+            },
+            SpentInput::AccountCounter(_, counter) => DoubleSpend::Account {
+                counter: *counter,
+                fragment_id,
+            },
+            SpentInput::MultisigAccount(_) => DoubleSpend::MultisigAccount { fragment_id },
+        }
+    }
+}
+
+/// the inputs spent by the pending fragments, indexed by input to find the
+/// double spends and by fragment to release the inputs of a removed fragment
+#[derive(Default)]
+struct SpentInputs {
+    by_input: HashMap<SpentInput, FragmentId>,
+    by_fragment: HashMap<FragmentId, Vec<SpentInput>>,
+}
+
+impl SpentInputs {
+    /// fails if one of the given inputs is spent by a pending fragment
+    fn check(&self, inputs: &[SpentInput]) -> Result<(), DoubleSpend> {
+        for input in inputs {
+            if let Some(pending_id) = self.by_input.get(input) {
+                return Err(input.double_spend(*pending_id));
+            }
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, fragment_id: FragmentId, inputs: Vec<SpentInput>) {
+        for input in inputs.iter() {
+            self.by_input.insert(input.clone(), fragment_id);
+        }
+        self.by_fragment.insert(fragment_id, inputs);
+    }
+
+    fn remove(&mut self, fragment_id: &FragmentId) {
+        if let Some(inputs) = self.by_fragment.remove(fragment_id) {
+            for input in inputs {
+                self.by_input.remove(&input);
+            }
+        }
+    }
+}
+
+/// the inputs spent by the fragment
+///
+/// The spending counter of an account input is searched from the counter of
+/// the account in the given ledger, up to `lookahead` counters ahead. An
+/// account input whose witness verifies with none of them is not indexed,
+/// the fragment is rejected by the ledger.
+fn spent_inputs(fragment: &Fragment, ledger: &Ledger, lookahead: u32) -> Vec<SpentInput> {
+    match fragment {
+        Fragment::Transaction(tx) => {
+            transaction_spent_inputs(tx, &tx.transaction.hash(), ledger, lookahead)
+        }
+        Fragment::OwnerStakeDelegation(tx) => {
+            transaction_spent_inputs(tx, &tx.transaction.hash(), ledger, lookahead)
+        }
+        Fragment::StakeDelegation(tx) => {
+            transaction_spent_inputs(tx, &tx.transaction.hash(), ledger, lookahead)
+        }
+        Fragment::PoolRegistration(tx) => {
+            transaction_spent_inputs(tx, &tx.transaction.hash(), ledger, lookahead)
+        }
+        Fragment::PoolManagement(tx) => {
+            transaction_spent_inputs(tx, &tx.transaction.hash(), ledger, lookahead)
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn transaction_spent_inputs<A, E>(
+    tx: &AuthenticatedTransaction<A, E>,
+    sign_data_hash: &TransactionSignDataHash,
+    ledger: &Ledger,
+    lookahead: u32,
+) -> Vec<SpentInput> {
+    let block0_hash = &ledger.get_static_parameters().block0_initial_hash;
+    tx.transaction
+        .inputs
+        .iter()
+        .map(Input::to_enum)
+        .zip(tx.witnesses.iter())
+        .filter_map(|(input, witness)| match input {
+            InputEnum::UtxoInput(pointer) => Some(SpentInput::Utxo(pointer)),
+            InputEnum::AccountInput(account, _value) => match account.to_single_account() {
+                Some(id) => {
+                    // an unknown account is rejected by the ledger
+                    let from = ledger
+                        .accounts()
+                        .get_state(&id)
+                        .map(|state| state.get_counter())
+                        .unwrap_or(0);
+                    signed_counter(witness, id, block0_hash, sign_data_hash, from, lookahead)
+                        .map(|counter| SpentInput::AccountCounter(account, counter))
+                }
+                None => Some(SpentInput::MultisigAccount(account)),
+            },
+        })
+        .collect()
+}
+
+/// the spending counter, between `from` and `from + lookahead`, the witness
+/// of an input of the given account is signed with
+fn signed_counter(
+    witness: &Witness,
+    id: account::Identifier,
+    block0_hash: &HeaderHash,
+    sign_data_hash: &TransactionSignDataHash,
+    from: u32,
+    lookahead: u32,
+) -> Option<u32> {
+    let signature = match witness {
+        Witness::Account(signature) => signature,
+        _ => return None,
+    };
+    let public_key: PublicKey<Ed25519> = id.into();
+    (from..=from.saturating_add(lookahead)).find(|counter| {
+        let data = WitnessAccountData::new(
+            block0_hash,
+            sign_data_hash,
+            &SpendingCounter::from(*counter),
+        );
+        match signature.verify(&public_key, &data) {
+            Verification::Success => true,
+            Verification::Failed => false,
+        }
+    })
+}
+
 pub(super) mod internal {
     use super::*;
-    use crate::fragment::PoolEntry;
+    use crate::{blockcfg::HeaderHash, fragment::PoolEntry};
     use std::{
-        collections::{hash_map::Entry, HashMap, VecDeque},
+        collections::{HashMap, VecDeque},
         sync::Arc,
+        time::SystemTime,
     };
     use tokio::timer::{delay_queue, DelayQueue};
 
@@ -158,46 +448,213 @@ pub(super) mod internal {
         entries: HashMap<FragmentId, (Arc<PoolEntry>, Fragment, delay_queue::Key)>,
         entries_by_time: VecDeque<FragmentId>,
         expirations: DelayQueue<FragmentId>,
+        spent_inputs: SpentInputs,
+        /// the ledger of the tip with the pending fragments applied,
+        /// rebuilt when the tip changes and after removals
+        simulation: Option<Simulation>,
         ttl: Duration,
     }
 
+    /// the fragments are applied as if they were in the block following the
+    /// tip, on top of the fragments already selected for that block. The
+    /// pending fragments are applied again only once after a batch of
+    /// removals, on the next insertion
+    struct Simulation {
+        tip: HeaderHash,
+        /// the ledger of the tip with the selected fragments applied
+        base: Ledger,
+        /// the base ledger with the pending fragments applied, `None` when
+        /// some pending fragments have been removed since it was built
+        pending: Option<Ledger>,
+        parameters: LedgerParameters,
+        metadata: HeaderContentEvalContext,
+    }
+
+    impl Simulation {
+        fn new(tip: &Ref) -> Self {
+            let era = tip.epoch_leadership_schedule().era();
+            Simulation {
+                tip: tip.hash(),
+                base: tip.ledger().as_ref().clone(),
+                pending: None,
+                parameters: tip.epoch_ledger_parameters().as_ref().clone(),
+                metadata: HeaderContentEvalContext {
+                    block_date: tip.block_date().next(era),
+                    chain_length: tip.chain_length().next(),
+                    nonce: None,
+                },
+            }
+        }
+
+        /// the ledger with all the pending fragments applied
+        fn ledger(&self) -> &Ledger {
+            self.pending
+                .as_ref()
+                .expect("the pending fragments of the simulation are applied")
+        }
+
+        fn apply(&mut self, fragment: &Fragment) -> Result<(), Rejection> {
+            let ledger = self
+                .ledger()
+                .apply_fragment(&self.parameters, fragment, &self.metadata)
+                .map_err(|error| Rejection::Ledger {
+                    reason: ledger_error_reason(&error),
+                })?;
+            self.pending = Some(ledger);
+            Ok(())
+        }
+
+        /// apply the pending fragments, in the given order, on the base
+        /// ledger if some of them have been removed
+        fn replay<'a>(&mut self, fragments: impl Iterator<Item = &'a Fragment>) {
+            if self.pending.is_some() {
+                return;
+            }
+            self.pending = Some(self.base.clone());
+            for fragment in fragments {
+                // the pending fragments depending on a removed one, or no
+                // longer valid on a new tip, are left for the fragment
+                // selection to reject
+                let _ = self.apply(fragment);
+            }
+        }
+
+        /// the effects of the removed fragments are dropped on the next
+        /// replay
+        fn invalidate(&mut self) {
+            self.pending = None;
+        }
+
+        /// make the effects of the given fragment, selected for the next
+        /// block, part of the ledger the fragments are applied on
+        fn advance(&mut self, fragment: &Fragment) {
+            // a selected fragment the base ledger rejects is rejected by
+            // the block as well
+            if let Ok(ledger) = self
+                .base
+                .apply_fragment(&self.parameters, fragment, &self.metadata)
+            {
+                self.base = ledger;
+            }
+        }
+    }
+
     impl Pool {
         pub fn new(ttl: Duration) -> Self {
             Pool {
                 entries: HashMap::new(),
                 entries_by_time: VecDeque::new(),
                 expirations: DelayQueue::new(),
+                spent_inputs: SpentInputs::default(),
+                simulation: None,
                 ttl,
             }
         }
 
-        /// Returns clone of fragment if it was registered
-        pub fn insert(&mut self, fragment: Fragment) -> Option<Fragment> {
+        pub fn set_ttl(&mut self, ttl: Duration) {
+            self.ttl = ttl;
+        }
+
+        /// Returns clone of fragment if it was registered, fails if the
+        /// fragment spends an input already spent by a pending fragment or
+        /// if it cannot be applied on the ledger of the given tip with the
+        /// pending fragments
+        pub fn insert(
+            &mut self,
+            fragment: Fragment,
+            origin: FragmentOrigin,
+            tip: &Ref,
+        ) -> Result<Option<Fragment>, Rejection> {
+            let entry = PoolEntry::new(&fragment, origin);
+            let ttl = self.ttl;
+            self.insert_entry(fragment, entry, ttl, tip)
+        }
+
+        /// register a fragment saved before a restart of the node, it keeps
+        /// the time it has been received at: the fragment is rejected if it
+        /// has been pending for longer than the time to live of the pool
+        pub fn restore(
+            &mut self,
+            saved: SavedFragment,
+            tip: &Ref,
+        ) -> Result<Option<Fragment>, Rejection> {
+            let ttl = remaining_ttl(self.ttl, saved.received_at)?;
+            if !is_fragment_valid(&saved.fragment) {
+                return Err(Rejection::Unbalanced);
+            }
+            let entry = PoolEntry::restored(&saved.fragment, saved.origin, saved.received_at);
+            self.insert_entry(saved.fragment, entry, ttl, tip)
+        }
+
+        fn insert_entry(
+            &mut self,
+            fragment: Fragment,
+            entry: PoolEntry,
+            ttl: Duration,
+            tip: &Ref,
+        ) -> Result<Option<Fragment>, Rejection> {
             let fragment_id = fragment.id();
-            let entry = match self.entries.entry(fragment_id) {
-                Entry::Occupied(_) => return None,
-                Entry::Vacant(vacant) => vacant,
-            };
-            let pool_entry = Arc::new(PoolEntry::new(&fragment));
-            let delay = self.expirations.insert(fragment_id, self.ttl);
-            entry.insert((pool_entry, fragment.clone(), delay));
+            if self.entries.contains_key(&fragment_id) {
+                return Ok(None);
+            }
+            // every pending fragment spends at least one counter of the
+            // accounts it spends
+            let lookahead = self.entries.len() as u32;
+            let inputs = spent_inputs(&fragment, &self.simulation(tip).base, lookahead);
+            self.spent_inputs.check(&inputs)?;
+            self.simulation(tip).apply(&fragment)?;
+            let pool_entry = Arc::new(entry);
+            let delay = self.expirations.insert(fragment_id, ttl);
+            self.entries
+                .insert(fragment_id, (pool_entry, fragment.clone(), delay));
             self.entries_by_time.push_back(fragment_id);
-            Some(fragment)
+            self.spent_inputs.insert(fragment_id, inputs);
+            Ok(Some(fragment))
         }
 
-        /// Returns clones of registered fragments
-        pub fn insert_all(
-            &mut self,
-            fragments: impl IntoIterator<Item = Fragment>,
-        ) -> Vec<Fragment> {
-            fragments
-                .into_iter()
-                .filter_map(|fragment| self.insert(fragment))
-                .collect()
+        /// the pending fragments, in the order they have been received
+        pub fn pending(&self) -> impl Iterator<Item = (&PoolEntry, &Fragment)> {
+            self.entries_by_time.iter().map(move |fragment_id| {
+                let (entry, fragment, _) = &self.entries[fragment_id];
+                (entry.as_ref(), fragment)
+            })
+        }
+
+        /// the simulation of the ledger of the given tip with the pending
+        /// fragments applied in the order they have been received.
+        fn simulation(&mut self, tip: &Ref) -> &mut Simulation {
+            let outdated = match &self.simulation {
+                Some(simulation) => simulation.tip != tip.hash(),
+                None => true,
+            };
+            if outdated {
+                self.simulation = Some(Simulation::new(tip));
+            }
+            let simulation = self
+                .simulation
+                .as_mut()
+                .expect("the simulation of the pool has just been set");
+            let entries = &self.entries;
+            simulation.replay(
+                self.entries_by_time
+                    .iter()
+                    .map(|fragment_id| &entries[fragment_id].1),
+            );
+            simulation
+        }
+
+        /// drop the effects of a fragment which is no longer pending, the
+        /// remaining fragments are applied again on the next insertion
+        fn remove_from_simulation(&mut self) {
+            if let Some(simulation) = self.simulation.as_mut() {
+                simulation.invalidate();
+            }
         }
 
         pub fn remove(&mut self, fragment_id: &FragmentId) -> Option<Fragment> {
             if let Some((_, fragment, cache_key)) = self.entries.remove(fragment_id) {
+                self.remove_from_simulation();
+                self.spent_inputs.remove(fragment_id);
                 self.entries_by_time
                     .iter()
                     .position(|id| id == fragment_id)
@@ -212,6 +669,9 @@ pub(super) mod internal {
         }
 
         pub fn remove_all(&mut self, fragment_ids: impl IntoIterator<Item = FragmentId>) {
+            // the fragments have been added in a block, the simulation is
+            // built again on the new tip
+            self.simulation = None;
             // TODO fix terrible performance, entries_by_time are linear searched N times
             for fragment_id in fragment_ids {
                 self.remove(&fragment_id);
@@ -225,6 +685,12 @@ pub(super) mod internal {
                 .remove(&fragment_id)
                 .expect("Pool lost fragment ID consistency");
             self.expirations.remove(&cache_key);
+            // the fragment is selected for the next block, its effects are
+            // kept in the simulation
+            if let Some(simulation) = self.simulation.as_mut() {
+                simulation.advance(&fragment);
+            }
+            self.spent_inputs.remove(&fragment_id);
             Some(fragment)
         }
 
@@ -234,7 +700,10 @@ pub(super) mod internal {
                     Async::NotReady => return Ok(Async::Ready(())),
                     Async::Ready(None) => return Ok(Async::Ready(())),
                     Async::Ready(Some(entry)) => {
-                        self.entries.remove(entry.get_ref());
+                        if self.entries.remove(entry.get_ref()).is_some() {
+                            self.remove_from_simulation();
+                            self.spent_inputs.remove(entry.get_ref());
+                        }
                         self.entries_by_time
                             .iter()
                             .position(|id| id == entry.get_ref())
@@ -246,4 +715,125 @@ pub(super) mod internal {
             }
         }
     }
+
+    /// time a fragment received at the given time may still stay in the pool
+    fn remaining_ttl(ttl: Duration, received_at: SystemTime) -> Result<Duration, Rejection> {
+        let pending_for = received_at.elapsed().unwrap_or_default();
+        ttl.checked_sub(pending_for).ok_or(Rejection::Expired)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chain_crypto::SecretKey;
+        use jormungandr_lib::crypto::key::SigningKey;
+        use rand::rngs::OsRng;
+
+        /// the inputs spent by a transaction spending the account of the
+        /// given key, signed with the given spending counter
+        fn account_spend(
+            key: &SecretKey<Ed25519>,
+            counter: u32,
+            lookahead: u32,
+        ) -> Vec<SpentInput> {
+            let block0_hash = HeaderHash::hash_bytes(b"block0");
+            let sign_data_hash =
+                TransactionSignDataHash::hash_bytes(format!("spend {}", counter).as_bytes());
+            let witness = Witness::new_account(
+                &block0_hash,
+                &sign_data_hash,
+                &SpendingCounter::from(counter),
+                key,
+            );
+            let id = account::Identifier::from(key.to_public());
+            let account = AccountIdentifier::from_single_account(id.clone());
+            // the account has never been spent in the ledger of the tip
+            signed_counter(&witness, id, &block0_hash, &sign_data_hash, 0, lookahead)
+                .map(|counter| SpentInput::AccountCounter(account, counter))
+                .into_iter()
+                .collect()
+        }
+
+        fn account_key() -> SecretKey<Ed25519> {
+            SigningKey::<Ed25519>::generate(OsRng::new().unwrap()).into_secret_key()
+        }
+
+        #[test]
+        fn pending_spends_of_an_account_signed_with_the_same_counter() {
+            let key = account_key();
+            let first_id = FragmentId::hash_bytes(b"first");
+            let mut spent_inputs = SpentInputs::default();
+            spent_inputs.insert(first_id, account_spend(&key, 0, 1));
+
+            match spent_inputs.check(&account_spend(&key, 0, 1)) {
+                Err(DoubleSpend::Account {
+                    counter,
+                    fragment_id,
+                }) => {
+                    assert_eq!(counter, 0);
+                    assert_eq!(fragment_id, first_id);
+                }
+                Err(double_spend) => panic!("unexpected double spend: {}", double_spend),
+                Ok(()) => panic!("the second spend of the counter is accepted"),
+            }
+
+            spent_inputs.remove(&first_id);
+            spent_inputs.check(&account_spend(&key, 0, 1)).unwrap();
+        }
+
+        #[test]
+        fn pending_spends_of_an_account_signed_with_consecutive_counters() {
+            let key = account_key();
+            let mut spent_inputs = SpentInputs::default();
+            spent_inputs.insert(FragmentId::hash_bytes(b"first"), account_spend(&key, 0, 1));
+
+            let second = account_spend(&key, 1, 1);
+            assert_eq!(second.len(), 1);
+            spent_inputs.check(&second).unwrap();
+        }
+
+        #[test]
+        fn spend_of_an_account_signed_with_a_counter_out_of_reach() {
+            // no pending fragment spends the account, the counter 2 cannot
+            // be reached before the fragment is applied
+            let key = account_key();
+            assert!(account_spend(&key, 2, 1).is_empty());
+        }
+
+        #[test]
+        fn remaining_ttl_of_a_fresh_fragment() {
+            let ttl = Duration::from_secs(3600);
+            let remaining = remaining_ttl(ttl, SystemTime::now()).unwrap();
+            assert!(remaining <= ttl);
+            assert!(remaining > ttl - Duration::from_secs(60));
+        }
+
+        #[test]
+        fn remaining_ttl_of_a_fragment_pending_for_a_while() {
+            let ttl = Duration::from_secs(3600);
+            let received_at = SystemTime::now() - Duration::from_secs(1800);
+            let remaining = remaining_ttl(ttl, received_at).unwrap();
+            assert!(remaining <= Duration::from_secs(1800));
+            assert!(remaining > Duration::from_secs(1740));
+        }
+
+        #[test]
+        fn remaining_ttl_of_an_expired_fragment() {
+            let ttl = Duration::from_secs(3600);
+            let received_at = SystemTime::now() - Duration::from_secs(3601);
+            match remaining_ttl(ttl, received_at) {
+                Err(Rejection::Expired) => (),
+                Err(rejection) => panic!("unexpected rejection: {}", rejection),
+                Ok(remaining) => panic!("fragment not expired, {:?} remaining", remaining),
+            }
+        }
+
+        #[test]
+        fn remaining_ttl_of_a_fragment_received_in_the_future() {
+            // the clock went backward since the fragment has been saved
+            let ttl = Duration::from_secs(3600);
+            let received_at = SystemTime::now() + Duration::from_secs(600);
+            assert_eq!(remaining_ttl(ttl, received_at).unwrap(), ttl);
+        }
+    }
 }
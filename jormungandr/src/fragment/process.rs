@@ -1,17 +1,23 @@
 use crate::{
-    fragment::{Logs, Pool},
+    blockchain::Tip,
+    fragment::{persistence, Fragment, Logs, Pool},
     intercom::{NetworkMsg, TransactionMsg},
     stats_counter::StatsCounter,
     utils::{
         async_msg::{MessageBox, MessageQueue},
+        reloadable::{Reloadable, ReloadableInterval},
         task::TokioServiceInfo,
     },
 };
+use jormungandr_lib::interfaces::{FragmentOrigin, FragmentSubmission};
 use slog::Logger;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::{
     prelude::{
-        future::Either::{A, B},
+        future::{
+            self,
+            Either::{A, B},
+        },
         Future, Stream,
     },
     timer::Interval,
@@ -20,21 +26,25 @@ use tokio::{
 pub struct Process {
     pool: Pool,
     logs: Logs,
-    garbage_collection_interval: Duration,
+    garbage_collection_interval: Reloadable<Duration>,
+    persistence: Option<persistence::Settings>,
 }
 
 impl Process {
     pub fn new(
         pool_ttl: Duration,
         logs_ttl: Duration,
-        garbage_collection_interval: Duration,
+        garbage_collection_interval: Reloadable<Duration>,
+        persistence: Option<persistence::Settings>,
         network_msg_box: MessageBox<NetworkMsg>,
+        tip: Tip,
     ) -> Self {
         let logs = Logs::new(logs_ttl);
         Process {
-            pool: Pool::new(pool_ttl, logs.clone(), network_msg_box),
+            pool: Pool::new(pool_ttl, logs.clone(), network_msg_box, tip),
             logs,
             garbage_collection_interval,
+            persistence,
         }
     }
 
@@ -51,28 +61,41 @@ impl Process {
         stats_counter: StatsCounter,
         input: MessageQueue<TransactionMsg>,
     ) -> impl Future<Item = (), Error = ()> {
-        service_info.spawn(self.start_pool_garbage_collector(service_info.logger().clone()));
+        let logger = service_info.logger().clone();
+        service_info.spawn(self.start_pool_garbage_collector(logger.clone()));
+        if let Some(saver) = self.start_pool_saver(logger.clone()) {
+            service_info.spawn(saver);
+        }
+        // the saved fragments are restored before any new fragment is
+        // processed, so they keep the order they have been received in
+        self.restore_pool(logger)
+            .and_then(move |process| process.process_input(service_info, stats_counter, input))
+    }
+
+    fn process_input(
+        self,
+        service_info: TokioServiceInfo,
+        stats_counter: StatsCounter,
+        input: MessageQueue<TransactionMsg>,
+    ) -> impl Future<Item = (), Error = ()> {
         input.for_each(move |input| {
             match input {
                 TransactionMsg::SendTransaction(origin, txs) => {
-                    // Note that we cannot use apply_block here, since we don't have a valid context to which to apply
-                    // those blocks. one valid tx in a given context, could be invalid in another. for example
-                    // fee calculations, existence utxo / account solvency.
-
-                    // FIXME/TODO check that the txs are valid within themselves with basic requirements (e.g. inputs >= outputs).
-                    // we also want to keep a basic capability to filter away repetitive queries or definitely discarded txid.
+                    // The fragments are applied on a simulation of the ledger of the tip with the pending
+                    // fragments before being accepted. A fragment valid in this context could still be invalid
+                    // in the context of the next block, the fragment selection will reject it then.
 
                     // This interface only makes sense for messages coming from arbitrary users (like transaction, certificates),
                     // for other message we don't want to receive them through this interface, and possibly
                     // put them in another pool.
 
-                    let stats_counter = stats_counter.clone();
-                    A(self
-                        .pool
-                        .clone()
-                        .insert_and_propagate_all(origin, txs, service_info.logger().clone())
-                        .map(move |count| stats_counter.add_tx_recv_cnt(count)))
+                    A(A(self
+                        .insert_and_propagate_all(origin, txs, &service_info, &stats_counter)
+                        .map(|_submissions| ())))
                 }
+                TransactionMsg::SubmitTransaction(origin, txs, reply_handle) => A(B(self
+                    .insert_and_propagate_all(origin, txs, &service_info, &stats_counter)
+                    .map(move |submissions| reply_handle.reply_ok(submissions)))),
                 TransactionMsg::RemoveTransactions(fragment_ids, date) => {
                     B(self.pool.clone().remove_added_to_block(fragment_ids, date))
                 }
@@ -80,11 +103,96 @@ impl Process {
         })
     }
 
+    fn insert_and_propagate_all(
+        &self,
+        origin: FragmentOrigin,
+        txs: Vec<Fragment>,
+        service_info: &TokioServiceInfo,
+        stats_counter: &StatsCounter,
+    ) -> impl Future<Item = Vec<FragmentSubmission>, Error = ()> {
+        let stats_counter = stats_counter.clone();
+        self.pool
+            .clone()
+            .insert_and_propagate_all(origin, txs, service_info.logger().clone())
+            .map(move |submissions| {
+                let count = submissions
+                    .iter()
+                    .filter(|submission| submission.status().is_accepted())
+                    .count();
+                stats_counter.add_tx_recv_cnt(count);
+                submissions
+            })
+    }
+
+    /// load the fragments saved in the mempool file back in the pool
+    fn restore_pool(self, logger: Logger) -> impl Future<Item = Self, Error = ()> {
+        let path = match &self.persistence {
+            Some(persistence) => persistence.path.clone(),
+            None => return A(future::ok(self)),
+        };
+        let fragments = match persistence::load(&path) {
+            Ok(fragments) => fragments,
+            Err(error) => {
+                error!(
+                    logger,
+                    "cannot load the saved fragments, the mempool starts empty";
+                    "path" => path.display().to_string(),
+                    "reason" => error.to_string(),
+                );
+                return A(future::ok(self));
+            }
+        };
+        if fragments.is_empty() {
+            return A(future::ok(self));
+        }
+        let saved = fragments.len();
+        B(self
+            .pool
+            .clone()
+            .restore(fragments, logger.clone())
+            .map(move |restored| {
+                info!(
+                    logger,
+                    "saved fragments loaded in the mempool";
+                    "saved" => saved,
+                    "restored" => restored,
+                    "discarded" => saved - restored,
+                );
+                self
+            }))
+    }
+
+    /// save the pending fragments every `persistence.interval`, if set
+    fn start_pool_saver(&self, logger: Logger) -> Option<impl Future<Item = (), Error = ()>> {
+        let persistence = self.persistence.as_ref()?;
+        let interval = persistence.interval?;
+        let path = persistence.path.clone();
+        let pool = self.pool().clone();
+        let error_logger = logger.clone();
+        let saver = Interval::new(Instant::now() + interval, interval)
+            .map_err(move |error| {
+                error!(error_logger, "cannot run the periodic save of the MemPool" ; "reason" => error.to_string());
+            })
+            .for_each(move |_instant| {
+                let logger = logger.clone();
+                pool.save(path.clone()).then(move |result| {
+                    match result {
+                        Ok(saved) => debug!(logger, "pending fragments saved"; "fragments" => saved),
+                        Err(error) => {
+                            error!(logger, "cannot save the pending fragments" ; "reason" => error.to_string())
+                        }
+                    }
+                    Ok(())
+                })
+            });
+        Some(saver)
+    }
+
     fn start_pool_garbage_collector(&self, logger: Logger) -> impl Future<Item = (), Error = ()> {
         let mut pool = self.pool().clone();
-        let garbage_collection_interval = self.garbage_collection_interval;
+        let garbage_collection_interval = self.garbage_collection_interval.clone();
         let error_logger = logger.clone();
-        Interval::new_interval(garbage_collection_interval)
+        ReloadableInterval::new(garbage_collection_interval)
             .for_each(move |_instant| {
                 debug!(logger, "garbage collect entries in the MemPool and in the logs");
                 pool.poll_purge()
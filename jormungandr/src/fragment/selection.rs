@@ -5,6 +5,7 @@ use crate::{
     fragment::FragmentId,
 };
 use chain_core::property::Fragment as _;
+use chain_impl_mockchain::ledger::Error as LedgerError;
 use jormungandr_lib::interfaces::FragmentStatus;
 
 pub enum SelectionOutput {
@@ -73,15 +74,12 @@ impl FragmentSelectionAlgorithm for OldestFirst {
                     total += 1;
                     ledger_simulation = ledger_new;
                 }
-                Err(error) => {
-                    use std::error::Error as _;
-                    let error = if let Some(source) = error.source() {
-                        format!("{}: {}", error, source)
-                    } else {
-                        error.to_string()
-                    };
-                    logs.modify(&id.into(), FragmentStatus::Rejected { reason: error })
-                }
+                Err(error) => logs.modify(
+                    &id.into(),
+                    FragmentStatus::Rejected {
+                        reason: ledger_error_reason(&error),
+                    },
+                ),
             }
             if total >= self.max_per_block {
                 break;
@@ -89,3 +87,14 @@ impl FragmentSelectionAlgorithm for OldestFirst {
         }
     }
 }
+
+/// the reason of the rejection of a fragment by the ledger, as reported in
+/// the fragment logs
+pub(super) fn ledger_error_reason(error: &LedgerError) -> String {
+    use std::error::Error as _;
+    if let Some(source) = error.source() {
+        format!("{}: {}", error, source)
+    } else {
+        error.to_string()
+    }
+}
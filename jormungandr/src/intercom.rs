@@ -3,7 +3,7 @@ use crate::network::p2p::topology::NodeId;
 use blockchain::Checkpoints;
 use futures::prelude::*;
 use futures::sync::{mpsc, oneshot};
-use jormungandr_lib::interfaces::{BlockDate, FragmentOrigin};
+use jormungandr_lib::interfaces::{BlockDate, FragmentOrigin, FragmentSubmission};
 use network_core::error as core_error;
 use slog::Logger;
 use std::{
@@ -97,7 +97,7 @@ impl From<chain_storage::error::Error> for Error {
 
         let code = match err {
             BlockNotFound => core_error::Code::NotFound,
-            CannotIterate => core_error::Code::Internal,
+            CannotIterate => core_error::Code::InvalidArgument,
             BackendError(_) => core_error::Code::Internal,
             Block0InFuture => core_error::Code::Internal,
             BlockAlreadyPresent => core_error::Code::Internal,
@@ -343,6 +343,13 @@ pub fn stream_request<T, E>(buffer: usize) -> (RequestStreamHandle<T>, RequestSi
 #[derive(Debug)]
 pub enum TransactionMsg {
     SendTransaction(FragmentOrigin, Vec<Fragment>),
+    /// same as `SendTransaction`, replying with the result of the submission
+    /// of every fragment once they have been processed by the pool
+    SubmitTransaction(
+        FragmentOrigin,
+        Vec<Fragment>,
+        ReplyHandle<Vec<FragmentSubmission>>,
+    ),
     RemoveTransactions(Vec<FragmentId>, BlockDate),
 }
 
@@ -404,22 +411,28 @@ pub enum BlockMsg {
     LeadershipExpectEndOfEpoch(Epoch),
     /// A untrusted block Header has been received from the network task
     AnnouncedBlock(Header, NodeId),
-    /// An untrusted Block has been received from the network task.
+    /// An untrusted Block has been received from the network task, from
+    /// the given peer if it is known (the peers uploading blocks to the node
+    /// are not identified). The peer is reported as misbehaving if the
+    /// block is not valid.
     /// The reply handle must be used to enable continued streaming by
     /// sending `Ok`, or to cancel the incoming stream with an error sent in
     /// `Err`.
-    NetworkBlock(Block, ReplyHandle<()>),
+    NetworkBlock(Block, Option<NodeId>, ReplyHandle<()>),
     /// The stream of headers for missing chain blocks has been received
     /// from the network in response to a PullHeaders request or a Missing
     /// solicitation event.
     ChainHeaders(RequestStreamHandle<Header>),
+    /// Sent by the shutdown of the node, the reply is sent once the
+    /// messages queued before this one have been processed.
+    Flush(ReplyHandle<()>),
 }
 
 /// Propagation requests for the network task.
 #[derive(Clone, Debug)]
 pub enum PropagateMsg {
     Block(Header),
-    Fragment(Fragment),
+    Fragment(FragmentOrigin, Fragment),
 }
 
 /// Messages to the network task.
@@ -433,6 +446,15 @@ pub enum NetworkMsg {
         from: Checkpoints,
         to: HeaderHash,
     },
+    /// the peer sent blocks which have been rejected, stop communicating with it
+    MisbehavingPeer(NodeId),
+    /// apply the P2P settings modified by a reload of the node configuration
+    Reconfigure {
+        trusted_peers: Vec<poldercast::Address>,
+        max_connections: usize,
+    },
+    /// the node is shutting down, stop accepting and making connections
+    Shutdown,
 }
 
 /// Messages to the explorer task
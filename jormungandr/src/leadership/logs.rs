@@ -1,4 +1,5 @@
-use jormungandr_lib::interfaces::{LeadershipLog, LeadershipLogId};
+use crate::blockcfg::HeaderHash;
+use jormungandr_lib::interfaces::{LeadershipLog, LeadershipLogId, LeadershipLogStatus};
 use std::time::Duration;
 use tokio::{
     prelude::*,
@@ -15,6 +16,7 @@ pub struct Logs(Lock<internal::Logs>);
 /// without having to hold the [`Logs`]
 ///
 /// [`Logs`]: ./struct.Logs.html
+#[derive(Clone)]
 pub struct LeadershipLogHandle {
     internal_id: LeadershipLogId,
     logs: Logs,
@@ -47,6 +49,19 @@ impl LeadershipLogHandle {
     pub fn mark_finished(&self) -> impl Future<Item = (), Error = ()> {
         self.logs.mark_finished(self.internal_id)
     }
+
+    /// mark the block of the leadership event as created.
+    ///
+    /// The blockchain task will then update the status as the block is
+    /// adopted in the main branch or orphaned.
+    pub fn mark_produced(&self, block_id: HeaderHash) -> impl Future<Item = (), Error = ()> {
+        self.logs.mark_produced(self.internal_id, block_id)
+    }
+
+    /// mark the leadership event as failed to create a block
+    pub fn mark_failed(&self, reason: String) -> impl Future<Item = (), Error = ()> {
+        self.logs.mark_failed(self.internal_id, reason)
+    }
 }
 
 impl Logs {
@@ -93,6 +108,60 @@ impl Logs {
         })
     }
 
+    fn mark_produced(
+        &self,
+        leadership_log_id: LeadershipLogId,
+        block_id: HeaderHash,
+    ) -> impl Future<Item = (), Error = ()> {
+        self.inner().and_then(move |mut guard| {
+            guard.mark_produced(&leadership_log_id, block_id);
+            future::ok(())
+        })
+    }
+
+    fn mark_failed(
+        &self,
+        leadership_log_id: LeadershipLogId,
+        reason: String,
+    ) -> impl Future<Item = (), Error = ()> {
+        self.inner().and_then(move |mut guard| {
+            guard.mark_failed(&leadership_log_id, reason);
+            future::ok(())
+        })
+    }
+
+    /// list the blocks created by the leadership events whose outcome
+    /// may still change (produced, adopted or orphaned)
+    pub fn produced_blocks(
+        &self,
+    ) -> impl Future<Item = Vec<(LeadershipLogId, HeaderHash)>, Error = ()> {
+        self.inner()
+            .and_then(|guard| future::ok(guard.produced_blocks().collect()))
+    }
+
+    /// update the status of the given leadership logs. The logs already
+    /// purged are ignored.
+    pub fn update_statuses(
+        &self,
+        statuses: Vec<(LeadershipLogId, LeadershipLogStatus)>,
+    ) -> impl Future<Item = (), Error = ()> {
+        self.inner().and_then(move |mut guard| {
+            for (leadership_log_id, status) in statuses {
+                guard.modify(&leadership_log_id, status);
+            }
+            future::ok(())
+        })
+    }
+
+    /// set the time to live of the logs, the logs already registered keep
+    /// their current expiration time until they are modified.
+    pub fn set_ttl(&self, ttl: Duration) -> impl Future<Item = (), Error = ()> {
+        self.inner().and_then(move |mut guard| {
+            guard.set_ttl(ttl);
+            future::ok(())
+        })
+    }
+
     pub fn poll_purge(&mut self) -> impl Future<Item = (), Error = timer::Error> {
         self.inner()
             .and_then(move |mut guard| future::poll_fn(move || guard.poll_purge()))
@@ -110,7 +179,7 @@ impl Logs {
 }
 
 pub(super) mod internal {
-    use super::{LeadershipLog, LeadershipLogId};
+    use super::{HeaderHash, LeadershipLog, LeadershipLogId, LeadershipLogStatus};
     use std::{
         collections::HashMap,
         time::{Duration, Instant},
@@ -135,6 +204,10 @@ pub(super) mod internal {
             }
         }
 
+        pub fn set_ttl(&mut self, ttl: Duration) {
+            self.ttl = ttl;
+        }
+
         pub fn insert(&mut self, log: LeadershipLog) -> LeadershipLogId {
             let id = log.leadership_log_id();
 
@@ -175,6 +248,46 @@ pub(super) mod internal {
             }
         }
 
+        pub fn mark_produced(&mut self, leadership_log_id: &LeadershipLogId, block_id: HeaderHash) {
+            if let Some((ref mut log, ref key)) = self.entries.get_mut(leadership_log_id) {
+                log.mark_produced(block_id);
+
+                self.expirations.reset_at(key, Instant::now() + self.ttl);
+            }
+        }
+
+        pub fn mark_failed(&mut self, leadership_log_id: &LeadershipLogId, reason: String) {
+            if let Some((ref mut log, ref key)) = self.entries.get_mut(leadership_log_id) {
+                log.mark_failed(reason);
+
+                self.expirations.reset_at(key, Instant::now() + self.ttl);
+            }
+        }
+
+        /// the blocks created by the leadership events, whatever their
+        /// current outcome (produced, adopted or orphaned)
+        pub fn produced_blocks<'a>(
+            &'a self,
+        ) -> impl Iterator<Item = (LeadershipLogId, HeaderHash)> + 'a {
+            self.entries
+                .iter()
+                .filter_map(|(leadership_log_id, (log, _))| {
+                    log.status()
+                        .block_id()
+                        .map(|block_id| (*leadership_log_id, block_id.clone().into_hash()))
+                })
+        }
+
+        /// set the status of the log, the log's TTL is reset only if the
+        /// status has changed
+        pub fn modify(&mut self, leadership_log_id: &LeadershipLogId, status: LeadershipLogStatus) {
+            if let Some((ref mut log, ref key)) = self.entries.get_mut(leadership_log_id) {
+                if log.modify(status) {
+                    self.expirations.reset_at(key, Instant::now() + self.ttl);
+                }
+            }
+        }
+
         pub fn poll_purge(&mut self) -> Poll<(), timer::Error> {
             loop {
                 match self.expirations.poll()? {
@@ -192,3 +305,116 @@ pub(super) mod internal {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_impl_mockchain::block::BlockDate;
+    use jormungandr_lib::{interfaces::EnclaveLeaderId, time::SystemTime};
+
+    const BLOCK_ID: &str = "9a4b0e1d6c0b1c2a7d2e63ae9e1a3b7e4e2d5c8b7a6f5e4d3c2b1a0f9e8d7c6b";
+
+    fn insert(logs: &mut internal::Logs, slot_id: u32) -> LeadershipLogId {
+        logs.insert(LeadershipLog::new(
+            EnclaveLeaderId::new(),
+            BlockDate { epoch: 0, slot_id }.into(),
+            SystemTime::now(),
+        ))
+    }
+
+    fn status(logs: &internal::Logs, leadership_log_id: LeadershipLogId) -> LeadershipLogStatus {
+        logs.logs()
+            .find(|log| log.leadership_log_id() == leadership_log_id)
+            .map(|log| log.status().clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn status_transitions() {
+        let block_id: HeaderHash = BLOCK_ID.parse().unwrap();
+        let mut logs = internal::Logs::new(Duration::from_secs(3600));
+        let produced = insert(&mut logs, 1);
+        let failed = insert(&mut logs, 2);
+        let pending = insert(&mut logs, 3);
+
+        logs.mark_produced(&produced, block_id);
+        logs.mark_failed(&failed, "fragment selection failed".to_owned());
+
+        assert_eq!(
+            status(&logs, produced),
+            LeadershipLogStatus::Produced {
+                block_id: block_id.into()
+            }
+        );
+        assert_eq!(
+            status(&logs, failed),
+            LeadershipLogStatus::Failed {
+                reason: "fragment selection failed".to_owned()
+            }
+        );
+        assert_eq!(status(&logs, pending), LeadershipLogStatus::Pending);
+        assert_eq!(
+            logs.produced_blocks().collect::<Vec<_>>(),
+            vec![(produced, block_id)]
+        );
+
+        logs.modify(
+            &produced,
+            LeadershipLogStatus::Adopted {
+                block_id: block_id.into(),
+            },
+        );
+        assert_eq!(
+            status(&logs, produced),
+            LeadershipLogStatus::Adopted {
+                block_id: block_id.into()
+            }
+        );
+
+        // an adopted block may still be orphaned by a competing branch
+        assert_eq!(
+            logs.produced_blocks().collect::<Vec<_>>(),
+            vec![(produced, block_id)]
+        );
+        logs.modify(
+            &produced,
+            LeadershipLogStatus::Orphaned {
+                block_id: block_id.into(),
+            },
+        );
+        assert_eq!(
+            status(&logs, produced),
+            LeadershipLogStatus::Orphaned {
+                block_id: block_id.into()
+            }
+        );
+    }
+
+    #[test]
+    fn purged_logs_are_ignored() {
+        let block_id: HeaderHash = BLOCK_ID.parse().unwrap();
+        let mut logs = internal::Logs::new(Duration::from_secs(3600));
+        let unknown = LeadershipLog::new(
+            EnclaveLeaderId::new(),
+            BlockDate {
+                epoch: 0,
+                slot_id: 1,
+            }
+            .into(),
+            SystemTime::now(),
+        )
+        .leadership_log_id();
+
+        logs.mark_produced(&unknown, block_id);
+        logs.mark_failed(&unknown, "fragment selection failed".to_owned());
+        logs.modify(
+            &unknown,
+            LeadershipLogStatus::Adopted {
+                block_id: block_id.into(),
+            },
+        );
+
+        assert_eq!(logs.logs().count(), 0);
+        assert_eq!(logs.produced_blocks().count(), 0);
+    }
+}
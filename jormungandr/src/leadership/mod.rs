@@ -71,7 +71,12 @@ use crate::{
     blockchain::Tip,
     fragment,
     intercom::BlockMsg,
-    utils::{async_msg::MessageBox, task::TokioServiceInfo},
+    shutdown::Shutdown,
+    utils::{
+        async_msg::MessageBox,
+        reloadable::{Reloadable, ReloadableInterval},
+        task::TokioServiceInfo,
+    },
 };
 use chain_time::{
     era::{EpochPosition, EpochSlotOffset},
@@ -82,7 +87,7 @@ use std::{sync::Arc, time::Duration};
 use tokio::{
     prelude::*,
     sync::mpsc,
-    timer::{Delay, Interval},
+    timer::Delay,
 };
 
 error_chain! {
@@ -102,6 +107,9 @@ error_chain! {
         CannotSendLeadershipBlock {
             description("Cannot send the leadership's new created block")
         }
+        ShuttingDown {
+            description("the node is shutting down")
+        }
     }
 }
 
@@ -118,7 +126,8 @@ pub struct LeadershipModule {
     fragment_pool: fragment::Pool,
     tip: Tip,
     block_message: MessageBox<BlockMsg>,
-    garbage_collection_interval: Duration,
+    garbage_collection_interval: Reloadable<Duration>,
+    shutdown: Shutdown,
 }
 
 impl LeadershipModule {
@@ -131,29 +140,53 @@ impl LeadershipModule {
         let date = leader_event.date.clone();
         let ledger_parameters = schedule.epoch_ledger_parameters;
         let sender = self.block_message.clone();
-        let log_awake = schedule.log.mark_wake();
-        let log_finish = schedule.log.mark_finished();
+        let shutdown = self.shutdown.clone();
+        let log = schedule.log;
+        let log_failed = log.clone();
+        let log_awake = log.mark_wake();
+        let log_finish = log.mark_finished();
+        let error_logger = logger.clone();
 
         self.service_info.spawn(
             log_awake
                 .map_err(|()| unreachable!())
                 .and_then(move |()| {
+                    // the shutdown waits for the block until it is sent
+                    // to the block task
+                    let block_in_progress = match shutdown.start_block() {
+                        Some(block_in_progress) => block_in_progress,
+                        None => {
+                            return future::Either::A(future::err(ErrorKind::ShuttingDown.into()))
+                        }
+                    };
                     info!(logger, "leader event starting");
 
-                    prepare_block(fragment_pool, date, tip, ledger_parameters)
+                    future::Either::B(
+                        prepare_block(fragment_pool, date, tip, ledger_parameters)
+                            .map(move |bb| (bb, block_in_progress)),
+                    )
                 })
-                .and_then(move |bb| {
+                .and_then(move |(bb, block_in_progress)| {
                     enclave
                         .query_block_finalize(bb, leader_event)
-                        .map_err(|_| unimplemented!())
+                        .map_err(|e| Error::with_chain(e, ErrorKind::Enclave))
+                        .map(move |block| (block, block_in_progress))
+                })
+                .and_then(move |(block, block_in_progress)| {
+                    log.mark_produced(block.header.hash())
+                        .map_err(|()| unreachable!())
+                        .and_then(move |()| {
+                            sender
+                                .send(BlockMsg::LeadershipBlock(block))
+                                .map_err(|_send_error| ErrorKind::CannotSendLeadershipBlock.into())
+                        })
+                        .map(move |_: MessageBox<BlockMsg>| drop(block_in_progress))
                 })
-                .and_then(|block| {
-                    sender
-                        .send(BlockMsg::LeadershipBlock(block))
-                        .map_err(|_send_error| ErrorKind::CannotSendLeadershipBlock.into())
+                .or_else(move |error: Error| {
+                    error!(error_logger, "cannot create the block" ; "reason" => error.to_string());
+                    log_failed.mark_failed(error.to_string())
                 })
-                .and_then(|_: MessageBox<BlockMsg>| log_finish.map_err(|()| unreachable!()))
-                .map_err(|_: Error| unimplemented!()),
+                .and_then(|()| log_finish),
         );
     }
 
@@ -300,13 +333,13 @@ leader elections please prevent your system from suspending or hibernating.
 
     fn spawn_log_purge(&self) -> impl Future<Item = (), Error = ()> {
         let mut logs = self.logs.clone();
-        let garbage_collection_interval = self.garbage_collection_interval;
+        let garbage_collection_interval = self.garbage_collection_interval.clone();
         let logger = self
             .service_info
             .logger()
             .new(o!("sub task" => "garbage collection"));
         let error_logger = logger.clone();
-        Interval::new_interval(garbage_collection_interval)
+        ReloadableInterval::new(garbage_collection_interval)
             .for_each(move |_instant| {
                 debug!(logger, "garbage collect entries in the logs");
                 logs.poll_purge()
@@ -319,12 +352,13 @@ leader elections please prevent your system from suspending or hibernating.
     pub fn start(
         service_info: TokioServiceInfo,
         logs: Logs,
-        garbage_collection_interval: Duration,
+        garbage_collection_interval: Reloadable<Duration>,
         enclave: Enclave,
         fragment_pool: fragment::Pool,
         tip_branch: Tip,
         new_epoch_events: mpsc::Receiver<NewEpochToSchedule>,
         block_message: MessageBox<BlockMsg>,
+        shutdown: Shutdown,
     ) -> impl Future<Item = (), Error = Error> {
         let scheduler_future = Schedules::new().into_future();
         let new_epoch_future = new_epoch_events.into_future();
@@ -337,9 +371,12 @@ leader elections please prevent your system from suspending or hibernating.
             tip: tip_branch,
             block_message,
             garbage_collection_interval,
+            shutdown,
         };
 
-        leadership_module.spawn_log_purge();
+        leadership_module
+            .service_info
+            .spawn(leadership_module.spawn_log_purge());
 
         future::loop_fn(
             (leadership_module, scheduler_future, new_epoch_future),
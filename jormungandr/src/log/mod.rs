@@ -1,6 +1,8 @@
 mod asyncable_drain;
+mod reloadable_drain;
 
 pub use self::asyncable_drain::AsyncableDrain;
+pub use self::reloadable_drain::ReloadableDrain;
 
 pub const KEY_TASK: &str = "task";
 pub const KEY_SUB_TASK: &str = "sub_task";
@@ -0,0 +1,49 @@
+use slog::{Drain, Level, Never, OwnedKVList, Record, SendSyncRefUnwindSafeDrain};
+use std::sync::{Arc, RwLock};
+
+type BoxedDrain = Box<dyn SendSyncRefUnwindSafeDrain<Ok = (), Err = Never>>;
+
+/// drain forwarding the records to an inner drain which can be replaced
+/// while the node is running.
+///
+/// Every logger derived from a root logger built on top of this drain
+/// will use the new inner drain as soon as it is swapped.
+#[derive(Clone)]
+pub struct ReloadableDrain(Arc<RwLock<BoxedDrain>>);
+
+impl ReloadableDrain {
+    pub fn new<D>(drain: D) -> Self
+    where
+        D: SendSyncRefUnwindSafeDrain<Ok = (), Err = Never> + 'static,
+    {
+        ReloadableDrain(Arc::new(RwLock::new(Box::new(drain))))
+    }
+
+    /// replace the inner drain, the previous drain is dropped (flushing
+    /// the pending records if it is an asynchronous drain).
+    pub fn swap<D>(&self, drain: D)
+    where
+        D: SendSyncRefUnwindSafeDrain<Ok = (), Err = Never> + 'static,
+    {
+        let previous = {
+            let mut inner = self.0.write().unwrap_or_else(|e| e.into_inner());
+            std::mem::replace(&mut *inner, Box::new(drain))
+        };
+        drop(previous)
+    }
+}
+
+impl Drain for ReloadableDrain {
+    type Ok = ();
+    type Err = Never;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        let inner = self.0.read().unwrap_or_else(|e| e.into_inner());
+        inner.log(record, values)
+    }
+
+    fn is_enabled(&self, level: Level) -> bool {
+        let inner = self.0.read().unwrap_or_else(|e| e.into_inner());
+        inner.is_enabled(level)
+    }
+}
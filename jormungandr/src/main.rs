@@ -27,6 +27,7 @@ extern crate linked_hash_map;
 extern crate native_tls;
 extern crate network_core;
 extern crate network_grpc;
+extern crate openssl;
 extern crate poldercast;
 extern crate rand;
 extern crate serde;
@@ -49,20 +50,27 @@ extern crate slog_syslog;
 extern crate slog_term;
 extern crate structopt;
 extern crate tokio;
+extern crate tokio_openssl;
+extern crate tokio_signal;
 
 use crate::{
     blockcfg::{HeaderHash, Leader},
     blockchain::Blockchain,
+    intercom::{BlockMsg, ClientMsg, ExplorerMsg},
+    log::ReloadableDrain,
     secure::enclave::Enclave,
     settings::start::Settings,
-    utils::{async_msg, task::Services},
+    shutdown::Shutdown,
+    utils::{
+        async_msg,
+        reloadable::Reloadable,
+        task::{Input, Services, ThreadServiceInfo, TokioServiceInfo},
+    },
 };
 use futures::Future;
-use settings::{start::RawSettings, CommandLine};
+use settings::{start::RawSettings, Command, CommandLine};
 use slog::Logger;
-use std::thread;
 use std::time::Duration;
-use tokio::sync::lock::Lock;
 
 pub mod blockcfg;
 pub mod blockchain;
@@ -73,9 +81,11 @@ pub mod intercom;
 pub mod leadership;
 pub mod log;
 pub mod network;
+pub mod reload;
 pub mod rest;
 pub mod secure;
 pub mod settings;
+pub mod shutdown;
 pub mod start_up;
 pub mod state;
 mod stats_counter;
@@ -86,6 +96,19 @@ use stats_counter::StatsCounter;
 fn start() -> Result<(), start_up::Error> {
     let initialized_node = initialize_node()?;
 
+    if let Some(Command::Storage(command)) = initialized_node.command {
+        let logger = initialized_node
+            .logger
+            .new(o!(log::KEY_TASK => "storage"));
+        return start_up::run_storage_command(
+            command,
+            initialized_node.block0,
+            initialized_node.storage,
+            BLOCK_CACHE_TTL,
+            &logger,
+        );
+    }
+
     let bootstrapped_node = bootstrap(initialized_node)?;
 
     start_services(bootstrapped_node)
@@ -93,32 +116,62 @@ fn start() -> Result<(), start_up::Error> {
 
 pub struct BootstrappedNode {
     settings: Settings,
+    command_line: CommandLine,
+    log_drain: ReloadableDrain,
     blockchain: Blockchain,
     blockchain_tip: blockchain::Tip,
     block0_hash: HeaderHash,
+    network_tls: Option<network::tls::TlsContext>,
     new_epoch_announcements: tokio::sync::mpsc::Sender<self::leadership::NewEpochToSchedule>,
     new_epoch_notifier: tokio::sync::mpsc::Receiver<self::leadership::NewEpochToSchedule>,
     logger: Logger,
     explorer_db: Option<explorer::ExplorerDB>,
 }
 
-const FRAGMENT_TASK_QUEUE_LEN: usize = 1024;
-const NETWORK_TASK_QUEUE_LEN: usize = 32;
+// TODO: we should get this value from the configuration
+const BLOCK_CACHE_TTL: Duration = Duration::from_secs(5 * 24 * 3600);
 
 fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::Error> {
-    let mut services = Services::new(bootstrapped_node.logger.clone());
+    let shutdown = Shutdown::default();
+    let mut services = Services::new(bootstrapped_node.logger.clone(), shutdown.clone());
+    let mempool_file =
+        start_up::mempool_file(&bootstrapped_node.settings, &bootstrapped_node.logger);
+    let intercom = bootstrapped_node.settings.intercom.clone();
+    services.set_queue_len(intercom.task_queue_len);
+    services.set_policy(bootstrapped_node.settings.supervisor.clone().into());
 
     // initialize the network propagation channel
-    let (mut network_msgbox, network_queue) = async_msg::channel(NETWORK_TASK_QUEUE_LEN);
-    let (fragment_msgbox, fragment_queue) = async_msg::channel(FRAGMENT_TASK_QUEUE_LEN);
-    let mut new_epoch_announcements = bootstrapped_node.new_epoch_announcements;
+    let (network_msgbox, network_queue) = async_msg::channel(intercom.network_queue_len);
+    let (fragment_msgbox, fragment_queue) = async_msg::channel(intercom.fragment_queue_len);
+    let network_queue_metrics = network_msgbox.metrics();
+    let new_epoch_announcements = bootstrapped_node.new_epoch_announcements;
     let new_epoch_notifier = bootstrapped_node.new_epoch_notifier;
     let blockchain_tip = bootstrapped_node.blockchain_tip;
     let blockchain = bootstrapped_node.blockchain;
+    let rejected_headers =
+        start_up::load_rejected_headers(&bootstrapped_node.settings, &bootstrapped_node.logger)?;
+    {
+        let rejected_headers = rejected_headers.clone();
+        services.spawn_future("rejected_headers", move |info| {
+            rejected_headers.flush_periodically(info.logger().clone())
+        });
+    }
     let leadership_logs =
         leadership::Logs::new(bootstrapped_node.settings.leadership.log_ttl.into());
-    let leadership_garbage_collection_interval =
-        bootstrapped_node.settings.leadership.log_ttl.into();
+    let leadership_garbage_collection_interval = Reloadable::new(
+        bootstrapped_node
+            .settings
+            .leadership
+            .garbage_collection_interval
+            .into(),
+    );
+    let mempool_garbage_collection_interval = Reloadable::new(
+        bootstrapped_node
+            .settings
+            .mempool
+            .garbage_collection_interval
+            .into(),
+    );
 
     let stats_counter = StatsCounter::default();
 
@@ -127,12 +180,19 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
         let process = fragment::Process::new(
             bootstrapped_node.settings.mempool.fragment_ttl.into(),
             bootstrapped_node.settings.mempool.log_ttl.into(),
-            bootstrapped_node
-                .settings
-                .mempool
-                .garbage_collection_interval
-                .into(),
+            mempool_garbage_collection_interval.clone(),
+            mempool_file
+                .clone()
+                .map(|path| fragment::persistence::Settings {
+                    path,
+                    interval: bootstrapped_node
+                        .settings
+                        .mempool
+                        .persistence_interval
+                        .map(Into::into),
+                }),
             network_msgbox.clone(),
+            blockchain_tip.clone(),
         );
 
         let pool = process.pool().clone();
@@ -141,6 +201,7 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
         services.spawn_future("fragment", move |info| {
             process.start(info, stats_counter, fragment_queue)
         });
+        services.monitor_queue("fragment", fragment_msgbox.metrics());
         (pool, logs)
     };
 
@@ -150,14 +211,17 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
                 .explorer_db
                 .expect("explorer db to be bootstrapped");
 
-            let mut explorer =
+            let explorer =
                 explorer::Explorer::new(explorer_db.clone(), explorer::graphql::create_schema());
 
             // Context to give to the rest api
             let context = explorer.clone();
 
-            let task_msg_box = services.spawn_future_with_inputs("explorer", move |info, input| {
-                explorer.handle_input(info, input)
+            let task_msg_box = services.spawn_future_with_inputs("explorer", move || {
+                let mut explorer = explorer.clone();
+                move |info: &TokioServiceInfo, input: Input<ExplorerMsg>| {
+                    explorer.handle_input(info, input)
+                }
             });
             Some((task_msg_box, context))
         } else {
@@ -165,36 +229,87 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
         }
     };
 
+    let reloader = reload::Reloader::new(
+        bootstrapped_node.command_line,
+        bootstrapped_node.settings.clone(),
+        reload::Handles {
+            log_drain: bootstrapped_node.log_drain,
+            fragment_pool: fragment_pool.clone(),
+            fragment_logs: pool_logs.clone(),
+            mempool_garbage_collection_interval,
+            leadership_logs: leadership_logs.clone(),
+            leadership_garbage_collection_interval: leadership_garbage_collection_interval
+                .clone(),
+            network_msgbox: network_msgbox.clone(),
+        },
+        bootstrapped_node.logger.new(o!(log::KEY_TASK => "reload")),
+    );
+
+    #[cfg(unix)]
+    {
+        let reloader = reloader.clone();
+        services.spawn_future("reload", move |info| reload::reload_on_sighup(info, reloader));
+    }
+
+    {
+        let shutdown = shutdown.clone();
+        services.spawn_future("shutdown", move |info| {
+            shutdown::shutdown_on_signal(info, shutdown)
+        });
+    }
+
+    let shutdown_network_msgbox = network_msgbox.clone();
+
     let block_task = {
-        let mut blockchain = blockchain.clone();
-        let mut blockchain_tip = blockchain_tip.clone();
-        let mut fragment_msgbox = fragment_msgbox.clone();
-        let mut explorer_msg_box = explorer.as_ref().map(|(msg_box, _context)| msg_box.clone());
+        let blockchain = blockchain.clone();
+        let blockchain_tip = blockchain_tip.clone();
+        let fragment_msgbox = fragment_msgbox.clone();
+        let explorer_msg_box = explorer.as_ref().map(|(msg_box, _context)| msg_box.clone());
         let stats_counter = stats_counter.clone();
-        services.spawn_future_with_inputs("block", move |info, input| {
-            blockchain::handle_input(
-                info,
-                &mut blockchain,
-                &mut blockchain_tip,
-                &stats_counter,
-                &mut new_epoch_announcements,
-                &mut network_msgbox,
-                &mut fragment_msgbox,
-                &mut explorer_msg_box,
-                input,
-            )
+        let leadership_logs = leadership_logs.clone();
+        let rejected_headers = rejected_headers.clone();
+        services.spawn_future_with_inputs("block", move || {
+            let mut blockchain = blockchain.clone();
+            let mut blockchain_tip = blockchain_tip.clone();
+            let mut new_epoch_announcements = new_epoch_announcements.clone();
+            let mut network_msgbox = network_msgbox.clone();
+            let mut fragment_msgbox = fragment_msgbox.clone();
+            let mut explorer_msg_box = explorer_msg_box.clone();
+            let stats_counter = stats_counter.clone();
+            let leadership_logs = leadership_logs.clone();
+            let rejected_headers = rejected_headers.clone();
+            move |info: &TokioServiceInfo, input: Input<BlockMsg>| {
+                blockchain::handle_input(
+                    info,
+                    &mut blockchain,
+                    &mut blockchain_tip,
+                    &stats_counter,
+                    &leadership_logs,
+                    &rejected_headers,
+                    &mut new_epoch_announcements,
+                    &mut network_msgbox,
+                    &mut fragment_msgbox,
+                    &mut explorer_msg_box,
+                    input,
+                )
+            }
         })
     };
 
     let client_task = {
-        let mut task_data = client::TaskData {
-            storage: blockchain.storage().clone(),
-            block0_hash: bootstrapped_node.block0_hash,
-            blockchain_tip: blockchain_tip.clone(),
-        };
+        let storage = blockchain.storage().clone();
+        let block0_hash = bootstrapped_node.block0_hash;
+        let blockchain_tip = blockchain_tip.clone();
 
-        services.spawn_with_inputs("client-query", move |info, input| {
-            client::handle_input(info, &mut task_data, input)
+        services.spawn_with_inputs("client-query", move || {
+            let mut task_data = client::TaskData {
+                storage: storage.clone(),
+                block0_hash,
+                blockchain_tip: blockchain_tip.clone(),
+            };
+            move |info: &ThreadServiceInfo, input: Input<ClientMsg>| {
+                client::handle_input(info, &mut task_data, input)
+            }
         })
     };
 
@@ -204,6 +319,7 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
         let block_msgbox = block_task.clone();
         let block0_hash = bootstrapped_node.block0_hash;
         let config = bootstrapped_node.settings.network.clone();
+        let tls = bootstrapped_node.network_tls.clone();
         let channels = network::Channels {
             client_box: client_msgbox,
             transaction_box: fragment_msgbox,
@@ -216,10 +332,12 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
                 block0_hash,
                 input: network_queue,
                 channels,
+                tls,
                 logger: info.into_logger(),
             };
             network::run(params);
         });
+        services.monitor_queue("network", network_queue_metrics);
     }
 
     let leader_secrets: Result<Vec<Leader>, start_up::Error> = bootstrapped_node
@@ -243,6 +361,7 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
         let block_task = block_task.clone();
         let blockchain_tip = blockchain_tip.clone();
         let enclave = leadership::Enclave::new(enclave.clone());
+        let shutdown = shutdown.clone();
 
         services.spawn_future("leadership", move |info| {
             leadership::LeadershipModule::start(
@@ -254,6 +373,7 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
                 blockchain_tip,
                 new_epoch_notifier,
                 block_task,
+                shutdown,
             )
             .map_err(|e| unimplemented!("error in leadership {}", e))
         });
@@ -263,27 +383,53 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
         Some(rest) => {
             let context = rest::Context {
                 stats_counter,
-                blockchain,
-                blockchain_tip,
+                blockchain: blockchain.clone(),
+                blockchain_tip: blockchain_tip.clone(),
                 transaction_task: fragment_msgbox,
+                client_task: client_task.clone(),
                 logs: pool_logs,
                 leadership_logs,
-                server: Lock::new(None),
+                shutdown: shutdown.clone(),
                 enclave,
+                reloader,
                 explorer: explorer.as_ref().map(|(_msg_box, context)| context.clone()),
+                logger: bootstrapped_node.logger.new(o!(log::KEY_TASK => "rest")),
+                services: services.health(),
+                utxo_index: Default::default(),
             };
             Some(rest::start_rest_server(&rest, context)?)
         }
         None => None,
     };
 
-    match rest_server {
-        Some(server) => server.wait_for_stop(),
-        None => thread::sleep(Duration::from_secs(u64::max_value())),
+    let reason = shutdown.wait();
+    let report = shutdown::run(
+        &shutdown,
+        reason,
+        shutdown::Handles {
+            rest_server,
+            network_msgbox: shutdown_network_msgbox,
+            block_msgbox: block_task.clone(),
+            blockchain,
+            blockchain_tip,
+            rejected_headers,
+            fragment_pool,
+            mempool_file,
+        },
+        &bootstrapped_node
+            .logger
+            .new(o!(log::KEY_TASK => "shutdown")),
+    );
+
+    if let shutdown::Reason::ServiceFailure(service) = reason {
+        Err(start_up::Error::ServiceFailure { service })
+    } else if report.is_success() {
+        Ok(())
+    } else {
+        Err(start_up::Error::Shutdown {
+            failed_steps: report.failed_steps(),
+        })
     }
-    info!(bootstrapped_node.logger, "Shutting down node");
-
-    Ok(())
 }
 
 /// # Bootstrap phase
@@ -301,9 +447,13 @@ fn start_services(bootstrapped_node: BootstrappedNode) -> Result<(), start_up::E
 fn bootstrap(initialized_node: InitializedNode) -> Result<BootstrappedNode, start_up::Error> {
     let InitializedNode {
         settings,
+        command_line,
+        log_drain,
         block0,
         storage,
+        network_tls,
         logger,
+        command: _,
     } = initialized_node;
     let bootstrap_logger = logger.new(o!(log::KEY_TASK => "bootstrap"));
 
@@ -313,18 +463,16 @@ fn bootstrap(initialized_node: InitializedNode) -> Result<BootstrappedNode, star
 
     let block0_explorer = block0.clone();
 
-    // TODO: we should get this value from the configuration
-    let block_cache_ttl: Duration = Duration::from_secs(5 * 24 * 3600);
-
     let (blockchain, blockchain_tip) = start_up::load_blockchain(
         block0,
         storage,
         new_epoch_announcements.clone(),
-        block_cache_ttl,
+        BLOCK_CACHE_TTL,
     )?;
 
     let bootstrapped = network::bootstrap(
         &settings.network,
+        network_tls.as_ref(),
         blockchain.clone(),
         blockchain_tip.clone(),
         &bootstrap_logger,
@@ -348,9 +496,12 @@ fn bootstrap(initialized_node: InitializedNode) -> Result<BootstrappedNode, star
 
     Ok(BootstrappedNode {
         settings,
+        command_line,
+        log_drain,
         block0_hash,
         blockchain,
         blockchain_tip,
+        network_tls,
         new_epoch_announcements,
         new_epoch_notifier,
         logger,
@@ -360,13 +511,18 @@ fn bootstrap(initialized_node: InitializedNode) -> Result<BootstrappedNode, star
 
 pub struct InitializedNode {
     pub settings: Settings,
+    pub command_line: CommandLine,
+    pub log_drain: ReloadableDrain,
     pub block0: blockcfg::Block,
     pub storage: start_up::NodeStorage,
+    pub network_tls: Option<network::tls::TlsContext>,
     pub logger: Logger,
+    pub command: Option<Command>,
 }
 
 fn initialize_node() -> Result<InitializedNode, start_up::Error> {
-    let command_line = CommandLine::load();
+    let mut command_line = CommandLine::load();
+    let command = command_line.command.take();
 
     if command_line.full_version {
         println!("{}", env!("FULL_VERSION"));
@@ -376,9 +532,10 @@ fn initialize_node() -> Result<InitializedNode, start_up::Error> {
         std::process::exit(0);
     }
 
-    let raw_settings = RawSettings::load(command_line)?;
+    let raw_settings = RawSettings::load(command_line.clone())?;
 
-    let logger = raw_settings.to_logger()?;
+    let log_drain = ReloadableDrain::new(raw_settings.log_settings().to_drain()?);
+    let logger = Logger::root(log_drain.clone(), o!());
 
     // The log crate is used by some libraries, e.g. tower-grpc.
     // Set up forwarding from log to slog.
@@ -391,18 +548,24 @@ fn initialize_node() -> Result<InitializedNode, start_up::Error> {
     let storage = start_up::prepare_storage(&settings, &init_logger)?;
 
     // TODO: load network module here too (if needed)
+    let network_tls = start_up::prepare_network_tls(&settings, &init_logger)?;
 
     let block0 = start_up::prepare_block_0(
         &settings,
         &storage,
+        network_tls.as_ref(),
         &init_logger, /* add network to fetch block0 */
     )?;
 
     Ok(InitializedNode {
         settings,
+        command_line,
+        log_drain,
         block0,
         storage,
+        network_tls,
         logger,
+        command,
     })
 }
 
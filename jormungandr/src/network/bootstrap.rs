@@ -1,4 +1,4 @@
-use super::{grpc, BlockConfig};
+use super::{grpc, tls::TlsContext, BlockConfig};
 use crate::blockcfg::{Block, HeaderHash};
 use crate::blockchain::{
     Blockchain, Error as BlockchainError, PreCheckedHeader, Ref, Tip, MAIN_BRANCH_TAG,
@@ -72,6 +72,7 @@ impl error::Error for Error {
 
 pub fn bootstrap_from_peer(
     peer: Peer,
+    tls: Option<TlsContext>,
     blockchain: Blockchain,
     mut branch: Tip,
     logger: &Logger,
@@ -80,7 +81,7 @@ pub fn bootstrap_from_peer(
 
     let mut storage = blockchain.storage().clone();
 
-    let bootstrap = grpc::connect(peer.address(), None)
+    let bootstrap = grpc::connect(peer.address(), None, tls)
         .map_err(Error::Connect)
         .and_then(|client: Connection<BlockConfig>| client.ready().map_err(Error::ClientNotReady))
         .join(branch.get_ref().map_err(|_| unreachable!()))
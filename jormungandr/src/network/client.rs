@@ -1,5 +1,7 @@
 use super::{
-    chain_pull, grpc,
+    chain_pull,
+    dandelion::RelayedFragment,
+    grpc,
     inbound::InboundProcessing,
     p2p::comm::{PeerComms, Subscription},
     p2p::topology,
@@ -7,7 +9,7 @@ use super::{
     Channels, ConnectionState, GlobalStateR,
 };
 use crate::{
-    blockcfg::{Block, Fragment, Header, HeaderHash},
+    blockcfg::{Block, Header, HeaderHash},
     intercom::{self, BlockMsg, ClientMsg},
 };
 use futures::prelude::*;
@@ -49,7 +51,7 @@ where
     S: core_client::Client,
     S: P2pService<NodeId = topology::NodeId>,
     S: BlockService<Block = Block>,
-    S: FragmentService<Fragment = Fragment>,
+    S: FragmentService<Fragment = RelayedFragment>,
     S: GossipService<Node = topology::Node>,
     S::UploadBlocksFuture: Send + 'static,
     S::FragmentSubscription: Send + 'static,
@@ -299,6 +301,7 @@ where
     // Temporary support for pulling chain blocks without two-stage
     // retrieval.
     fn pull_blocks_to_tip(&mut self, req: ChainPullRequest<HeaderHash>) {
+        let node_id = self.remote_node_id;
         let block_box = self.channels.block_box.clone();
         let logger = self.logger.clone();
         let err_logger = logger.clone();
@@ -320,7 +323,7 @@ where
                     InboundProcessing::with_unary(
                         block_box.clone(),
                         logger.clone(),
-                        |block, reply| BlockMsg::NetworkBlock(block, reply),
+                        move |block, reply| BlockMsg::NetworkBlock(block, Some(node_id), reply),
                     )
                     .sink_map_err(move |e| {
                         warn!(sink_err_logger, "pulled block validation failed: {:?}", e)
@@ -341,6 +344,7 @@ where
     S::GetBlocksStream: Send + 'static,
 {
     fn solicit_blocks(&mut self, block_ids: &[HeaderHash]) {
+        let node_id = self.remote_node_id;
         let block_box = self.channels.block_box.clone();
         let logger = self.logger.clone();
         let err_logger = logger.clone();
@@ -365,7 +369,7 @@ where
                     InboundProcessing::with_unary(
                         block_box.clone(),
                         logger.clone(),
-                        |block, reply| BlockMsg::NetworkBlock(block, reply),
+                        move |block, reply| BlockMsg::NetworkBlock(block, Some(node_id), reply),
                     )
                     .sink_map_err(move |e| {
                         warn!(sink_err_logger, "network block validation failed: {:?}", e)
@@ -475,6 +479,7 @@ pub fn connect(
 ) -> impl Future<Item = (Client<grpc::Connection>, PeerComms), Error = ()> {
     let addr = state.connection;
     let expected_block0 = state.global.block0_hash;
+    let tls = state.global.tls.clone();
     let connect_err_logger = state.logger().clone();
     let ready_err_logger = state.logger().clone();
     let handshake_err_logger = state.logger().clone();
@@ -482,7 +487,7 @@ pub fn connect(
 
     // TODO: we need to filter the `addr` to prevent to connect to invalid address
 
-    grpc::connect(addr, Some(state.global.as_ref().node.id()))
+    grpc::connect(addr, Some(state.global.as_ref().node.id()), tls)
         .map_err(move |e| {
             if let Some(e) = e.connect_error() {
                 info!(connect_err_logger, "error connecting to peer"; "reason" => %e);
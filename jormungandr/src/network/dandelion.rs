@@ -0,0 +1,321 @@
+//! private relay of the fragments (Dandelion)
+//!
+//! The fragments submitted to the node through the REST interface are not
+//! diffused to all the peers right away: they are first sent to a single
+//! peer selected at random (the _stem_ phase). A node relaying a fragment
+//! received along the stem sends it further along the stem too, to another
+//! peer than the one it has been received from, unless it decides to
+//! diffuse it (the _fluff_ phase) with the configured probability. The stem
+//! goes through a few hops on average before the fragment is diffused,
+//! making it harder to link the fragment to the IP address of the node it
+//! has been submitted to. The fragments received in the fluff phase are
+//! always diffused to all the peers.
+//!
+//! The phase is sent along with the fragment: a byte is appended after the
+//! fragments relayed along the stem, the diffused fragments are sent as is.
+//!
+//! A fragment sent along the stem is expected to come back from the network
+//! once it is diffused. If it does not before the stem timeout (the peer may
+//! have dropped it), the node diffuses the fragment itself.
+
+use super::p2p::topology::{Node, NodeId};
+use crate::{
+    blockcfg::{Fragment, FragmentId},
+    settings::start::network::Dandelion as Config,
+};
+use chain_core::property::{self, Deserialize as _, Fragment as _, Serialize as _};
+use jormungandr_lib::interfaces::FragmentOrigin;
+use linked_hash_map::LinkedHashMap;
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, Write},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::timer::Delay;
+
+/// byte appended after a fragment relayed along the stem
+const STEM_PHASE: u8 = 1;
+
+/// number of fragments received from the network the phase is remembered
+/// for, until the fragment pool hands them back to be relayed
+const MAX_RECEIVED_FRAGMENTS: usize = 4096;
+
+/// phase of a fragment relayed between the nodes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Stem,
+    Fluff,
+}
+
+/// a fragment relayed between the nodes, along with its phase
+#[derive(Clone, Debug)]
+pub struct RelayedFragment {
+    pub fragment: Fragment,
+    pub phase: Phase,
+}
+
+impl RelayedFragment {
+    pub fn stem(fragment: Fragment) -> Self {
+        RelayedFragment {
+            fragment,
+            phase: Phase::Stem,
+        }
+    }
+
+    pub fn fluff(fragment: Fragment) -> Self {
+        RelayedFragment {
+            fragment,
+            phase: Phase::Fluff,
+        }
+    }
+}
+
+impl property::Fragment for RelayedFragment {
+    type Id = FragmentId;
+
+    fn id(&self) -> Self::Id {
+        self.fragment.id()
+    }
+}
+
+impl property::Serialize for RelayedFragment {
+    type Error = io::Error;
+
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), Self::Error> {
+        self.fragment.serialize(&mut writer)?;
+        // the diffused fragments are sent without the phase, so the nodes
+        // not supporting Dandelion can still decode them.
+        if self.phase == Phase::Stem {
+            writer.write_all(&[STEM_PHASE])?;
+        }
+        Ok(())
+    }
+}
+
+impl property::Deserialize for RelayedFragment {
+    type Error = io::Error;
+
+    fn deserialize<R: BufRead>(mut reader: R) -> Result<Self, Self::Error> {
+        let fragment = Fragment::deserialize(&mut reader)?;
+        let phase = match reader.fill_buf()?.first().cloned() {
+            None => Phase::Fluff,
+            Some(STEM_PHASE) => Phase::Stem,
+            Some(byte) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid Dandelion phase {}", byte),
+                ));
+            }
+        };
+        if phase == Phase::Stem {
+            reader.consume(1);
+        }
+        Ok(RelayedFragment { fragment, phase })
+    }
+}
+
+/// how to relay a fragment to the peers
+pub enum Relay {
+    /// send the fragment to the given peer only
+    Stem(Node),
+    /// send the fragment to all the peers
+    Fluff,
+}
+
+pub struct Dandelion {
+    fluff_probability: u8,
+    stem_timeout: Duration,
+    /// the fragments sent along the stem which have not been received back
+    /// from the network yet
+    embargoes: Mutex<HashSet<FragmentId>>,
+    /// the phase of the fragments received along the stem and the peer they
+    /// have been received from, the oldest fragments are forgotten first.
+    stems: Mutex<LinkedHashMap<FragmentId, NodeId>>,
+}
+
+impl Dandelion {
+    pub fn new(config: &Config) -> Self {
+        assert!(
+            config.fluff_probability <= 100,
+            "the fluff probability is a percentage"
+        );
+        Dandelion {
+            fluff_probability: config.fluff_probability,
+            stem_timeout: config.stem_timeout,
+            embargoes: Mutex::new(HashSet::new()),
+            stems: Mutex::new(LinkedHashMap::new()),
+        }
+    }
+
+    /// register a fragment received from the given peer. A fragment
+    /// received in the fluff phase ends its embargo.
+    pub fn received(&self, fragment: &RelayedFragment, node_id: NodeId) {
+        let fragment_id = fragment.id();
+        match fragment.phase {
+            Phase::Fluff => {
+                self.end_embargo(&fragment_id);
+            }
+            Phase::Stem => {
+                let mut stems = self.stems.lock().unwrap();
+                stems.insert(fragment_id, node_id);
+                if stems.len() > MAX_RECEIVED_FRAGMENTS {
+                    stems.pop_front();
+                }
+            }
+        }
+    }
+
+    /// select how to relay a fragment among the given peers. The fragments
+    /// submitted to the node are always relayed along the stem, the
+    /// fragments received along the stem are relayed along the stem unless
+    /// the node decides to diffuse them, and the other fragments received
+    /// from the network are always diffused. A fragment is never relayed
+    /// along the stem to the peer it has been received from.
+    pub fn relay(&self, origin: FragmentOrigin, fragment_id: &FragmentId, nodes: &[Node]) -> Relay {
+        let mut rng = rand::thread_rng();
+        let received_from = self.stems.lock().unwrap().remove(fragment_id);
+        let stem = match (origin, received_from) {
+            (FragmentOrigin::Rest, _) => true,
+            (FragmentOrigin::Network, Some(_)) => rng.gen_range(0, 100) >= self.fluff_probability,
+            (FragmentOrigin::Network, None) => false,
+        };
+        if !stem {
+            return Relay::Fluff;
+        }
+        let candidates = nodes
+            .iter()
+            .filter(|node| Some(node.id()) != received_from)
+            .collect::<Vec<_>>();
+        match candidates.choose(&mut rng) {
+            Some(node) => Relay::Stem((*node).clone()),
+            None => Relay::Fluff,
+        }
+    }
+
+    /// register a fragment relayed along the stem, the returned delay
+    /// expires at the end of the stem timeout.
+    pub fn embargo(&self, fragment_id: FragmentId) -> Delay {
+        self.embargoes.lock().unwrap().insert(fragment_id);
+        Delay::new(Instant::now() + self.stem_timeout)
+    }
+
+    /// unregister a fragment relayed along the stem, returns `true` if the
+    /// fragment was still waiting to be received back from the network.
+    pub fn end_embargo(&self, fragment_id: &FragmentId) -> bool {
+        self.embargoes.lock().unwrap().remove(fragment_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockcfg::ConfigParams;
+    use chain_core::property::{Deserialize as _, Fragment as _, Serialize as _};
+
+    fn dandelion(fluff_probability: u8) -> Dandelion {
+        Dandelion::new(&Config {
+            fluff_probability,
+            stem_timeout: Duration::from_secs(30),
+        })
+    }
+
+    fn fragment() -> Fragment {
+        Fragment::Initial(ConfigParams::new())
+    }
+
+    fn stem_target(relay: Relay) -> Option<NodeId> {
+        match relay {
+            Relay::Stem(node) => Some(node.id()),
+            Relay::Fluff => None,
+        }
+    }
+
+    #[test]
+    fn phase_round_trip() {
+        for relayed in vec![
+            RelayedFragment::stem(fragment()),
+            RelayedFragment::fluff(fragment()),
+        ] {
+            let bytes = relayed.serialize_as_vec().unwrap();
+            let decoded = RelayedFragment::deserialize(bytes.as_slice()).unwrap();
+            assert_eq!(decoded.phase, relayed.phase);
+            assert_eq!(decoded.id(), relayed.id());
+        }
+    }
+
+    #[test]
+    fn fluff_is_sent_as_a_plain_fragment() {
+        let relayed = RelayedFragment::fluff(fragment());
+        assert_eq!(
+            relayed.serialize_as_vec().unwrap(),
+            relayed.fragment.serialize_as_vec().unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_phase() {
+        let mut bytes = fragment().serialize_as_vec().unwrap();
+        bytes.push(STEM_PHASE + 1);
+        assert!(RelayedFragment::deserialize(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn submitted_fragments_are_stemmed() {
+        let dandelion = dandelion(100);
+        let nodes = vec![Node::new(None)];
+        let fragment_id = fragment().id();
+        assert_eq!(
+            stem_target(dandelion.relay(FragmentOrigin::Rest, &fragment_id, &nodes)),
+            Some(nodes[0].id())
+        );
+        assert!(stem_target(dandelion.relay(FragmentOrigin::Rest, &fragment_id, &[])).is_none());
+    }
+
+    #[test]
+    fn fluffed_fragments_are_diffused() {
+        let dandelion = dandelion(0);
+        let nodes = vec![Node::new(None), Node::new(None)];
+        let relayed = RelayedFragment::fluff(fragment());
+        dandelion.received(&relayed, nodes[0].id());
+        let relay = dandelion.relay(FragmentOrigin::Network, &relayed.id(), &nodes);
+        assert!(stem_target(relay).is_none());
+    }
+
+    #[test]
+    fn stemmed_fragments_are_not_sent_back() {
+        let dandelion = dandelion(0);
+        let nodes = vec![Node::new(None), Node::new(None)];
+        let relayed = RelayedFragment::stem(fragment());
+        for _ in 0..16 {
+            dandelion.received(&relayed, nodes[0].id());
+            let relay = dandelion.relay(FragmentOrigin::Network, &relayed.id(), &nodes);
+            assert_eq!(stem_target(relay), Some(nodes[1].id()));
+        }
+        dandelion.received(&relayed, nodes[0].id());
+        let relay = dandelion.relay(FragmentOrigin::Network, &relayed.id(), &nodes[..1]);
+        assert!(stem_target(relay).is_none());
+    }
+
+    #[test]
+    fn stemmed_fragments_are_fluffed_with_the_probability() {
+        let dandelion = dandelion(100);
+        let nodes = vec![Node::new(None), Node::new(None)];
+        let relayed = RelayedFragment::stem(fragment());
+        dandelion.received(&relayed, nodes[0].id());
+        let relay = dandelion.relay(FragmentOrigin::Network, &relayed.id(), &nodes);
+        assert!(stem_target(relay).is_none());
+    }
+
+    #[test]
+    fn fluffed_fragments_end_the_embargo() {
+        let dandelion = dandelion(0);
+        let node = Node::new(None);
+        let fragment = fragment();
+        let _timeout = dandelion.embargo(fragment.id());
+        dandelion.received(&RelayedFragment::stem(fragment.clone()), node.id());
+        dandelion.received(&RelayedFragment::fluff(fragment.clone()), node.id());
+        assert!(!dandelion.end_embargo(&fragment.id()));
+    }
+}
@@ -1,12 +1,13 @@
 use crate::{
     blockcfg::{Block, HeaderHash},
     network::p2p::topology::NodeId,
+    network::tls::{Stream, TlsContext},
     network::BlockConfig,
     settings::start::network::Peer,
 };
 use futures::prelude::*;
 use http::{HttpTryFrom, Uri};
-use hyper::client::connect::{Destination, HttpConnector};
+use hyper::client::connect::{Connect as HttpConnect, Connected, Destination, HttpConnector};
 use network_core::client::{BlockService, Client as _};
 use network_core::error as core_error;
 use network_grpc::client::{Connect, ConnectError, ConnectFuture};
@@ -52,13 +53,40 @@ impl Error for FetchBlockError {
 
 pub type Connection = network_grpc::client::Connection<BlockConfig>;
 
+/// establishes the connections to the peers, with TLS if it is enabled
+#[derive(Clone)]
+pub struct Connector {
+    http: HttpConnector,
+    tls: Option<TlsContext>,
+}
+
+impl HttpConnect for Connector {
+    type Transport = Stream;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (Stream, Connected), Error = io::Error> + Send>;
+
+    fn connect(&self, dst: Destination) -> Self::Future {
+        let connecting = self.http.connect(dst);
+        match self.tls.clone() {
+            None => {
+                Box::new(connecting.map(|(stream, connected)| (Stream::Plain(stream), connected)))
+            }
+            Some(tls) => Box::new(connecting.and_then(move |(stream, connected)| {
+                tls.connect(stream).map(|stream| (stream, connected))
+            })),
+        }
+    }
+}
+
 pub fn connect(
     addr: SocketAddr,
     node_id: Option<NodeId>,
-) -> ConnectFuture<BlockConfig, HttpConnector, DefaultExecutor> {
+    tls: Option<TlsContext>,
+) -> ConnectFuture<BlockConfig, Connector, DefaultExecutor> {
     let uri = destination_uri(addr);
-    let mut connector = HttpConnector::new(2);
-    connector.set_nodelay(true);
+    let mut http = HttpConnector::new(2);
+    http.set_nodelay(true);
+    let connector = Connector { http, tls };
     let mut builder = Connect::new(connector, DefaultExecutor::current());
     if let Some(id) = node_id {
         builder.node_id(id);
@@ -80,10 +108,11 @@ fn destination_uri(addr: SocketAddr) -> Uri {
 pub fn fetch_block(
     peer: Peer,
     hash: &HeaderHash,
+    tls: Option<TlsContext>,
     logger: &Logger,
 ) -> Result<Block, FetchBlockError> {
     info!(logger, "fetching block {}", hash);
-    let fetch = connect(peer.address(), None)
+    let fetch = connect(peer.address(), None, tls)
         .map_err(|err| FetchBlockError::Connect { source: err })
         .and_then(move |client: Connection| {
             client
@@ -1,8 +1,8 @@
 mod client;
 mod server;
 
-use super::{p2p::topology as p2p, BlockConfig};
-use crate::blockcfg::{Block, BlockDate, Fragment, FragmentId, Header, HeaderHash};
+use super::{dandelion::RelayedFragment, p2p::topology as p2p, BlockConfig};
+use crate::blockcfg::{Block, BlockDate, FragmentId, Header, HeaderHash};
 
 pub use self::client::{connect, fetch_block, Connection, FetchBlockError};
 pub use self::server::run_listen_socket;
@@ -12,7 +12,7 @@ impl network_grpc::client::ProtocolConfig for BlockConfig {
     type Header = Header;
     type BlockId = HeaderHash;
     type BlockDate = BlockDate;
-    type Fragment = Fragment;
+    type Fragment = RelayedFragment;
     type FragmentId = FragmentId;
     type Node = p2p::Node;
     type NodeId = p2p::NodeId;
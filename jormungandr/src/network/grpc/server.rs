@@ -1,8 +1,16 @@
-use super::super::{service::NodeService, Channels, GlobalStateR};
+use super::super::{service::NodeService, tls::Stream, Channels, GlobalStateR};
 use crate::settings::start::network::Listen;
+use futures::future::Either;
 use network_grpc::server::{self, Server};
+use std::time::Duration;
 use tokio::prelude::*;
 
+/// time allowed to the incoming connections to complete the TLS handshake
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// maximum number of incoming connections in the TLS handshake at once
+const MAX_TLS_HANDSHAKES: usize = 64;
+
 pub fn run_listen_socket(
     listen: Listen,
     state: GlobalStateR,
@@ -26,6 +34,9 @@ pub fn run_listen_socket(
         Ok(listener_stream) => {
             let fold_logger = state.logger().clone();
             let err_logger = state.logger().clone();
+            let handshake_logger = state.logger().clone();
+            let tls = state.tls.clone();
+            let fold_state = state.clone();
             let node_server = NodeService::new(channels, state);
             let server = Server::new(node_server);
 
@@ -39,14 +50,43 @@ pub fn run_listen_socket(
                         "Error while accepting connection on {}: {:?}", sockaddr, err
                     );
                 })
+                .map(move |stream| {
+                    let tls = match &tls {
+                        None => return Either::A(future::ok(Some(Stream::Plain(stream)))),
+                        Some(tls) => tls,
+                    };
+                    let logger =
+                        handshake_logger.new(o!("peer_addr" => stream.peer_addr().ok()));
+                    Either::B(
+                        tls.accept(stream)
+                            .timeout(TLS_HANDSHAKE_TIMEOUT)
+                            .then(move |res| match res {
+                                Ok(stream) => Ok(Some(stream)),
+                                Err(err) => {
+                                    info!(logger, "incoming P2P TLS handshake failed"; "reason" => %err);
+                                    Ok(None)
+                                }
+                            }),
+                    )
+                })
+                .buffer_unordered(MAX_TLS_HANDSHAKES)
+                .filter_map(|stream| stream)
                 .fold(server, move |mut server, stream| {
                     // received incoming connection
                     let conn_logger =
                         fold_logger.new(o!("peer_addr" => stream.peer_addr().unwrap()));
+                    if fold_state.is_shutting_down() {
+                        info!(
+                            conn_logger,
+                            "refusing incoming P2P connection, the node is shutting down"
+                        );
+                        return future::ok(server);
+                    }
                     info!(
                         conn_logger,
                         "incoming P2P connection on {}",
-                        stream.local_addr().unwrap(),
+                        stream.local_addr().unwrap();
+                        "fingerprint" => stream.peer_fingerprint().map(|fp| fp.to_string()),
                     );
 
                     let conn = server.serve(stream);
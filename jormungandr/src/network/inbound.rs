@@ -8,7 +8,7 @@ use slog::Logger;
 
 use std::mem;
 
-pub type MsgFunc<T, Msg> = fn(T, ReplyHandle<()>) -> Msg;
+pub type MsgFunc<T, Msg> = Box<dyn Fn(T, ReplyHandle<()>) -> Msg + Send + Sync>;
 
 pub struct InboundProcessing<T, Msg> {
     state: State<Msg>,
@@ -61,10 +61,13 @@ impl<Msg> State<Msg> {
 }
 
 impl<T, Msg> InboundProcessing<T, Msg> {
-    pub fn with_unary(mbox: MessageBox<Msg>, logger: Logger, f: MsgFunc<T, Msg>) -> Self {
+    pub fn with_unary<F>(mbox: MessageBox<Msg>, logger: Logger, f: F) -> Self
+    where
+        F: Fn(T, ReplyHandle<()>) -> Msg + Send + Sync + 'static,
+    {
         InboundProcessing {
             state: State::Ready(mbox),
-            conv: f,
+            conv: Box::new(f),
             logger,
         }
     }
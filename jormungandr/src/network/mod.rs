@@ -7,11 +7,13 @@
 
 pub mod bootstrap;
 mod client;
+mod dandelion;
 mod grpc;
 mod inbound;
 pub mod p2p;
 mod service;
 mod subscription;
+pub mod tls;
 
 // Constants
 
@@ -25,11 +27,13 @@ mod chain_pull {
     pub const CHUNK_SIZE: usize = 32;
 }
 
+use self::dandelion::{Dandelion, Relay, RelayedFragment};
 use self::p2p::{
     comm::{PeerComms, Peers},
     topology::{self, P2pTopology},
 };
-use crate::blockcfg::{Block, HeaderHash};
+use self::tls::TlsContext;
+use crate::blockcfg::{Block, Fragment, Header, HeaderHash};
 use crate::blockchain::{Blockchain as NewBlockchain, Tip};
 use crate::intercom::{BlockMsg, ClientMsg, NetworkMsg, PropagateMsg, TransactionMsg};
 use crate::settings::start::network::{Configuration, Peer, Protocol};
@@ -37,12 +41,21 @@ use crate::utils::{
     async_msg::{MessageBox, MessageQueue},
     task::TaskMessageBox,
 };
+use chain_core::property::Fragment as _;
 use futures::prelude::*;
 use futures::stream;
 use network_core::gossip::{Gossip, Node};
 use rand::seq::SliceRandom;
 use slog::Logger;
-use std::{iter, net::SocketAddr, sync::Arc, time::Duration};
+use std::{
+    iter,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use tokio::timer::Interval;
 
 pub use self::bootstrap::Error as BootstrapError;
@@ -74,17 +87,29 @@ pub struct GlobalState {
     pub config: Configuration,
     pub topology: P2pTopology,
     pub node: topology::Node,
+    pub trusted_peers: topology::modules::TrustedPeers,
     pub peers: Peers,
+    pub dandelion: Option<Dandelion>,
+    pub tls: Option<TlsContext>,
     pub logger: Logger,
+    /// set once the node is shutting down: no new connection is made
+    /// or accepted
+    shutting_down: AtomicBool,
 }
 
 type GlobalStateR = Arc<GlobalState>;
 
 impl GlobalState {
     /// the network global state
-    pub fn new(block0_hash: HeaderHash, config: Configuration, logger: Logger) -> Self {
+    pub fn new(
+        block0_hash: HeaderHash,
+        config: Configuration,
+        tls: Option<TlsContext>,
+        logger: Logger,
+    ) -> Self {
         let node_address = config.public_address.clone().map(|addr| addr.0.into());
         let mut node = topology::Node::new(node_address);
+        node.set_fingerprint(tls.as_ref().map(TlsContext::fingerprint));
 
         use self::p2p::topology::{NEW_BLOCKS_TOPIC, NEW_MESSAGES_TOPIC};
 
@@ -99,29 +124,54 @@ impl GlobalState {
 
         let mut topology = P2pTopology::new(node.clone(), logger.clone());
         topology.set_poldercast_modules();
-        topology.add_module(topology::modules::TrustedPeers::new_with(
+        let trusted_peers = topology::modules::TrustedPeers::new_with(
             config
                 .trusted_peers
                 .iter()
                 .cloned()
                 .map(|trusted_peer| poldercast::Node::new_with(trusted_peer)),
-        ));
+        );
+        topology.add_module(trusted_peers.clone());
 
         let peers = Peers::new(config.max_connections, logger.clone());
+        let dandelion = config.dandelion.as_ref().map(Dandelion::new);
 
         GlobalState {
             block0_hash,
             config,
             topology,
             node,
+            trusted_peers,
             peers,
+            dandelion,
+            tls,
             logger,
+            shutting_down: AtomicBool::new(false),
         }
     }
 
     pub fn logger(&self) -> &Logger {
         &self.logger
     }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// attach to a gossiped node the fingerprint of its certificate,
+    /// if it is known.
+    fn with_fingerprint(&self, mut node: topology::Node) -> topology::Node {
+        if let Some(tls) = &self.tls {
+            let fingerprint = if node.id() == self.node.id() {
+                Some(tls.fingerprint())
+            } else {
+                node.address()
+                    .and_then(|address| tls.peer_fingerprint(&address))
+            };
+            node.set_fingerprint(fingerprint);
+        }
+        node
+    }
 }
 
 pub struct ConnectionState {
@@ -157,6 +207,7 @@ pub struct TaskParams {
     pub block0_hash: HeaderHash,
     pub input: MessageQueue<NetworkMsg>,
     pub channels: Channels,
+    pub tls: Option<TlsContext>,
     pub logger: Logger,
 }
 
@@ -170,6 +221,7 @@ pub fn run(params: TaskParams) {
     let global_state = Arc::new(GlobalState::new(
         params.block0_hash,
         params.config,
+        params.tls,
         logger.clone(),
     ));
 
@@ -261,49 +313,149 @@ fn handle_network_input(
             state.peers.pull_headers(node_id, from.into(), to);
             Ok(())
         }
+        NetworkMsg::MisbehavingPeer(node_id) => {
+            warn!(state.logger(), "dropping misbehaving peer"; "node_id" => node_id.to_string());
+            state.peers.remove_peer(node_id);
+            state.topology.evict_node(node_id);
+            Ok(())
+        }
+        NetworkMsg::Reconfigure {
+            trusted_peers,
+            max_connections,
+        } => {
+            info!(state.logger(), "applying the reloaded P2P settings");
+            state.trusted_peers.set(
+                trusted_peers
+                    .into_iter()
+                    .map(|trusted_peer| poldercast::Node::new_with(trusted_peer)),
+            );
+            state.peers.set_max_connections(max_connections);
+            Ok(())
+        }
+        NetworkMsg::Shutdown => {
+            info!(
+                state.logger(),
+                "node shutting down, disconnecting from the peers"
+            );
+            state.shutting_down.store(true, Ordering::SeqCst);
+            state.peers.clear();
+            Ok(())
+        }
     })
 }
 
 fn handle_propagation_msg(msg: PropagateMsg, state: GlobalStateR, channels: Channels) {
     trace!(state.logger(), "to propagate: {:?}", &msg);
     let nodes = state.topology.view().collect::<Vec<_>>();
+    match msg {
+        PropagateMsg::Block(header) => propagate_block(header, nodes, state, channels),
+        PropagateMsg::Fragment(origin, fragment) => {
+            let relay = state.dandelion.as_ref().map_or(Relay::Fluff, |dandelion| {
+                dandelion.relay(origin, &fragment.id(), &nodes)
+            });
+            match relay {
+                Relay::Stem(node) => {
+                    debug!(
+                        state.logger(),
+                        "relaying fragment along the stem";
+                        "fragment_id" => fragment.id().to_string(),
+                        "node_id" => node.id().to_string(),
+                    );
+                    fluff_on_stem_timeout(fragment.clone(), state.clone(), channels.clone());
+                    propagate_fragment(RelayedFragment::stem(fragment), vec![node], state, channels)
+                }
+                Relay::Fluff => {
+                    propagate_fragment(RelayedFragment::fluff(fragment), nodes, state, channels)
+                }
+            }
+        }
+    }
+}
+
+/// diffuse the fragment relayed along the stem if it has not been received
+/// back from the network before the stem timeout.
+fn fluff_on_stem_timeout(fragment: Fragment, state: GlobalStateR, channels: Channels) {
+    let fragment_id = fragment.id();
+    let timeout = match &state.dandelion {
+        Some(dandelion) => dandelion.embargo(fragment_id),
+        None => return,
+    };
+    tokio::spawn(timeout.then(move |_| {
+        let stalled = state
+            .dandelion
+            .as_ref()
+            .map_or(false, |dandelion| dandelion.end_embargo(&fragment_id));
+        if stalled {
+            debug!(
+                state.logger(),
+                "stem timeout expired, diffusing fragment";
+                "fragment_id" => fragment_id.to_string(),
+            );
+            let nodes = state.topology.view().collect::<Vec<_>>();
+            propagate_fragment(RelayedFragment::fluff(fragment), nodes, state, channels);
+        }
+        Ok(())
+    }));
+}
+
+fn propagate_block(
+    header: Header,
+    nodes: Vec<topology::Node>,
+    state: GlobalStateR,
+    channels: Channels,
+) {
     debug!(
         state.logger(),
-        "will propagate to: {:?}",
+        "will propagate block to: {:?}",
         nodes.iter().map(|node| node.id()).collect::<Vec<_>>()
     );
-    let res = match msg {
-        PropagateMsg::Block(ref header) => state.peers.propagate_block(nodes, header.clone()),
-        PropagateMsg::Fragment(ref fragment) => {
-            state.peers.propagate_fragment(nodes, fragment.clone())
-        }
-    };
     // If any nodes selected for propagation are not in the
     // active subscriptions map, connect to them and deliver
     // the item.
-    if let Err(unreached_nodes) = res {
+    if let Err(unreached_nodes) = state.peers.propagate_block(nodes, header.clone()) {
         for node in unreached_nodes {
-            let msg = msg.clone();
-            connect_and_propagate_with(
-                node,
-                state.clone(),
-                channels.clone(),
-                |handles| match msg {
-                    PropagateMsg::Block(header) => handles
-                        .try_send_block_announcement(header)
-                        .map_err(|e| e.kind()),
-                    PropagateMsg::Fragment(fragment) => {
-                        handles.try_send_fragment(fragment).map_err(|e| e.kind())
-                    }
-                },
-            );
+            let header = header.clone();
+            connect_and_propagate_with(node, state.clone(), channels.clone(), |handles| {
+                handles
+                    .try_send_block_announcement(header)
+                    .map_err(|e| e.kind())
+            });
+        }
+    }
+}
+
+fn propagate_fragment(
+    fragment: RelayedFragment,
+    nodes: Vec<topology::Node>,
+    state: GlobalStateR,
+    channels: Channels,
+) {
+    debug!(
+        state.logger(),
+        "will propagate fragment to: {:?}",
+        nodes.iter().map(|node| node.id()).collect::<Vec<_>>()
+    );
+    if let Err(unreached_nodes) = state.peers.propagate_fragment(nodes, fragment.clone()) {
+        for node in unreached_nodes {
+            let fragment = fragment.clone();
+            connect_and_propagate_with(node, state.clone(), channels.clone(), |handles| {
+                handles.try_send_fragment(fragment).map_err(|e| e.kind())
+            });
         }
     }
 }
 
 fn send_gossip(state: GlobalStateR, channels: Channels) {
+    if state.is_shutting_down() {
+        return;
+    }
     for node in state.topology.view() {
-        let gossip = Gossip::from_nodes(state.topology.select_gossips(&node));
+        let gossip = Gossip::from_nodes(
+            state
+                .topology
+                .select_gossips(&node)
+                .map(|gossip| state.with_fingerprint(gossip)),
+        );
         debug!(state.logger(), "sending gossip to node {}", node.id());
         let res = state.peers.propagate_gossip_to(node.id(), gossip);
         if let Err(gossip) = res {
@@ -322,6 +474,9 @@ fn connect_and_propagate_with<F>(
 ) where
     F: FnOnce(&mut PeerComms) -> Result<(), p2p::comm::ErrorKind> + Send + 'static,
 {
+    if state.is_shutting_down() {
+        return;
+    }
     let addr = match node.address() {
         Some(addr) => addr,
         None => {
@@ -388,6 +543,7 @@ fn trusted_peers_shuffled(config: &Configuration) -> Vec<SocketAddr> {
 
 pub fn bootstrap(
     config: &Configuration,
+    tls: Option<&TlsContext>,
     blockchain: NewBlockchain,
     branch: Tip,
     logger: &Logger,
@@ -405,7 +561,13 @@ pub fn bootstrap(
     for address in trusted_peers_shuffled(&config) {
         let logger = logger.new(o!("peer_addr" => address.to_string()));
         let peer = Peer::new(address, Protocol::Grpc);
-        let res = bootstrap::bootstrap_from_peer(peer, blockchain.clone(), branch.clone(), &logger);
+        let res = bootstrap::bootstrap_from_peer(
+            peer,
+            tls.cloned(),
+            blockchain.clone(),
+            branch.clone(),
+            &logger,
+        );
 
         match res {
             Err(bootstrap::Error::Connect(err)) => {
@@ -431,6 +593,7 @@ pub fn bootstrap(
 /// to retrieve the genesis block.
 pub fn fetch_block(
     config: &Configuration,
+    tls: Option<&TlsContext>,
     hash: &HeaderHash,
     logger: &Logger,
 ) -> Result<Block, FetchBlockError> {
@@ -449,7 +612,7 @@ pub fn fetch_block(
     for address in trusted_peers_shuffled(&config) {
         let logger = logger.new(o!("peer_address" => address.to_string()));
         let peer = Peer::new(address, Protocol::Grpc);
-        match grpc::fetch_block(peer, hash, &logger) {
+        match grpc::fetch_block(peer, hash, tls.cloned(), &logger) {
             Err(err) => {
                 warn!(logger, "error downloading block" ; "reason" => err.to_string());
             }
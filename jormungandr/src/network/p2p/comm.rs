@@ -1,7 +1,8 @@
 mod peer_map;
 
 use super::topology;
-use crate::blockcfg::{Block, Fragment, Header, HeaderHash};
+use crate::blockcfg::{Block, Header, HeaderHash};
+use crate::network::dandelion::RelayedFragment;
 use futures::prelude::*;
 use futures::{stream, sync::mpsc};
 use network_core::error as core_error;
@@ -143,7 +144,7 @@ pub struct PeerComms {
     block_announcements: CommHandle<Header>,
     block_solicitations: CommHandle<Vec<HeaderHash>>,
     chain_pulls: CommHandle<ChainPullRequest<HeaderHash>>,
-    fragments: CommHandle<Fragment>,
+    fragments: CommHandle<RelayedFragment>,
     gossip: CommHandle<Gossip<topology::Node>>,
 }
 
@@ -161,8 +162,8 @@ impl PeerComms {
 
     pub fn try_send_fragment(
         &mut self,
-        fragment: Fragment,
-    ) -> Result<(), PropagateError<Fragment>> {
+        fragment: RelayedFragment,
+    ) -> Result<(), PropagateError<RelayedFragment>> {
         self.fragments.try_send(fragment)
     }
 
@@ -185,7 +186,7 @@ impl PeerComms {
         self.chain_pulls.subscribe()
     }
 
-    pub fn subscribe_to_fragments(&mut self) -> Subscription<Fragment> {
+    pub fn subscribe_to_fragments(&mut self) -> Subscription<RelayedFragment> {
         self.fragments.subscribe()
     }
 
@@ -211,6 +212,11 @@ impl Peers {
         }
     }
 
+    pub fn set_max_connections(&self, max_connections: usize) {
+        let mut map = self.mutex.lock().unwrap();
+        map.set_capacity(max_connections);
+    }
+
     pub fn insert_peer(&self, id: topology::NodeId, comms: PeerComms) {
         let mut map = self.mutex.lock().unwrap();
         map.insert_peer(id, comms)
@@ -221,6 +227,12 @@ impl Peers {
         map.remove_peer(id);
     }
 
+    /// drop the communication handles of all the peers
+    pub fn clear(&self) {
+        let mut map = self.mutex.lock().unwrap();
+        map.clear();
+    }
+
     pub fn subscribe_to_block_events(&self, id: topology::NodeId) -> BlockEventSubscription {
         let mut map = self.mutex.lock().unwrap();
         let handles = map.ensure_peer_comms(id);
@@ -239,7 +251,7 @@ impl Peers {
             .select(missing_events)
     }
 
-    pub fn subscribe_to_fragments(&self, id: topology::NodeId) -> Subscription<Fragment> {
+    pub fn subscribe_to_fragments(&self, id: topology::NodeId) -> Subscription<RelayedFragment> {
         let mut map = self.mutex.lock().unwrap();
         let handles = map.ensure_peer_comms(id);
         handles.fragments.subscribe()
@@ -307,7 +319,7 @@ impl Peers {
     pub fn propagate_fragment(
         &self,
         nodes: Vec<topology::Node>,
-        fragment: Fragment,
+        fragment: RelayedFragment,
     ) -> Result<(), Vec<topology::Node>> {
         self.propagate_with(nodes, |handles| handles.try_send_fragment(fragment.clone()))
     }
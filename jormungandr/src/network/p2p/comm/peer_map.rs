@@ -16,6 +16,15 @@ impl PeerMap {
         }
     }
 
+    /// change the maximum number of peers, the least recently used
+    /// peers are evicted if there are too many
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.map.len() > self.capacity {
+            self.map.pop_front();
+        }
+    }
+
     pub fn entry<'a>(&'a mut self, id: NodeId) -> Option<Entry<'a>> {
         use linked_hash_map::Entry::*;
 
@@ -45,6 +54,10 @@ impl PeerMap {
         self.map.remove(&id)
     }
 
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
     pub fn next_peer_for_block_fetch(&mut self) -> Option<(NodeId, &mut PeerComms)> {
         self.map
             .iter_mut()
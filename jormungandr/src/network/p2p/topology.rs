@@ -1,6 +1,7 @@
 //! module defining the p2p topology management objects
 //!
 
+use crate::network::tls::Fingerprint;
 use bincode;
 use chain_core::property;
 use network_core::gossip::{self, Node as _};
@@ -29,8 +30,10 @@ impl From<bincode::Error> for Error {
     }
 }
 
+/// a node of the P2P topology, along with the fingerprint of its TLS
+/// certificate if it is known.
 #[derive(Clone, Debug)]
-pub struct Node(poldercast::Node);
+pub struct Node(poldercast::Node, Option<Fingerprint>);
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct NodeId(pub poldercast::Id);
@@ -59,14 +62,21 @@ impl Node {
     #[inline]
     pub fn new(address: Option<Address>) -> Self {
         if let Some(address) = address {
-            Node(poldercast::Node::new_with(address))
+            Node(poldercast::Node::new_with(address), None)
         } else {
-            Node(poldercast::Node::new(
-                &mut rand::rngs::OsRng::new().unwrap(),
-            ))
+            Node(
+                poldercast::Node::new(&mut rand::rngs::OsRng::new().unwrap()),
+                None,
+            )
         }
     }
 
+    /// set the fingerprint of the TLS certificate of the node advertised
+    /// in the gossips
+    pub fn set_fingerprint(&mut self, fingerprint: Option<Fingerprint>) {
+        self.1 = fingerprint;
+    }
+
     pub fn add_message_subscription(&mut self, interest_level: InterestLevel) {
         self.0
             .add_subscription(Subscription::new(NEW_MESSAGES_TOPIC.into(), interest_level));
@@ -162,17 +172,30 @@ pub struct P2pTopology {
 impl property::Serialize for Node {
     type Error = Error;
 
-    fn serialize<W: std::io::Write>(&self, writer: W) -> Result<(), Self::Error> {
-        Ok(bincode::serialize_into(writer, &self.0)?)
+    fn serialize<W: std::io::Write>(&self, mut writer: W) -> Result<(), Self::Error> {
+        bincode::serialize_into(&mut writer, &self.0)?;
+        // the fingerprint is appended after the node, so the nodes not
+        // supporting TLS can still decode the gossip.
+        if let Some(fingerprint) = &self.1 {
+            writer.write_all(fingerprint.as_ref())?;
+        }
+        Ok(())
     }
 }
 
 impl property::Deserialize for Node {
     type Error = Error;
 
-    fn deserialize<R: std::io::BufRead>(reader: R) -> Result<Self, Self::Error> {
-        let inner = bincode::deserialize_from(reader)?;
-        Ok(Node(inner))
+    fn deserialize<R: std::io::BufRead>(mut reader: R) -> Result<Self, Self::Error> {
+        let inner = bincode::deserialize_from(&mut reader)?;
+        let fingerprint = if reader.fill_buf()?.is_empty() {
+            None
+        } else {
+            let mut bytes = [0; 32];
+            reader.read_exact(&mut bytes)?;
+            Some(Fingerprint::from(bytes))
+        };
+        Ok(Node(inner, fingerprint))
     }
 }
 
@@ -224,7 +247,7 @@ impl P2pTopology {
     /// to contact for event dissemination.
     pub fn view(&self) -> impl Iterator<Item = Node> {
         let topology = self.lock.read().unwrap();
-        topology.view().into_iter().map(Node)
+        topology.view().into_iter().map(|node| Node(node, None))
     }
 
     /// this is the function to utilise when we receive a gossip in order
@@ -258,7 +281,7 @@ impl P2pTopology {
         topology
             .select_gossips(&gossip_recipient.0)
             .into_iter()
-            .map(|(_, v)| Node(v))
+            .map(|(_, v)| Node(v, None))
     }
 
     pub fn evict_node(&self, id: NodeId) {
@@ -269,10 +292,17 @@ impl P2pTopology {
 
 pub mod modules {
     use poldercast::{topology::Module, Id, Node};
-    use std::collections::BTreeMap;
-
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, RwLock},
+    };
+
+    /// the trusted peers of the node, the clones of the module share the
+    /// same list so it can be updated after the module has been added to
+    /// the topology.
+    #[derive(Clone)]
     pub struct TrustedPeers {
-        peers: Vec<Node>,
+        peers: Arc<RwLock<Vec<Node>>>,
     }
     impl TrustedPeers {
         pub fn new_with<I>(nodes: I) -> Self
@@ -280,9 +310,17 @@ pub mod modules {
             I: IntoIterator<Item = Node>,
         {
             TrustedPeers {
-                peers: nodes.into_iter().collect(),
+                peers: Arc::new(RwLock::new(nodes.into_iter().collect())),
             }
         }
+
+        /// replace the list of trusted peers
+        pub fn set<I>(&self, nodes: I)
+        where
+            I: IntoIterator<Item = Node>,
+        {
+            *self.peers.write().unwrap() = nodes.into_iter().collect();
+        }
     }
 
     impl Module for TrustedPeers {
@@ -304,12 +342,13 @@ pub mod modules {
         }
         fn view(&self, _: &BTreeMap<Id, Node>, view: &mut BTreeMap<Id, Node>) {
             const MAX_TRUSTED_PEER_VIEW: usize = 4;
+            let peers = self.peers.read().unwrap();
             let count = std::cmp::max(
                 MAX_TRUSTED_PEER_VIEW,
-                self.peers.len() - std::cmp::min(self.peers.len(), view.len()),
+                peers.len() - std::cmp::min(peers.len(), view.len()),
             );
             view.extend(
-                self.peers
+                peers
                     .iter()
                     .take(count)
                     .map(|node| (*node.id(), node.clone())),
@@ -1,11 +1,12 @@
 use super::{
     chain_pull,
+    dandelion::RelayedFragment,
     inbound::InboundProcessing,
     p2p::comm::{BlockEventSubscription, Subscription},
     p2p::topology,
     subscription, Channels, GlobalStateR,
 };
-use crate::blockcfg::{Block, BlockDate, Fragment, FragmentId, Header, HeaderHash};
+use crate::blockcfg::{Block, BlockDate, FragmentId, Header, HeaderHash};
 use crate::intercom::{self, BlockMsg, ClientMsg, ReplyFuture, ReplyStream, RequestSink};
 use crate::utils::async_msg::MessageBox;
 use futures::future::{self, FutureResult};
@@ -155,7 +156,7 @@ impl BlockService for NodeService {
         future::ok(InboundProcessing::with_unary(
             self.channels.block_box.clone(),
             self.logger.clone(),
-            |block, handle| BlockMsg::NetworkBlock(block, handle),
+            |block, handle| BlockMsg::NetworkBlock(block, None, handle),
         ))
     }
 
@@ -218,11 +219,11 @@ impl Future for ChainHeadersSinkFuture {
 }
 
 impl FragmentService for NodeService {
-    type Fragment = Fragment;
+    type Fragment = RelayedFragment;
     type FragmentId = FragmentId;
     type GetFragmentsStream = ReplyStream<Self::Fragment, core_error::Error>;
     type GetFragmentsFuture = ReplyFuture<Self::GetFragmentsStream, core_error::Error>;
-    type FragmentSubscription = Subscription<Fragment>;
+    type FragmentSubscription = Subscription<RelayedFragment>;
     type FragmentSubscriptionFuture = FutureResult<Self::FragmentSubscription, core_error::Error>;
 
     fn get_fragments(&mut self, _ids: &[Self::FragmentId]) -> Self::GetFragmentsFuture {
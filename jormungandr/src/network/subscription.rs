@@ -1,9 +1,10 @@
 use super::{
+    dandelion::RelayedFragment,
     p2p::topology::{Node, NodeId},
     GlobalState, GlobalStateR,
 };
 use crate::{
-    blockcfg::{Fragment, Header},
+    blockcfg::Header,
     intercom::{BlockMsg, TransactionMsg},
     settings::start::network::Configuration,
     utils::async_msg::MessageBox,
@@ -12,7 +13,7 @@ use futures::prelude::*;
 use futures::sink;
 use jormungandr_lib::interfaces::FragmentOrigin;
 use network_core::error as core_error;
-use network_core::gossip::Gossip;
+use network_core::gossip::{Gossip, Node as _};
 use slog::Logger;
 
 pub fn process_block_announcements<S>(
@@ -91,7 +92,7 @@ pub fn process_fragments<S>(
     logger: Logger,
 ) -> tokio::executor::Spawn
 where
-    S: Stream<Item = Fragment, Error = core_error::Error> + Send + 'static,
+    S: Stream<Item = RelayedFragment, Error = core_error::Error> + Send + 'static,
 {
     let stream_err_logger = logger.clone();
     let sink_err_logger = logger.clone();
@@ -104,7 +105,10 @@ where
         })
         .map(move |fragment| {
             global_state.peers.refresh_peer(node_id);
-            TransactionMsg::SendTransaction(FragmentOrigin::Network, vec![fragment])
+            if let Some(dandelion) = &global_state.dandelion {
+                dandelion.received(&fragment, node_id);
+            }
+            TransactionMsg::SendTransaction(FragmentOrigin::Network, vec![fragment.fragment])
         });
     tokio::spawn(
         transaction_box
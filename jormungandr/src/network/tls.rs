@@ -0,0 +1,417 @@
+//! TLS of the connections between the nodes
+//!
+//! Every node has its own self-signed certificate, generated at the first
+//! start. The connections are mutually authenticated: both ends present
+//! their certificate. Since the certificates are not signed by an
+//! authority, a peer is authenticated with the fingerprint (the SHA-256
+//! digest) of its certificate:
+//!
+//! * the fingerprints of the trusted peers are pinned in the node
+//!   configuration, they are the only fingerprints checked;
+//! * the fingerprint presented by the other peers on the outgoing
+//!   connections is remembered for their exact socket address, the last one
+//!   presented replaces the previous one. It is only advertised along with
+//!   the address of the peer in the gossips, it is never checked since it
+//!   may have been presented by a man-in-the-middle.
+//!
+//! When connecting to a peer whose fingerprint is pinned, the connection is
+//! dropped if the certificate presented by the peer does not match. The
+//! certificates presented in the handshakes must be well formed and valid,
+//! the only verification failure tolerated is the certificate being
+//! self-signed.
+
+use crate::settings::start::network::Tls as Config;
+use futures::future::{self, Either};
+use linked_hash_map::LinkedHashMap;
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    error::ErrorStack,
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    ssl::{SslAcceptor, SslConnector, SslMethod, SslRef, SslVerifyMode},
+    x509::{X509NameBuilder, X509Ref, X509StoreContextRef, X509},
+};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::{self, Read, Write},
+    net::SocketAddr,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    prelude::*,
+};
+use tokio_openssl::SslStream;
+
+/// validity of the generated certificates
+const CERTIFICATE_VALIDITY_DAYS: u32 = 10 * 365;
+
+/// common name of the generated certificates, the peers are not
+/// authenticated with their name but with the fingerprint of their
+/// certificate.
+const CERTIFICATE_COMMON_NAME: &str = "jormungandr";
+
+/// verification error of a self-signed certificate, `X509_V_ERR_DEPTH_ZERO_SELF_SIGNED_CERT`
+const DEPTH_ZERO_SELF_SIGNED_CERT: i32 = 18;
+
+/// number of peer addresses the fingerprint presented on the last outgoing
+/// connection is remembered for
+const MAX_PRESENTED_FINGERPRINTS: usize = 1024;
+
+custom_error! {pub Error
+    Io { source: io::Error } = "cannot access the certificate or the private key",
+    Openssl { source: ErrorStack } = "invalid certificate or private key",
+}
+
+custom_error! {pub FingerprintError
+    InvalidLength { length: usize } = "invalid fingerprint length, expected 64 hexadecimal characters but got {length}",
+    InvalidCharacter = "invalid fingerprint, expected hexadecimal characters",
+}
+
+/// SHA-256 digest of the DER encoding of a certificate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    fn of(certificate: &X509Ref) -> Result<Self, ErrorStack> {
+        let digest = certificate.digest(MessageDigest::sha256())?;
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(&digest);
+        Ok(Fingerprint(bytes))
+    }
+
+    fn of_peer(ssl: &SslRef) -> io::Result<Self> {
+        let certificate = ssl.peer_certificate().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "the peer has no certificate")
+        })?;
+        Fingerprint::of(&certificate).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl From<[u8; 32]> for Fingerprint {
+    fn from(bytes: [u8; 32]) -> Self {
+        Fingerprint(bytes)
+    }
+}
+
+impl AsRef<[u8]> for Fingerprint {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = FingerprintError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 64 {
+            return Err(FingerprintError::InvalidLength { length: s.len() });
+        }
+        let mut bytes = [0; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = s
+                .get(2 * i..2 * i + 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or(FingerprintError::InvalidCharacter)?;
+        }
+        Ok(Fingerprint(bytes))
+    }
+}
+
+impl Serialize for Fingerprint {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Fingerprint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+}
+
+/// the certificate and the private key of the node
+struct Identity {
+    certificate: X509,
+    private_key: PKey<Private>,
+}
+
+impl Identity {
+    /// load the certificate and the private key of the node, they are
+    /// generated if none of the files exist yet.
+    fn load_or_generate(certificate: &Path, private_key: &Path) -> Result<Self, Error> {
+        if certificate.exists() || private_key.exists() {
+            Ok(Identity {
+                certificate: X509::from_pem(&fs::read(certificate)?)?,
+                private_key: PKey::private_key_from_pem(&fs::read(private_key)?)?,
+            })
+        } else {
+            let identity = Identity::generate()?;
+            fs::write(certificate, identity.certificate.to_pem()?)?;
+            fs::write(
+                private_key,
+                identity.private_key.private_key_to_pem_pkcs8()?,
+            )?;
+            Ok(identity)
+        }
+    }
+
+    fn generate() -> Result<Self, ErrorStack> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let private_key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::COMMONNAME, CERTIFICATE_COMMON_NAME)?;
+        let name = name.build();
+
+        let mut serial_number = BigNum::new()?;
+        serial_number.rand(128, MsbOption::MAYBE_ZERO, false)?;
+
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        builder.set_serial_number(&serial_number.to_asn1_integer()?)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&private_key)?;
+        builder.set_not_before(&Asn1Time::days_from_now(0)?)?;
+        builder.set_not_after(&Asn1Time::days_from_now(CERTIFICATE_VALIDITY_DAYS)?)?;
+        builder.sign(&private_key, MessageDigest::sha256())?;
+
+        Ok(Identity {
+            certificate: builder.build(),
+            private_key,
+        })
+    }
+}
+
+/// the TLS settings of the node, cloning it shares the known fingerprints
+#[derive(Clone)]
+pub struct TlsContext {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    fingerprint: Fingerprint,
+    acceptor: SslAcceptor,
+    connector: SslConnector,
+    /// fingerprints pinned in the configuration, the only ones checked.
+    pinned: HashMap<SocketAddr, Fingerprint>,
+    /// fingerprint presented on the last outgoing connection to a peer
+    /// address, the least recently connected addresses are forgotten first.
+    presented: RwLock<LinkedHashMap<SocketAddr, Fingerprint>>,
+}
+
+/// verification of the certificates presented in the handshakes: the
+/// certificates are self-signed, any other verification failure (expired
+/// certificate, malformed certificate...) drops the connection. The peers
+/// are then authenticated with the fingerprint of their certificate.
+fn verify_certificate(preverify_ok: bool, context: &mut X509StoreContextRef) -> bool {
+    preverify_ok
+        || (context.error_depth() == 0 && context.error().as_raw() == DEPTH_ZERO_SELF_SIGNED_CERT)
+}
+
+impl TlsContext {
+    pub fn new(config: &Config) -> Result<Self, Error> {
+        let identity = Identity::load_or_generate(&config.certificate, &config.private_key)?;
+
+        let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+        acceptor.set_certificate(&identity.certificate)?;
+        acceptor.set_private_key(&identity.private_key)?;
+        acceptor.check_private_key()?;
+        acceptor.set_verify_callback(
+            SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+            verify_certificate,
+        );
+
+        let mut connector = SslConnector::builder(SslMethod::tls())?;
+        connector.set_certificate(&identity.certificate)?;
+        connector.set_private_key(&identity.private_key)?;
+        connector.check_private_key()?;
+        connector.set_verify_callback(SslVerifyMode::PEER, verify_certificate);
+
+        let pinned = config
+            .trusted_peers_fingerprints
+            .iter()
+            .filter_map(|(address, fingerprint)| {
+                address
+                    .to_socketaddr()
+                    .map(|address| (address, *fingerprint))
+            })
+            .collect();
+
+        Ok(TlsContext {
+            inner: Arc::new(Inner {
+                fingerprint: Fingerprint::of(&identity.certificate)?,
+                acceptor: acceptor.build(),
+                connector: connector.build(),
+                pinned,
+                presented: RwLock::new(LinkedHashMap::new()),
+            }),
+        })
+    }
+
+    /// the fingerprint of the certificate of the node
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.inner.fingerprint
+    }
+
+    /// the fingerprint of the peer at the given address to advertise in
+    /// the gossips: the pinned one if any, otherwise the one presented on
+    /// the last outgoing connection to this address.
+    pub fn peer_fingerprint(&self, address: &SocketAddr) -> Option<Fingerprint> {
+        self.inner
+            .pinned
+            .get(address)
+            .cloned()
+            .or_else(|| self.inner.presented.read().unwrap().get(address).cloned())
+    }
+
+    /// remember the fingerprint presented on an outgoing connection to the
+    /// given address, it replaces the one presented previously.
+    fn presented(&self, address: SocketAddr, fingerprint: Fingerprint) {
+        let mut presented = self.inner.presented.write().unwrap();
+        presented.insert(address, fingerprint);
+        if presented.len() > MAX_PRESENTED_FINGERPRINTS {
+            presented.pop_front();
+        }
+    }
+
+    /// run the TLS handshake of an incoming connection
+    pub fn accept(&self, stream: TcpStream) -> impl Future<Item = Stream, Error = io::Error> {
+        tokio_openssl::accept_async(&self.inner.acceptor, stream)
+            .map_err(|err| io::Error::new(io::ErrorKind::ConnectionAborted, err.to_string()))
+            .map(Stream::Tls)
+    }
+
+    /// run the TLS handshake of an outgoing connection, the certificate of
+    /// the peer is checked against the fingerprint pinned for its address in
+    /// the configuration, if any.
+    pub fn connect(&self, stream: TcpStream) -> impl Future<Item = Stream, Error = io::Error> {
+        let address = match stream.peer_addr() {
+            Ok(address) => address,
+            Err(err) => return Either::A(future::err(err)),
+        };
+        let expected = self.inner.pinned.get(&address).cloned();
+        let context = self.clone();
+        let configuration = match self.inner.connector.configure() {
+            Ok(configuration) => configuration
+                .use_server_name_indication(false)
+                .verify_hostname(false),
+            Err(err) => {
+                return Either::A(future::err(io::Error::new(io::ErrorKind::Other, err)));
+            }
+        };
+        Either::B(
+            tokio_openssl::connect_async(configuration, CERTIFICATE_COMMON_NAME, stream)
+                .map_err(|err| io::Error::new(io::ErrorKind::ConnectionAborted, err.to_string()))
+                .and_then(move |stream| {
+                    let fingerprint = Fingerprint::of_peer(stream.get_ref().ssl())?;
+                    match expected {
+                        Some(expected) if expected != fingerprint => Err(io::Error::new(
+                            io::ErrorKind::PermissionDenied,
+                            format!(
+                                "certificate fingerprint mismatch, expected {} but the peer presented {}",
+                                expected, fingerprint
+                            ),
+                        )),
+                        _ => {
+                            context.presented(address, fingerprint);
+                            Ok(Stream::Tls(stream))
+                        }
+                    }
+                }),
+        )
+    }
+}
+
+/// a connection between two nodes, with or without TLS
+pub enum Stream {
+    Plain(TcpStream),
+    Tls(SslStream<TcpStream>),
+}
+
+impl Stream {
+    fn tcp(&self) -> &TcpStream {
+        match self {
+            Stream::Plain(stream) => stream,
+            Stream::Tls(stream) => stream.get_ref().get_ref(),
+        }
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp().peer_addr()
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp().local_addr()
+    }
+
+    /// the fingerprint of the certificate of the peer, if the connection
+    /// uses TLS.
+    pub fn peer_fingerprint(&self) -> Option<Fingerprint> {
+        match self {
+            Stream::Plain(_) => None,
+            Stream::Tls(stream) => Fingerprint::of_peer(stream.get_ref().ssl()).ok(),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.read(buf),
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buf),
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+impl AsyncRead for Stream {}
+
+impl AsyncWrite for Stream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        match self {
+            Stream::Plain(stream) => AsyncWrite::shutdown(stream),
+            Stream::Tls(stream) => AsyncWrite::shutdown(stream),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! reloading of the node configuration while the node is running
+//!
+//! The node configuration file is read again (the command line arguments
+//! still taking precedence) when the node receives `SIGHUP` (unix only) or
+//! when it is requested through the REST API. Only the [`RuntimeSettings`]
+//! are applied, the other modified entries of the configuration are
+//! reported as requiring a restart of the node.
+//!
+//! [`RuntimeSettings`]: ../settings/start/struct.RuntimeSettings.html
+
+use crate::{
+    fragment,
+    intercom::NetworkMsg,
+    leadership,
+    log::ReloadableDrain,
+    settings::{
+        logging::Error as LogError,
+        start::{Error as SettingsError, RawSettings, RuntimeSettings, Settings},
+        CommandLine,
+    },
+    utils::{async_msg::MessageBox, reloadable::Reloadable},
+};
+use slog::Logger;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    prelude::{
+        future::{
+            self,
+            Either::{A, B},
+        },
+        Future,
+    },
+    sync::lock::Lock,
+};
+
+custom_error! {pub Error
+    Settings { source: SettingsError } = "cannot load the node configuration",
+    Log { source: LogError } = "cannot apply the new log settings",
+    Network = "cannot send the new P2P settings to the network task",
+}
+
+/// outcome of a reload of the node configuration
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// the modified entries which have been applied
+    pub applied: Vec<&'static str>,
+    /// the modified entries which will be taken into account at the
+    /// next start of the node
+    pub requires_restart: Vec<&'static str>,
+}
+
+/// the parts of the node affected by a reload of the configuration
+pub struct Handles {
+    pub log_drain: ReloadableDrain,
+    pub fragment_pool: fragment::Pool,
+    pub fragment_logs: fragment::Logs,
+    pub mempool_garbage_collection_interval: Reloadable<Duration>,
+    pub leadership_logs: leadership::Logs,
+    pub leadership_garbage_collection_interval: Reloadable<Duration>,
+    pub network_msgbox: MessageBox<NetworkMsg>,
+}
+
+#[derive(Clone)]
+pub struct Reloader {
+    command_line: Arc<CommandLine>,
+    started: Arc<Settings>,
+    current: Lock<RuntimeSettings>,
+    handles: Arc<Handles>,
+    logger: Logger,
+}
+
+impl Reloader {
+    /// `settings` are the settings the node has been started with, the
+    /// reloaded settings are compared to them.
+    pub fn new(
+        command_line: CommandLine,
+        settings: Settings,
+        handles: Handles,
+        logger: Logger,
+    ) -> Self {
+        Reloader {
+            command_line: Arc::new(command_line),
+            current: Lock::new(settings.runtime()),
+            started: Arc::new(settings),
+            handles: Arc::new(handles),
+            logger,
+        }
+    }
+
+    /// read the node configuration again and apply the modified
+    /// runtime settings.
+    ///
+    /// Nothing is applied if the configuration cannot be loaded, if the
+    /// new log settings are not valid or if the new P2P settings cannot be
+    /// sent to the network task. The outcome is logged.
+    pub fn reload(&self) -> impl Future<Item = Report, Error = Error> {
+        let logger = self.logger.clone();
+        self.reload_settings().then(move |result| {
+            log_result(&logger, &result);
+            result
+        })
+    }
+
+    fn reload_settings(&self) -> impl Future<Item = Report, Error = Error> {
+        let settings = RawSettings::load((*self.command_line).clone())
+            .and_then(|raw_settings| raw_settings.try_into_settings(&self.logger));
+        let settings = match settings {
+            Err(error) => return A(future::err(error.into())),
+            Ok(settings) => settings,
+        };
+
+        let requires_restart = self.started.fields_requiring_restart(&settings);
+        let runtime = settings.runtime();
+        let handles = self.handles.clone();
+        let mut current = self.current.clone();
+
+        B(
+            future::poll_fn(move || Ok(current.poll_lock())).and_then(move |mut current| {
+                let applied = current.changed_fields(&runtime);
+                apply(&handles, &current, runtime).map(move |runtime| {
+                    *current = runtime;
+                    Report {
+                        applied,
+                        requires_restart,
+                    }
+                })
+            }),
+        )
+    }
+}
+
+fn apply(
+    handles: &Handles,
+    previous: &RuntimeSettings,
+    settings: RuntimeSettings,
+) -> impl Future<Item = RuntimeSettings, Error = Error> {
+    // everything which may fail is done before anything is applied, so
+    // a failed reload leaves the previous settings in place
+    let drain = if previous.log != settings.log {
+        match settings.log.to_drain() {
+            Err(error) => return A(future::err(error.into())),
+            Ok(drain) => Some(drain),
+        }
+    } else {
+        None
+    };
+
+    if previous.trusted_peers != settings.trusted_peers
+        || previous.max_connections != settings.max_connections
+    {
+        let msg = NetworkMsg::Reconfigure {
+            trusted_peers: settings.trusted_peers.clone(),
+            max_connections: settings.max_connections,
+        };
+        if handles.network_msgbox.clone().try_send(msg).is_err() {
+            return A(future::err(Error::Network));
+        }
+    }
+
+    if let Some(drain) = drain {
+        handles.log_drain.swap(drain);
+    }
+
+    handles
+        .mempool_garbage_collection_interval
+        .set(settings.mempool.garbage_collection_interval.into());
+    handles
+        .leadership_garbage_collection_interval
+        .set(settings.leadership.garbage_collection_interval.into());
+
+    B(handles
+        .fragment_pool
+        .set_ttl(settings.mempool.fragment_ttl.into())
+        .join3(
+            handles.fragment_logs.set_ttl(settings.mempool.log_ttl.into()),
+            handles
+                .leadership_logs
+                .set_ttl(settings.leadership.log_ttl.into()),
+        )
+        .map_err(|()| unreachable!())
+        .map(move |_| settings))
+}
+
+/// reload the node configuration every time the node receives `SIGHUP`
+#[cfg(unix)]
+pub fn reload_on_sighup(
+    service_info: crate::utils::task::TokioServiceInfo,
+    reloader: Reloader,
+) -> impl Future<Item = (), Error = ()> {
+    use tokio::prelude::Stream;
+    use tokio_signal::unix::{Signal, SIGHUP};
+
+    let logger = service_info.logger().clone();
+    let error_logger = logger.clone();
+    Signal::new(SIGHUP)
+        .flatten_stream()
+        .map_err(move |error| {
+            error!(error_logger, "cannot listen to SIGHUP" ; "reason" => error.to_string());
+        })
+        .for_each(move |_signal| {
+            info!(logger, "SIGHUP received, reloading the node configuration");
+            reloader.reload().then(|_| Ok(()))
+        })
+}
+
+fn log_result(logger: &Logger, result: &Result<Report, Error>) {
+    match result {
+        Err(error) => {
+            error!(logger, "cannot reload the node configuration" ; "reason" => error.to_string())
+        }
+        Ok(report) => {
+            info!(
+                logger,
+                "node configuration reloaded";
+                "applied" => report.applied.join(", ")
+            );
+            if !report.requires_restart.is_empty() {
+                warn!(
+                    logger,
+                    "some modified settings require a restart of the node";
+                    "fields" => report.requires_restart.join(", ")
+                );
+            }
+        }
+    }
+}
@@ -4,25 +4,27 @@ mod server;
 
 pub mod explorer;
 pub mod v0;
+pub mod v1;
 
 pub use self::server::{Error, Server};
 
 use actix_web::dev::Resource;
 use actix_web::middleware::cors::Cors;
 use actix_web::App;
-use futures::{future, Future};
-use std::convert::Infallible;
-use tokio::sync::lock::Lock;
+use slog::Logger;
 
 use crate::blockchain::{Blockchain, Tip};
 use crate::fragment::Logs;
 use crate::leadership::Logs as LeadershipLogs;
+use crate::reload::Reloader;
 use crate::secure::enclave::Enclave;
 use crate::settings::start::{Cors as CorsConfig, Error as ConfigError, Rest};
+use crate::shutdown::Shutdown;
 use crate::stats_counter::StatsCounter;
 
-use crate::intercom::TransactionMsg;
+use crate::intercom::{ClientMsg, TransactionMsg};
 use crate::utils::async_msg::MessageBox;
+use crate::utils::task::{ServicesHealth, TaskMessageBox};
 
 #[derive(Clone)]
 pub struct Context {
@@ -30,14 +32,19 @@ pub struct Context {
     pub blockchain: Blockchain,
     pub blockchain_tip: Tip,
     pub transaction_task: MessageBox<TransactionMsg>,
+    pub client_task: TaskMessageBox<ClientMsg>,
     pub logs: Logs,
     pub leadership_logs: LeadershipLogs,
-    pub server: Lock<Option<Server>>,
+    pub shutdown: Shutdown,
     pub enclave: Enclave,
+    pub reloader: Reloader,
     pub explorer: Option<crate::explorer::Explorer>,
+    pub logger: Logger,
+    pub services: ServicesHealth,
+    pub utxo_index: v0::UtxoIndexCache,
 }
 
-pub fn start_rest_server(config: &Rest, mut context: Context) -> Result<Server, ConfigError> {
+pub fn start_rest_server(config: &Rest, context: Context) -> Result<Server, ConfigError> {
     let app_context = context.clone();
     let cors_cfg = config.cors.clone();
     let explorer_enabled = app_context.explorer.is_some();
@@ -48,6 +55,10 @@ pub fn start_rest_server(config: &Rest, mut context: Context) -> Result<Server,
             v0::resources(),
             &cors_cfg,
         )];
+        apps.push(
+            build_app(app_context.clone(), "/api/v1", v1::resources(), &cors_cfg)
+                .middleware(v1::JsonErrors),
+        );
 
         if explorer_enabled {
             apps.push(build_app(
@@ -60,10 +71,6 @@ pub fn start_rest_server(config: &Rest, mut context: Context) -> Result<Server,
 
         apps
     })?;
-    future::poll_fn(|| Ok(context.server.poll_lock()))
-        .wait()
-        .unwrap_or_else(|e: Infallible| match e {})
-        .replace(server.clone());
     Ok(server)
 }
 
@@ -4,20 +4,27 @@ use jormungandr_lib::time::SystemTime;
 use actix_web::error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound};
 use actix_web::{Error, HttpResponse};
 use actix_web::{Json, Path, Query, Responder, State};
-use chain_core::property::{Block, Deserialize, Serialize as _};
+use chain_core::property::{Block, ChainLength as _, Deserialize, Fragment as _, Serialize as _};
 use chain_crypto::{Blake2b256, PublicKey};
 use chain_impl_mockchain::account::{AccountAlg, Identifier};
 use chain_impl_mockchain::fragment::Fragment;
 use chain_impl_mockchain::key::Hash;
 use chain_impl_mockchain::leadership::{Leader, LeadershipConsensus};
+use chain_impl_mockchain::transaction::Transaction;
 use chain_impl_mockchain::value::{Value, ValueError};
+use chain_storage::error::Error as StorageError;
+use chain_time::era::{EpochPosition, EpochSlotOffset};
+use network_core::error::Code;
 
-use crate::blockchain::Ref;
-use crate::intercom::TransactionMsg;
+use super::utxo_index::UtxoCursor;
+use crate::blockcfg::{Header, HeaderContentEvalContext, HeaderHash, Ledger};
+use crate::blockchain::{Blockchain, ErrorKind, Ref, MAX_REPLAY_DEPTH};
+use crate::client;
+use crate::intercom::{self, ClientMsg, TransactionMsg};
 use crate::secure::NodeSecret;
+use crate::shutdown::Reason as ShutdownReason;
 use bytes::{Bytes, IntoBuf};
 use futures::{future, Future, IntoFuture, Stream};
-use std::convert::Infallible;
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -34,19 +41,238 @@ fn chain_tip_fut<'a>(context: &State<Context>) -> impl Future<Item = Arc<Ref>, E
         .map_err(|infallible| match infallible {})
 }
 
-pub fn get_utxos(context: State<Context>) -> ActixFuture!() {
-    chain_tip_fut(&context).map(|tip_reference| {
-        let utxos = tip_reference.ledger().utxos();
-        let utxos = utxos.map(UTxOInfo::from).collect::<Vec<_>>();
-        Json(utxos)
-    })
+#[derive(Deserialize)]
+pub struct StateQuery {
+    block: Option<String>,
+    chain_length: Option<u32>,
+}
+
+/// the block the state of the ledger is read at
+enum StateAt {
+    Tip,
+    Block(Hash),
+    ChainLength(u32),
+}
+
+fn parse_state_at(query: &StateQuery) -> Result<StateAt, Error> {
+    match (&query.block, query.chain_length) {
+        (None, None) => Ok(StateAt::Tip),
+        (Some(block), None) => parse_block_hash(block).map(StateAt::Block),
+        (None, Some(chain_length)) => Ok(StateAt::ChainLength(chain_length)),
+        (Some(_), Some(_)) => Err(ErrorBadRequest(
+            "Only one of `block' and `chain_length' can be given",
+        )),
+    }
+}
+
+/// the `Ref` of the block given in the query, of the block of the chain of
+/// the tip at the given chain length or the `Ref` of the tip.
+///
+/// The states of the blocks no longer in the cache are recovered by
+/// replaying the blocks from the storage, the request fails if more than
+/// `MAX_REPLAY_DEPTH` blocks would have to be replayed.
+fn chain_ref_fut(
+    context: &State<Context>,
+    query: &StateQuery,
+) -> impl Future<Item = Arc<Ref>, Error = Error> {
+    let blockchain = context.blockchain.clone();
+    let tip = chain_tip_fut(context);
+    parse_state_at(query)
+        .into_future()
+        .and_then(move |state_at| match state_at {
+            StateAt::Tip => future::Either::A(tip),
+            StateAt::Block(block_id) => {
+                future::Either::B(future::Either::A(block_ref_fut(blockchain, block_id)))
+            }
+            StateAt::ChainLength(chain_length) => {
+                let storage = blockchain.storage().clone();
+                future::Either::B(future::Either::B(
+                    tip.and_then(move |tip| {
+                        storage
+                            .get_ancestor_at_depth(tip.hash(), chain_length.into())
+                            .map_err(|e| ErrorInternalServerError(e))
+                    })
+                    .and_then(move |block_id| {
+                        block_id.ok_or_else(|| {
+                            ErrorNotFound(format!("No block at chain length {}", chain_length))
+                        })
+                    })
+                    .and_then(move |block_id| block_ref_fut(blockchain, block_id)),
+                ))
+            }
+        })
+}
+
+fn block_ref_fut(
+    mut blockchain: Blockchain,
+    block_id: Hash,
+) -> impl Future<Item = Arc<Ref>, Error = Error> {
+    blockchain
+        .get_ref_or_replay(block_id, MAX_REPLAY_DEPTH)
+        .map_err(|e| match e.kind() {
+            ErrorKind::ReplayTooDeep(_) => ErrorBadRequest(e.to_string()),
+            _ => ErrorInternalServerError(e.to_string()),
+        })
+        .and_then(|reference| reference.ok_or_else(|| ErrorNotFound("Block not found")))
+}
+
+/// number of UTxOs of a page when `after` is given without `count`
+const DEFAULT_UTXO_COUNT: usize = 100;
+const MAX_UTXO_COUNT: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct UtxoQuery {
+    address: Option<String>,
+    after: Option<String>,
+    count: Option<usize>,
+}
+
+/// the UTxOs of the ledger of the tip, or the ones of the given address.
+///
+/// The UTxOs of a legacy address are the ones declared in the block 0 with
+/// the funds of the legacy chain and not redeemed yet.
+///
+/// The UTxOs are ordered by transaction identifier and output index. All of
+/// them are returned if neither `count` nor `after` is given. Otherwise at
+/// most `count` UTxOs are returned (`DEFAULT_UTXO_COUNT` if not given, up
+/// to `MAX_UTXO_COUNT`), the next page starts `after` the last UTxO of the
+/// page, given as `<transaction_id>:<index>`.
+pub fn get_utxos(
+    context: State<Context>,
+    query: Query<UtxoQuery>,
+    state_query: Query<StateQuery>,
+) -> ActixFuture!() {
+    parse_utxo_address(&query.address)
+        .and_then(|address| Ok((address, parse_utxo_cursor(&query.after)?)))
+        .into_future()
+        .and_then(move |(address, after)| {
+            let count = match (query.count, &after) {
+                (Some(count), _) => count.min(MAX_UTXO_COUNT),
+                (None, Some(_)) => DEFAULT_UTXO_COUNT,
+                (None, None) => usize::max_value(),
+            };
+            let utxo_index = context.utxo_index.clone();
+            chain_ref_fut(&context, &state_query).map(move |tip_reference| match address {
+                Some(UtxoAddress::Legacy(address)) => {
+                    let utxos = legacy_utxos(tip_reference.ledger(), &address);
+                    HttpResponse::Ok().json(paginate(utxos, after, count))
+                }
+                address => {
+                    let utxos =
+                        utxo_index
+                            .get(&tip_reference)
+                            .page(after.as_ref(), count, |utxo| match &address {
+                                Some(UtxoAddress::Address(address)) => {
+                                    utxo.address().as_ref() == address.as_ref()
+                                }
+                                _ => true,
+                            });
+                    HttpResponse::Ok().json(utxos)
+                }
+            })
+        })
+}
+
+pub fn get_utxo(
+    context: State<Context>,
+    path: Path<(String, u8)>,
+    state_query: Query<StateQuery>,
+) -> ActixFuture!() {
+    let (transaction_id, index) = path.into_inner();
+    parse_block_hash(&transaction_id)
+        .into_future()
+        .and_then(move |transaction_id| {
+            let utxo_index = context.utxo_index.clone();
+            let cursor = UtxoCursor {
+                transaction_id,
+                index,
+            };
+            chain_ref_fut(&context, &state_query).and_then(move |tip_reference| {
+                utxo_index
+                    .get(&tip_reference)
+                    .get(&cursor)
+                    .cloned()
+                    .map(Json)
+                    .ok_or_else(|| ErrorNotFound("UTxO not found"))
+            })
+        })
 }
 
-pub fn get_account_state(context: State<Context>, account_id_hex: Path<String>) -> ActixFuture!() {
+enum UtxoAddress {
+    Address(Address),
+    Legacy(OldAddress),
+}
+
+fn parse_utxo_address(address: &Option<String>) -> Result<Option<UtxoAddress>, Error> {
+    match address {
+        None => Ok(None),
+        Some(address) => address
+            .parse()
+            .map(UtxoAddress::Address)
+            .or_else(|_| address.parse().map(UtxoAddress::Legacy))
+            .map(Some)
+            .map_err(|_| ErrorBadRequest("Invalid address")),
+    }
+}
+
+fn parse_utxo_cursor(cursor: &Option<String>) -> Result<Option<UtxoCursor>, Error> {
+    let cursor = match cursor {
+        None => return Ok(None),
+        Some(cursor) => cursor,
+    };
+    let mut parts = cursor.splitn(2, ':');
+    let transaction_id = parse_block_hash(parts.next().unwrap_or_default())?;
+    let index = parts
+        .next()
+        .and_then(|index| index.parse().ok())
+        .ok_or_else(|| ErrorBadRequest("Invalid UTxO cursor, expected <transaction_id>:<index>"))?;
+    Ok(Some(UtxoCursor {
+        transaction_id,
+        index,
+    }))
+}
+
+/// sort the UTxOs and keep the ones of the requested page
+fn paginate<T>(mut utxos: Vec<(UtxoCursor, T)>, after: Option<UtxoCursor>, count: usize) -> Vec<T> {
+    utxos.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    utxos
+        .into_iter()
+        .filter(|(cursor, _)| after.as_ref().map_or(true, |after| cursor > after))
+        .take(count)
+        .map(|(_, utxo)| utxo)
+        .collect()
+}
+
+/// the legacy UTxOs of the address not redeemed yet in the given ledger
+fn legacy_utxos(ledger: &Ledger, address: &OldAddress) -> Vec<(UtxoCursor, LegacyUTxOInfo)> {
+    ledger
+        .old_utxos()
+        .filter(|entry| &entry.output.address == address.as_ref())
+        .map(|entry| {
+            let cursor = UtxoCursor {
+                transaction_id: entry.fragment_id.clone(),
+                index: entry.output_index,
+            };
+            let utxo = LegacyUTxOInfo::new(
+                entry.fragment_id.clone().into(),
+                entry.output_index,
+                address.clone(),
+                entry.output.value.into(),
+            );
+            (cursor, utxo)
+        })
+        .collect()
+}
+
+pub fn get_account_state(
+    context: State<Context>,
+    account_id_hex: Path<String>,
+    state_query: Query<StateQuery>,
+) -> ActixFuture!() {
     parse_account_id(&account_id_hex)
         .into_future()
         .and_then(move |account_id| {
-            chain_tip_fut(&context).map(|tip_reference| (tip_reference, account_id))
+            chain_ref_fut(&context, &state_query).map(|tip_reference| (tip_reference, account_id))
         })
         .and_then(|(tip_reference, account_id)| {
             let state = tip_reference
@@ -73,23 +299,189 @@ pub fn get_message_logs(context: State<Context>) -> ActixFuture!() {
         .map(Json)
 }
 
-pub fn post_message(context: State<Context>, message: Bytes) -> Result<HttpResponse, Error> {
-    let fragment = Fragment::deserialize(message.into_buf()).map_err(ErrorBadRequest)?;
-    let msg = TransactionMsg::SendTransaction(FragmentOrigin::Rest, vec![fragment]);
+/// the maximum number of fragments of a batch
+pub const MAX_FRAGMENTS_BATCH: usize = 100;
+
+#[derive(Deserialize)]
+pub struct MessageQuery {
+    #[serde(default)]
+    wait: bool,
+}
+
+impl MessageQuery {
+    pub fn wait(&self) -> bool {
+        self.wait
+    }
+}
+
+/// post a fragment. With `wait`, the response is sent once the fragment has
+/// been processed by the fragment pool, with the result of its submission.
+pub fn post_message(
+    context: State<Context>,
+    message: Bytes,
+    query: Query<MessageQuery>,
+) -> ActixFuture!() {
+    let context = Context::clone(&context);
+    Fragment::deserialize(message.into_buf())
+        .map_err(ErrorBadRequest)
+        .into_future()
+        .and_then(move |fragment| {
+            if query.wait {
+                future::Either::A(
+                    submit_fragments_fut(&context, vec![fragment])
+                        .and_then(|submissions| {
+                            submissions.into_iter().next().ok_or_else(|| {
+                                ErrorInternalServerError("The fragment has not been processed")
+                            })
+                        })
+                        .map(|submission| HttpResponse::Ok().json(submission)),
+                )
+            } else {
+                let msg = TransactionMsg::SendTransaction(FragmentOrigin::Rest, vec![fragment]);
+                let sent = context
+                    .transaction_task
+                    .clone()
+                    .try_send(msg)
+                    .map_err(|e| ErrorInternalServerError(e))
+                    .map(|()| HttpResponse::Ok().finish());
+                future::Either::B(sent.into_future())
+            }
+        })
+}
+
+/// post a batch of fragments, each one prefixed by its size as a big endian
+/// `u16`. The response is sent once the fragments have been processed by the
+/// fragment pool, with the result of the submission of every fragment in the
+/// order of the batch.
+pub fn post_fragments(context: State<Context>, batch: Bytes) -> ActixFuture!() {
+    let context = Context::clone(&context);
+    parse_fragments_batch(&batch)
+        .into_future()
+        .and_then(move |fragments| submit_fragments_fut(&context, fragments))
+        .map(Json)
+}
+
+fn parse_fragments_batch(mut batch: &[u8]) -> Result<Vec<Fragment>, Error> {
+    let mut fragments = Vec::new();
+    while !batch.is_empty() {
+        if fragments.len() == MAX_FRAGMENTS_BATCH {
+            return Err(ErrorBadRequest(format!(
+                "Too many fragments, at most {} are accepted",
+                MAX_FRAGMENTS_BATCH
+            )));
+        }
+        if batch.len() < 2 {
+            return Err(ErrorBadRequest("Truncated fragment size"));
+        }
+        let size = u16::from_be_bytes([batch[0], batch[1]]) as usize;
+        if batch.len() < 2 + size {
+            return Err(ErrorBadRequest("Truncated fragment"));
+        }
+        let (fragment, rest) = batch[2..].split_at(size);
+        fragments.push(Fragment::deserialize(fragment.into_buf()).map_err(ErrorBadRequest)?);
+        batch = rest;
+    }
+    Ok(fragments)
+}
+
+pub fn submit_fragments_fut(
+    context: &Context,
+    fragments: Vec<Fragment>,
+) -> impl Future<Item = Vec<FragmentSubmission>, Error = Error> {
+    let (reply_handle, reply_future) = intercom::unary_reply(context.logger.clone());
+    let msg = TransactionMsg::SubmitTransaction(FragmentOrigin::Rest, fragments, reply_handle);
     context
         .transaction_task
         .clone()
         .try_send(msg)
-        .map_err(|e| ErrorInternalServerError(e))?;
-    Ok(HttpResponse::Ok().finish())
+        .map_err(|e| ErrorInternalServerError(e))
+        .into_future()
+        .and_then(move |()| reply_future.map_err(|e: intercom::Error| ErrorInternalServerError(e)))
+}
+
+/// apply a fragment on the ledger of the tip, the fragment is neither added
+/// in the fragment pool nor propagated
+pub fn post_fragment_simulate(context: State<Context>, message: Bytes) -> ActixFuture!() {
+    let fragment = Fragment::deserialize(message.into_buf()).map_err(ErrorBadRequest);
+    fragment
+        .into_future()
+        .join(chain_tip_fut(&context))
+        .map(|(fragment, tip)| {
+            let metadata = HeaderContentEvalContext {
+                block_date: *tip.block_date(),
+                chain_length: tip.chain_length().next(),
+                nonce: None,
+            };
+            let parameters = tip.epoch_ledger_parameters();
+            let fragment_id = fragment.id().to_string();
+            let applied = tip
+                .ledger()
+                .apply_fragment(parameters.as_ref(), &fragment, &metadata);
+            Json(match applied {
+                Ok(_) => json!({
+                    "fragment_id": fragment_id,
+                    "valid": true,
+                    "fee": fragment_fee(&fragment).map(|fee| fee.0),
+                }),
+                Err(error) => json!({
+                    "fragment_id": fragment_id,
+                    "valid": false,
+                    "error": error.to_string(),
+                    "causes": error_causes(&error),
+                }),
+            })
+        })
+}
+
+/// the fee paid by a fragment: the value of its inputs not spent by its
+/// outputs
+fn fragment_fee(fragment: &Fragment) -> Option<Value> {
+    match fragment {
+        Fragment::Transaction(tx) => transaction_fee(&tx.transaction),
+        Fragment::OwnerStakeDelegation(tx) => transaction_fee(&tx.transaction),
+        Fragment::StakeDelegation(tx) => transaction_fee(&tx.transaction),
+        Fragment::PoolRegistration(tx) => transaction_fee(&tx.transaction),
+        Fragment::PoolManagement(tx) => transaction_fee(&tx.transaction),
+        _ => None,
+    }
+}
+
+fn transaction_fee<A, E>(tx: &Transaction<A, E>) -> Option<Value> {
+    let input = tx.total_input().ok()?;
+    let output = tx.total_output().ok()?;
+    input.checked_sub(output).ok()
+}
+
+/// the sources of an error, from the closest to the root cause
+fn error_causes(error: &dyn std::error::Error) -> Vec<String> {
+    let mut causes = Vec::new();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+    causes
 }
 
 pub fn get_tip(context: State<Context>) -> ActixFuture!() {
     chain_tip_fut(&context).map(|tip| tip.hash().to_string())
 }
 
+pub fn post_node_config_reload(context: State<Context>) -> ActixFuture!() {
+    context
+        .reloader
+        .reload()
+        .map_err(|e| ErrorInternalServerError(e))
+        .map(Json)
+}
+
+pub fn get_node_services(context: State<Context>) -> impl Responder {
+    Json(context.services.status())
+}
+
 pub fn get_stats_counter(context: State<Context>) -> ActixFuture!() {
     let stats = context.stats_counter.clone();
+    let services = context.services.clone();
     chain_tip_fut(&context)
         .and_then(move |tip| {
             let header = tip.header().clone();
@@ -131,6 +523,8 @@ pub fn get_stats_counter(context: State<Context>) -> ActixFuture!() {
             Ok(Json(json!({
                 "txRecvCnt": stats.tx_recv_cnt(),
                 "blockRecvCnt": stats.block_recv_cnt(),
+                "blockRejectedCnt": stats.block_rejected_cnt(),
+                "blockRejectedReplayCnt": stats.block_rejected_replay_cnt(),
                 "uptime": stats.uptime_sec(),
                 "lastBlockHash": tip_header.hash().to_string(),
                 "lastBlockHeight": tip_header.chain_length().to_string(),
@@ -139,6 +533,7 @@ pub fn get_stats_counter(context: State<Context>) -> ActixFuture!() {
                 "lastBlockTx": block_tx_count,
                 "lastBlockSum": block_input_sum.0,
                 "lastBlockFees": block_fee_sum.0,
+                "queues": services.queues(),
             })))
         })
 }
@@ -153,10 +548,11 @@ pub fn get_block_id(context: State<Context>, block_id_hex: Path<String>) -> Acti
                 .get(block_id)
                 .map_err(|e| ErrorInternalServerError(e))
         })
-        .map(|block| Bytes::from(block.unwrap().serialize_as_vec().unwrap()))
+        .and_then(|block| block.ok_or_else(|| ErrorNotFound("Block not found")))
+        .map(|block| Bytes::from(block.serialize_as_vec().unwrap()))
 }
 
-fn parse_block_hash(hex: &str) -> Result<Hash, Error> {
+pub fn parse_block_hash(hex: &str) -> Result<Hash, Error> {
     Blake2b256::from_str(hex)
         .map_err(|e| ErrorBadRequest(e))
         .map(Into::into)
@@ -192,6 +588,141 @@ pub fn get_block_next_id(
 
 const MAX_COUNT: u64 = 100;
 
+/// the maximum number of blocks sent by a single request of blocks range
+const MAX_BLOCKS_BATCH: u64 = 100;
+
+#[derive(Deserialize)]
+pub struct BlocksRangeQuery {
+    from: String,
+    to: Option<String>,
+    count: Option<u64>,
+}
+
+/// the blocks following `from` (excluded) up to `to` (included, the tip if
+/// not given), concatenated.
+///
+/// At most `MAX_BLOCKS_BATCH` blocks are sent, the next batch starts from
+/// the last block sent.
+pub fn get_blocks(context: State<Context>, query: Query<BlocksRangeQuery>) -> ActixFuture!() {
+    let count = query
+        .count
+        .unwrap_or(MAX_BLOCKS_BATCH)
+        .min(MAX_BLOCKS_BATCH);
+    let client_task = context.client_task.clone();
+    let logger = context.logger.clone();
+    blocks_range_fut(&context, &query, count)
+        .and_then(move |(from, to)| {
+            let (handle, stream) = intercom::stream_reply(logger);
+            client_task.send_to(ClientMsg::GetBlocksRange(from, to, handle));
+            client_stream_fut(stream)
+        })
+        .map(|blocks| {
+            HttpResponse::Ok()
+                .content_type("application/octet-stream")
+                .streaming(
+                    blocks.and_then(|block: chain_impl_mockchain::block::Block| {
+                        block
+                            .serialize_as_vec()
+                            .map(Bytes::from)
+                            .map_err(ErrorInternalServerError)
+                    }),
+                )
+        })
+}
+
+/// the headers of the blocks following `from` (excluded) up to `to`
+/// (included, the tip if not given), each one prefixed by its size as a
+/// big endian `u16`.
+///
+/// At most `client::MAX_HEADERS` headers are sent, the next batch starts
+/// from the last header sent.
+pub fn get_headers(context: State<Context>, query: Query<BlocksRangeQuery>) -> ActixFuture!() {
+    let max_headers = client::MAX_HEADERS as u64;
+    let count = query.count.unwrap_or(max_headers).min(max_headers);
+    let client_task = context.client_task.clone();
+    let logger = context.logger.clone();
+    blocks_range_fut(&context, &query, count)
+        .and_then(move |(from, to)| {
+            let (handle, stream) = intercom::stream_reply(logger);
+            client_task.send_to(ClientMsg::GetHeadersRange(vec![from], to, handle));
+            client_stream_fut(stream)
+        })
+        .map(|headers| {
+            HttpResponse::Ok()
+                .content_type("application/octet-stream")
+                .streaming(headers.and_then(|header: Header| {
+                    let header = header
+                        .serialize_as_vec()
+                        .map_err(ErrorInternalServerError)?;
+                    let mut bytes = Bytes::with_capacity(header.len() + 2);
+                    bytes.extend_from_slice(&(header.len() as u16).to_be_bytes());
+                    bytes.extend_from_slice(&header);
+                    Ok(bytes)
+                }))
+        })
+}
+
+/// the range of at most `count` blocks to send: the `from` block and the
+/// `to` block, or its ancestor `count` blocks after `from`.
+///
+/// The blocks are not read here, the range is streamed by the client task.
+fn blocks_range_fut(
+    context: &State<Context>,
+    query: &BlocksRangeQuery,
+    count: u64,
+) -> impl Future<Item = (HeaderHash, HeaderHash), Error = Error> {
+    let storage = context.blockchain.storage().clone();
+    let tip = chain_tip_fut(context);
+    let range = parse_block_hash(&query.from).and_then(|from| {
+        let to = match &query.to {
+            Some(to) => Some(parse_block_hash(to)?),
+            None => None,
+        };
+        Ok((from, to))
+    });
+    range
+        .into_future()
+        .and_then(move |(from, to)| match to {
+            Some(to) => future::Either::A(future::ok((from, to))),
+            None => future::Either::B(tip.map(move |tip| (from, tip.hash()))),
+        })
+        .and_then(move |(from, to)| {
+            storage
+                .get_block_info(from.clone())
+                .map_err(ErrorInternalServerError)
+                .and_then(|info| info.ok_or_else(|| ErrorNotFound("Block not found")))
+                .and_then(move |from_info| {
+                    storage
+                        .get_ancestor_at_depth(to.clone(), from_info.depth + count)
+                        .then(move |ancestor| match ancestor {
+                            Ok(ancestor) => Ok((from, ancestor.unwrap_or(to))),
+                            Err(StorageError::BlockNotFound) => {
+                                Err(ErrorNotFound("Block not found"))
+                            }
+                            Err(e) => Err(ErrorInternalServerError(e)),
+                        })
+                })
+        })
+}
+
+/// the stream replied by the client task: an error replied before any item
+/// fails the request with the matching status, an error replied later fails
+/// the stream and aborts the response.
+fn client_stream_fut<T>(
+    stream: intercom::ReplyStream<T, intercom::Error>,
+) -> impl Future<Item = impl Stream<Item = T, Error = Error>, Error = Error> {
+    stream.into_future().then(|res| match res {
+        Ok((first, stream)) => {
+            Ok(futures::stream::iter_ok(first).chain(stream.map_err(ErrorInternalServerError)))
+        }
+        Err((e, _)) => Err(match e.code() {
+            Code::NotFound | Code::FailedPrecondition => ErrorNotFound(e),
+            Code::InvalidArgument => ErrorBadRequest(e),
+            _ => ErrorInternalServerError(e),
+        }),
+    })
+}
+
 #[derive(Deserialize)]
 pub struct QueryParams {
     count: Option<u64>,
@@ -203,8 +734,11 @@ impl QueryParams {
     }
 }
 
-pub fn get_stake_distribution(context: State<Context>) -> ActixFuture!() {
-    chain_tip_fut(&context).map(|blockchain_tip| {
+pub fn get_stake_distribution(
+    context: State<Context>,
+    state_query: Query<StateQuery>,
+) -> ActixFuture!() {
+    chain_ref_fut(&context, &state_query).map(|blockchain_tip| {
         let leadership = blockchain_tip.epoch_leadership_schedule();
         let last_epoch = blockchain_tip.block_date().epoch;
         if let LeadershipConsensus::GenesisPraos(gp) = leadership.consensus() {
@@ -228,8 +762,8 @@ pub fn get_stake_distribution(context: State<Context>) -> ActixFuture!() {
     })
 }
 
-pub fn get_settings(context: State<Context>) -> ActixFuture!() {
-    chain_tip_fut(&context).map(move |blockchain_tip| {
+pub fn get_settings(context: State<Context>, state_query: Query<StateQuery>) -> ActixFuture!() {
+    chain_ref_fut(&context, &state_query).map(move |blockchain_tip| {
         let ledger = blockchain_tip.ledger();
         let static_params = ledger.get_static_parameters();
         let consensus_version = ledger.consensus_version();
@@ -257,17 +791,16 @@ pub fn get_settings(context: State<Context>) -> ActixFuture!() {
     })
 }
 
-pub fn get_shutdown(context: State<Context>) -> ActixFuture!() {
+pub fn shutdown(context: State<Context>) -> impl Responder {
     // Server finishes ongoing tasks before stopping, so user will get response to this request
-    // Node should be shutdown automatically when server stopping is finished
-    future::poll_fn(move || Ok(context.server.clone().poll_lock())).then(|server_res| {
-        server_res
-            .unwrap_or_else(|e: Infallible| match e {})
-            .as_ref()
-            .ok_or_else(|| ErrorInternalServerError("Server not set in context"))
-            .map(|server| server.stop())
-            .map(|_| HttpResponse::Ok().finish())
-    })
+    // The node stops the server as the first step of its shutdown
+    if !context.shutdown.request(ShutdownReason::Rest) {
+        info!(
+            context.logger,
+            "shutdown requested, the node is already shutting down"
+        );
+    }
+    HttpResponse::Ok().finish()
 }
 
 pub fn get_leaders(context: State<Context>) -> impl Responder {
@@ -303,6 +836,74 @@ pub fn get_leaders_logs(context: State<Context>) -> ActixFuture!() {
         .map_err(|_| ErrorInternalServerError("Failed to get leader logs"))
 }
 
+pub fn get_leaders_logs_summary(context: State<Context>) -> ActixFuture!() {
+    context
+        .leadership_logs
+        .logs()
+        .map(|logs| Json(LeadershipEpochSummary::summarize(&logs)))
+        .map_err(|_| ErrorInternalServerError("Failed to get leader logs"))
+}
+
+#[derive(Deserialize)]
+pub struct LeadersScheduleQuery {
+    epoch: Option<u32>,
+}
+
+/// list the slots the leaders of the enclave are elected for during the
+/// given epoch (the current epoch by default).
+///
+/// Only the schedule of the current epoch and of the next epoch can be
+/// computed: the stake distribution of the following epochs is not known yet.
+pub fn get_leaders_schedule(
+    context: State<Context>,
+    query: Query<LeadersScheduleQuery>,
+) -> ActixFuture!() {
+    let enclave = context.enclave.clone();
+    let mut blockchain = context.blockchain.clone();
+    chain_tip_fut(&context)
+        .and_then(move |tip| {
+            let current_epoch = tip.block_date().epoch;
+            let epoch = query.epoch.unwrap_or(current_epoch);
+            if epoch < current_epoch {
+                return Err(ErrorNotFound(format!(
+                    "The leadership schedule of epoch {} is no longer available",
+                    epoch
+                )));
+            }
+            if epoch > current_epoch + 1 {
+                return Err(ErrorNotFound(format!(
+                    "The stake distribution of epoch {} is not known yet",
+                    epoch
+                )));
+            }
+            let time_frame = tip.time_frame().clone();
+            let (leadership, _, _, _) = blockchain.new_epoch_leadership_from(epoch, tip);
+            Ok((leadership, time_frame))
+        })
+        .and_then(move |(leadership, time_frame)| {
+            // evaluating the whole epoch is expensive, do not block the server
+            actix_threadpool::run(move || {
+                let era = leadership.era();
+                enclave
+                    .leadership_evaluate(&leadership, 0, era.slots_per_epoch())
+                    .into_iter()
+                    .map(|event| {
+                        let slot = era.from_era_to_slot(EpochPosition {
+                            epoch: chain_time::Epoch(event.date.epoch),
+                            slot: EpochSlotOffset(event.date.slot_id),
+                        });
+                        let time = time_frame.slot_to_systemtime(slot).ok_or_else(|| {
+                            format!("Block date {} is out of the time frame", event.date)
+                        })?;
+                        Ok(LeaderSlot::new(event.id, event.date.into(), time.into()))
+                    })
+                    .collect::<Result<Vec<_>, String>>()
+            })
+            .map_err(|err| ErrorInternalServerError(err))
+        })
+        .map(Json)
+}
+
 pub fn get_stake_pools(context: State<Context>) -> ActixFuture!() {
     chain_tip_fut(&context).map(|blockchain_tip| {
         let stake_pool_ids = blockchain_tip
@@ -1,4 +1,7 @@
-mod handlers;
+pub(super) mod handlers;
+mod utxo_index;
+
+pub use self::utxo_index::UtxoIndexCache;
 
 use actix_web::dev::Resource;
 
@@ -10,15 +13,23 @@ pub fn resources() -> Vec<(
         ("/account/{account_id}", &|r| {
             r.get().with_async(handlers::get_account_state)
         }),
+        ("/blocks", &|r| r.get().with_async(handlers::get_blocks)),
         ("/block/{block_id}", &|r| {
             r.get().with_async(handlers::get_block_id)
         }),
         ("/block/{block_id}/next_id", &|r| {
             r.get().with_async(handlers::get_block_next_id)
         }),
+        ("/fragments", &|r| {
+            r.post().with_async(handlers::post_fragments)
+        }),
+        ("/fragment/simulate", &|r| {
+            r.post().with_async(handlers::post_fragment_simulate)
+        }),
         ("/fragment/logs", &|r| {
             r.get().with_async(handlers::get_message_logs)
         }),
+        ("/headers", &|r| r.get().with_async(handlers::get_headers)),
         ("/leaders", &|r| {
             r.get().with(handlers::get_leaders);
             r.post().with(handlers::post_leaders);
@@ -26,6 +37,12 @@ pub fn resources() -> Vec<(
         ("/leaders/logs", &|r| {
             r.get().with_async(handlers::get_leaders_logs);
         }),
+        ("/leaders/logs/summary", &|r| {
+            r.get().with_async(handlers::get_leaders_logs_summary);
+        }),
+        ("/leaders/schedule", &|r| {
+            r.get().with_async(handlers::get_leaders_schedule);
+        }),
         ("/leaders/{leader_id}", &|r| {
             r.delete().with(handlers::delete_leaders)
         }),
@@ -36,12 +53,21 @@ pub fn resources() -> Vec<(
         ("/stake_pools", &|r| {
             r.get().with_async(handlers::get_stake_pools)
         }),
-        ("/shutdown", &|r| r.get().with_async(handlers::get_shutdown)),
-        ("/message", &|r| r.post().with(handlers::post_message)),
+        ("/shutdown", &|r| r.get().with(handlers::shutdown)),
+        ("/message", &|r| r.post().with_async(handlers::post_message)),
+        ("/node/config/reload", &|r| {
+            r.post().with_async(handlers::post_node_config_reload)
+        }),
+        ("/node/services", &|r| {
+            r.get().with(handlers::get_node_services)
+        }),
         ("/node/stats", &|r| {
             r.get().with_async(handlers::get_stats_counter)
         }),
         ("/tip", &|r| r.get().with_async(handlers::get_tip)),
         ("/utxo", &|r| r.get().with_async(handlers::get_utxos)),
+        ("/utxo/{fragment_id}/{index}", &|r| {
+            r.get().with_async(handlers::get_utxo)
+        }),
     ]
 }
@@ -0,0 +1,99 @@
+//! index of the UTxOs of the ledger of a block, for the listing of the UTxOs
+//!
+//! The UTxOs of the ledger are not ordered: the index sorts them once per
+//! block, the pages of UTxOs and the UTxOs are then looked up in the index.
+//! Only the index of the last block queried is kept.
+
+use crate::{blockcfg::HeaderHash, blockchain::Ref};
+use chain_impl_mockchain::key::Hash;
+use jormungandr_lib::interfaces::UTxOInfo;
+use std::sync::{Arc, Mutex};
+
+/// the position of a UTxO in the listing of the UTxOs, the listing is
+/// ordered by transaction identifier and then by output index.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UtxoCursor {
+    pub transaction_id: Hash,
+    pub index: u8,
+}
+
+pub struct UtxoIndex {
+    block: HeaderHash,
+    utxos: Vec<(UtxoCursor, UTxOInfo)>,
+}
+
+/// the index of the UTxOs of the last block queried, shared by the
+/// handlers of the REST API
+#[derive(Clone, Default)]
+pub struct UtxoIndexCache {
+    last: Arc<Mutex<Option<Arc<UtxoIndex>>>>,
+}
+
+impl UtxoIndexCache {
+    /// the index of the UTxOs of the ledger of the given block, it is built
+    /// if the last index is the one of another block
+    pub fn get(&self, reference: &Ref) -> Arc<UtxoIndex> {
+        let mut last = self.last.lock().unwrap();
+        match &*last {
+            Some(index) if index.block == reference.hash() => index.clone(),
+            _ => {
+                let index = Arc::new(UtxoIndex::new(reference));
+                *last = Some(index.clone());
+                index
+            }
+        }
+    }
+}
+
+impl UtxoIndex {
+    fn new(reference: &Ref) -> Self {
+        let mut utxos: Vec<_> = reference
+            .ledger()
+            .utxos()
+            .map(|entry| {
+                let cursor = UtxoCursor {
+                    transaction_id: entry.fragment_id.clone(),
+                    index: entry.output_index,
+                };
+                (cursor, UTxOInfo::from(entry))
+            })
+            .collect();
+        utxos.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        UtxoIndex {
+            block: reference.hash(),
+            utxos,
+        }
+    }
+
+    pub fn get(&self, cursor: &UtxoCursor) -> Option<&UTxOInfo> {
+        self.utxos
+            .binary_search_by(|(utxo_cursor, _)| utxo_cursor.cmp(cursor))
+            .ok()
+            .map(|position| &self.utxos[position].1)
+    }
+
+    /// at most `count` UTxOs following the `after` cursor (if any) and
+    /// matching the filter
+    pub fn page<F>(&self, after: Option<&UtxoCursor>, count: usize, filter: F) -> Vec<UTxOInfo>
+    where
+        F: Fn(&UTxOInfo) -> bool,
+    {
+        let start = match after {
+            None => 0,
+            Some(after) => match self
+                .utxos
+                .binary_search_by(|(utxo_cursor, _)| utxo_cursor.cmp(after))
+            {
+                Ok(position) => position + 1,
+                Err(position) => position,
+            },
+        };
+        self.utxos[start..]
+            .iter()
+            .map(|(_, utxo)| utxo)
+            .filter(|utxo| filter(utxo))
+            .take(count)
+            .cloned()
+            .collect()
+    }
+}
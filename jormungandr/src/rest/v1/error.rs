@@ -0,0 +1,114 @@
+use actix_web::http::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use actix_web::http::StatusCode;
+use actix_web::middleware::{Middleware, Response};
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use serde_json::Value;
+use std::fmt::{self, Display, Formatter};
+
+/// the kind of an error of the API, the HTTP status of the response
+/// depends on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    BadRequest,
+    NotFound,
+    MethodNotAllowed,
+    NotAcceptable,
+    UnsupportedMediaType,
+    InternalError,
+    ServiceUnavailable,
+}
+
+/// the body of all the error responses of the API
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    code: ErrorCode,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<Value>,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ErrorCode::NotAcceptable => StatusCode::NOT_ACCEPTABLE,
+            ErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// the code of an error response with the given status
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::METHOD_NOT_ALLOWED => ErrorCode::MethodNotAllowed,
+            StatusCode::NOT_ACCEPTABLE => ErrorCode::NotAcceptable,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => ErrorCode::UnsupportedMediaType,
+            StatusCode::SERVICE_UNAVAILABLE => ErrorCode::ServiceUnavailable,
+            status if status.is_client_error() => ErrorCode::BadRequest,
+            _ => ErrorCode::InternalError,
+        }
+    }
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        ApiError {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(self, details: Value) -> Self {
+        ApiError {
+            details: Some(details),
+            ..self
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.code.status()).json(self)
+    }
+}
+
+/// middleware replacing the body of the error responses which are not
+/// already an `ApiError`: the errors of the handlers shared with the v0 of
+/// the API, of the extractors and of the routing
+pub struct JsonErrors;
+
+impl<S> Middleware<S> for JsonErrors {
+    fn response(&self, _: &HttpRequest<S>, response: HttpResponse) -> actix_web::Result<Response> {
+        let status = response.status();
+        let is_json = response.headers().get(CONTENT_TYPE)
+            == Some(&HeaderValue::from_static("application/json"));
+        if !(status.is_client_error() || status.is_server_error()) || is_json {
+            return Ok(Response::Done(response));
+        }
+
+        let message = match response.error() {
+            Some(error) => error.to_string(),
+            None => status.canonical_reason().unwrap_or_default().to_owned(),
+        };
+        let mut builder = HttpResponse::build(status);
+        for (name, value) in response.headers() {
+            if name != CONTENT_TYPE && name != CONTENT_LENGTH {
+                builder.header(name.clone(), value.clone());
+            }
+        }
+        let error = ApiError::new(ErrorCode::from_status(status), message);
+        Ok(Response::Done(builder.json(error)))
+    }
+}
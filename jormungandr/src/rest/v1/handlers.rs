@@ -0,0 +1,308 @@
+use super::error::{ApiError, ErrorCode};
+use crate::intercom::TransactionMsg;
+use crate::rest::v0::handlers::{
+    parse_block_hash, submit_fragments_fut, MessageQuery, QueryParams,
+};
+use jormungandr_lib::interfaces::FragmentOrigin;
+
+use actix_web::http::header::{ACCEPT, CONTENT_TYPE};
+use actix_web::{HttpRequest, HttpResponse, Path, Query};
+use bytes::{Bytes, IntoBuf};
+use chain_core::property::{Block as _, Deserialize, Fragment as _, Serialize as _};
+use chain_impl_mockchain::fragment::Fragment;
+use chain_storage::error::Error as StorageError;
+use futures::future::Either::{A, B};
+use futures::{Future, IntoFuture, Stream};
+
+pub use crate::rest::Context;
+
+const BINARY: &str = "application/octet-stream";
+const JSON: &str = "application/json";
+const TEXT: &str = "text/plain";
+
+/// the supported media type with the highest quality in the `Accept` header
+/// of the request, the first of the supported media types wins a tie. The
+/// preferred one (the first) is chosen if the request has no `Accept` header
+fn negotiate<S>(
+    request: &HttpRequest<S>,
+    supported: &[&'static str],
+) -> Result<&'static str, ApiError> {
+    let accept = match request.headers().get(ACCEPT) {
+        None => return Ok(supported[0]),
+        Some(accept) => accept.to_str().unwrap_or_default(),
+    };
+    let mut best: Option<(&'static str, f32)> = None;
+    for media_type in supported.iter().cloned() {
+        let quality = quality(accept, media_type);
+        if quality > 0.0 && best.map_or(true, |(_, best_quality)| quality > best_quality) {
+            best = Some((media_type, quality));
+        }
+    }
+    best.map(|(media_type, _)| media_type).ok_or_else(|| {
+        ApiError::new(ErrorCode::NotAcceptable, "No acceptable media type")
+            .with_details(json!({ "supported": supported }))
+    })
+}
+
+/// the quality (the `q` parameter, 1 if not given) of the most specific
+/// media range of the `Accept` header matching the media type, 0 if none
+/// matches. A media range with an invalid quality is ignored
+fn quality(accept: &str, media_type: &str) -> f32 {
+    accept
+        .split(',')
+        .filter_map(|media_range| {
+            let mut params = media_range.split(';').map(str::trim);
+            let specificity = match params.next()? {
+                "*/*" => 0,
+                range if range.ends_with("/*") => {
+                    if !media_type.starts_with(&range[..range.len() - 1]) {
+                        return None;
+                    }
+                    1
+                }
+                range if range == media_type => 2,
+                _ => return None,
+            };
+            let quality = match params.find(|param| param.starts_with("q=")) {
+                None => 1.0,
+                Some(param) => param[2..].parse::<f32>().ok()?,
+            };
+            Some((specificity, quality))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, quality)| quality)
+}
+
+/// check the `Content-Type` of the body of the request, the requests
+/// without `Content-Type` are assumed to be of the expected media type
+fn expect_content_type<S>(
+    request: &HttpRequest<S>,
+    expected: &'static str,
+) -> Result<(), ApiError> {
+    let content_type = match request.headers().get(CONTENT_TYPE) {
+        None => return Ok(()),
+        Some(content_type) => content_type.to_str().unwrap_or_default(),
+    };
+    match content_type.split(';').next().map(str::trim) {
+        Some(media_type) if media_type == expected => Ok(()),
+        _ => Err(
+            ApiError::new(ErrorCode::UnsupportedMediaType, "Unsupported media type")
+                .with_details(json!({ "supported": [expected] })),
+        ),
+    }
+}
+
+fn internal_error(error: impl ToString) -> ApiError {
+    ApiError::new(ErrorCode::InternalError, error.to_string())
+}
+
+pub fn get_block_id(
+    request: HttpRequest<Context>,
+    block_id_hex: Path<String>,
+) -> impl Future<Item = HttpResponse, Error = ApiError> {
+    let context = request.state().clone();
+    negotiate(&request, &[BINARY, JSON])
+        .and_then(|media_type| {
+            parse_block_hash(&block_id_hex)
+                .map(|block_id| (media_type, block_id))
+                .map_err(|e| ApiError::new(ErrorCode::BadRequest, e.to_string()))
+        })
+        .into_future()
+        .and_then(move |(media_type, block_id)| {
+            context
+                .blockchain
+                .storage()
+                .get(block_id)
+                .map_err(internal_error)
+                .and_then(|block| {
+                    block.ok_or_else(|| ApiError::new(ErrorCode::NotFound, "Block not found"))
+                })
+                .map(move |block| match media_type {
+                    BINARY => HttpResponse::Ok()
+                        .content_type(BINARY)
+                        .body(block.serialize_as_vec().unwrap()),
+                    _ => HttpResponse::Ok().json(json!({
+                        "id": block.id().to_string(),
+                        "parent_id": block.parent_id().to_string(),
+                        "date": block.date().to_string(),
+                        "chain_length": u32::from(block.chain_length()),
+                        "fragments": block
+                            .contents
+                            .iter()
+                            .map(|fragment| fragment.id().to_string())
+                            .collect::<Vec<_>>(),
+                    })),
+                })
+        })
+}
+
+pub fn get_block_next_id(
+    request: HttpRequest<Context>,
+    block_id_hex: Path<String>,
+    query_params: Query<QueryParams>,
+) -> impl Future<Item = HttpResponse, Error = ApiError> {
+    let context = request.state().clone();
+    let count = query_params.get_count();
+    negotiate(&request, &[BINARY, JSON])
+        .and_then(|media_type| {
+            parse_block_hash(&block_id_hex)
+                .map(|block_id| (media_type, block_id))
+                .map_err(|e| ApiError::new(ErrorCode::BadRequest, e.to_string()))
+        })
+        .into_future()
+        .and_then(move |(media_type, block_id)| {
+            let storage = context.blockchain.storage().clone();
+            context
+                .blockchain_tip
+                .get_ref()
+                .map_err(|infallible| match infallible {})
+                .and_then(move |tip| {
+                    storage
+                        .stream_from_to(block_id, tip.hash())
+                        .then(|res| match res {
+                            Ok(Some(stream)) => Ok(stream.map_err(internal_error)),
+                            Ok(None) => Err(ApiError::new(
+                                ErrorCode::BadRequest,
+                                "The block is not an ancestor of the tip",
+                            )),
+                            Err(StorageError::BlockNotFound) => {
+                                Err(ApiError::new(ErrorCode::NotFound, "Block not found"))
+                            }
+                            Err(e) => Err(internal_error(e)),
+                        })
+                })
+                .flatten_stream()
+                .take(count)
+                .map(|block| block.id())
+                .collect()
+                .map(move |ids| match media_type {
+                    BINARY => {
+                        let mut bytes = Bytes::new();
+                        for id in ids {
+                            bytes.extend_from_slice(id.as_ref());
+                        }
+                        HttpResponse::Ok().content_type(BINARY).body(bytes)
+                    }
+                    _ => HttpResponse::Ok()
+                        .json(ids.iter().map(|id| id.to_string()).collect::<Vec<_>>()),
+                })
+        })
+}
+
+pub fn get_tip(
+    request: HttpRequest<Context>,
+) -> impl Future<Item = HttpResponse, Error = ApiError> {
+    let tip = request
+        .state()
+        .blockchain_tip
+        .get_ref()
+        .map_err(|infallible| match infallible {});
+    negotiate(&request, &[JSON, TEXT])
+        .into_future()
+        .and_then(|media_type| {
+            tip.map(move |tip| match media_type {
+                TEXT => HttpResponse::Ok()
+                    .content_type(TEXT)
+                    .body(tip.hash().to_string()),
+                _ => HttpResponse::Ok().json(json!({ "id": tip.hash().to_string() })),
+            })
+        })
+}
+
+/// submit a fragment to the node. The response is sent once the fragment
+/// has been handed over to the fragment pool, its processing can be
+/// followed with the fragment logs, or with `wait` once it has been processed
+/// by the fragment pool, with the result of its submission
+pub fn post_message(
+    request: HttpRequest<Context>,
+    message: Bytes,
+    query: Query<MessageQuery>,
+) -> impl Future<Item = HttpResponse, Error = ApiError> {
+    let context = request.state().clone();
+    expect_content_type(&request, BINARY)
+        .and_then(|()| negotiate(&request, &[JSON]))
+        .and_then(|_| {
+            Fragment::deserialize(message.into_buf())
+                .map_err(|e| ApiError::new(ErrorCode::BadRequest, e.to_string()))
+        })
+        .into_future()
+        .and_then(move |fragment| {
+            if query.wait() {
+                A(submit_fragments_fut(&context, vec![fragment])
+                    .map_err(internal_error)
+                    .and_then(|submissions| {
+                        submissions
+                            .into_iter()
+                            .next()
+                            .ok_or_else(|| internal_error("The fragment has not been processed"))
+                    })
+                    .map(|submission| HttpResponse::Ok().json(submission)))
+            } else {
+                let fragment_id = fragment.id();
+                let msg = TransactionMsg::SendTransaction(FragmentOrigin::Rest, vec![fragment]);
+                let sent = context
+                    .transaction_task
+                    .clone()
+                    .try_send(msg)
+                    .map_err(|e| ApiError::new(ErrorCode::ServiceUnavailable, e.to_string()))
+                    .map(|()| {
+                        HttpResponse::Accepted()
+                            .json(json!({ "fragment_id": fragment_id.to_string() }))
+                    });
+                B(sent.into_future())
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::test::TestRequest;
+
+    fn negotiate_accept(accept: &str, supported: &[&'static str]) -> Option<&'static str> {
+        let request = TestRequest::with_header(ACCEPT, accept).finish();
+        negotiate(&request, supported).ok()
+    }
+
+    #[test]
+    fn no_accept_header_selects_the_preferred_media_type() {
+        let request = TestRequest::default().finish();
+        assert_eq!(negotiate(&request, &[BINARY, JSON]).ok(), Some(BINARY));
+    }
+
+    #[test]
+    fn the_highest_quality_is_selected() {
+        let accept = "application/octet-stream;q=0.5, application/json";
+        assert_eq!(negotiate_accept(accept, &[BINARY, JSON]), Some(JSON));
+    }
+
+    #[test]
+    fn the_preferred_media_type_wins_a_tie() {
+        assert_eq!(negotiate_accept("*/*", &[BINARY, JSON]), Some(BINARY));
+        assert_eq!(
+            negotiate_accept("application/*", &[JSON, BINARY]),
+            Some(JSON)
+        );
+    }
+
+    #[test]
+    fn a_zero_quality_excludes_the_media_type() {
+        assert_eq!(
+            negotiate_accept("application/json;q=0", &[BINARY, JSON]),
+            None
+        );
+        let accept = "*/*, application/octet-stream; q=0";
+        assert_eq!(negotiate_accept(accept, &[BINARY, JSON]), Some(JSON));
+    }
+
+    #[test]
+    fn a_media_range_with_an_invalid_quality_is_ignored() {
+        assert_eq!(negotiate_accept("application/json;q=high", &[JSON]), None);
+    }
+
+    #[test]
+    fn unsupported_media_types_are_not_acceptable() {
+        assert_eq!(negotiate_accept("text/html", &[BINARY, JSON]), None);
+    }
+}
@@ -0,0 +1,73 @@
+//! the v1 of the REST API of the node: the errors are reported as JSON
+//! objects, the endpoints returning binary or JSON content select it from
+//! the `Accept` header of the request, and the endpoints changing the state
+//! of the node only accept `POST` requests
+
+mod error;
+mod handlers;
+
+pub use self::error::{ApiError, ErrorCode, JsonErrors};
+
+use super::v0::handlers as v0;
+use actix_web::dev::Resource;
+
+pub fn resources() -> Vec<(
+    &'static str,
+    &'static dyn Fn(&mut Resource<handlers::Context>),
+)> {
+    vec![
+        ("/account/{account_id}", &|r| {
+            r.get().with_async(v0::get_account_state)
+        }),
+        ("/blocks", &|r| r.get().with_async(v0::get_blocks)),
+        ("/block/{block_id}", &|r| {
+            r.get().with_async(handlers::get_block_id)
+        }),
+        ("/block/{block_id}/next_id", &|r| {
+            r.get().with_async(handlers::get_block_next_id)
+        }),
+        ("/fragments", &|r| r.post().with_async(v0::post_fragments)),
+        ("/fragment/simulate", &|r| {
+            r.post().with_async(v0::post_fragment_simulate)
+        }),
+        ("/fragment/logs", &|r| {
+            r.get().with_async(v0::get_message_logs)
+        }),
+        ("/headers", &|r| r.get().with_async(v0::get_headers)),
+        ("/leaders", &|r| {
+            r.get().with(v0::get_leaders);
+            r.post().with(v0::post_leaders);
+        }),
+        ("/leaders/logs", &|r| {
+            r.get().with_async(v0::get_leaders_logs);
+        }),
+        ("/leaders/logs/summary", &|r| {
+            r.get().with_async(v0::get_leaders_logs_summary);
+        }),
+        ("/leaders/schedule", &|r| {
+            r.get().with_async(v0::get_leaders_schedule);
+        }),
+        ("/leaders/{leader_id}", &|r| {
+            r.delete().with(v0::delete_leaders)
+        }),
+        ("/settings", &|r| r.get().with_async(v0::get_settings)),
+        ("/stake", &|r| {
+            r.get().with_async(v0::get_stake_distribution)
+        }),
+        ("/stake_pools", &|r| r.get().with_async(v0::get_stake_pools)),
+        ("/shutdown", &|r| r.post().with(v0::shutdown)),
+        ("/message", &|r| r.post().with_async(handlers::post_message)),
+        ("/node/config/reload", &|r| {
+            r.post().with_async(v0::post_node_config_reload)
+        }),
+        ("/node/services", &|r| r.get().with(v0::get_node_services)),
+        ("/node/stats", &|r| {
+            r.get().with_async(v0::get_stats_counter)
+        }),
+        ("/tip", &|r| r.get().with_async(handlers::get_tip)),
+        ("/utxo", &|r| r.get().with_async(v0::get_utxos)),
+        ("/utxo/{fragment_id}/{index}", &|r| {
+            r.get().with_async(v0::get_utxo)
+        }),
+    ]
+}
@@ -8,7 +8,7 @@ use crate::{
     settings::logging::{LogFormat, LogOutput},
 };
 
-#[derive(StructOpt, Debug)]
+#[derive(StructOpt, Debug, Clone)]
 pub struct StartArguments {
     /// Path to the blockchain pool storage directory
     #[structopt(long = "storage", parse(from_os_str))]
@@ -45,7 +45,7 @@ pub struct StartArguments {
     pub explorer_enabled: bool,
 }
 
-#[derive(StructOpt, Debug)]
+#[derive(StructOpt, Debug, Clone)]
 #[structopt(
     name = "jormungandr",
     raw(setting = "structopt::clap::AppSettings::ColoredHelp")
@@ -82,6 +82,27 @@ pub struct CommandLine {
     /// this option is useful for scripting retrieving the logs of the version of this application.
     #[structopt(long = "source-version")]
     pub source_version: bool,
+
+    /// run an offline maintenance command instead of starting the node
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt(rename_all = "kebab-case")]
+pub enum Command {
+    /// maintenance of the blockchain storage
+    Storage(StorageCommand),
+}
+
+#[derive(StructOpt, Debug, Clone, Copy)]
+#[structopt(rename_all = "kebab-case")]
+pub enum StorageCommand {
+    /// walk the blockchain from the HEAD down to the genesis block, checking
+    /// every header and applying every block again on the ledger
+    Verify,
+    /// verify the storage and reset the HEAD to the last consistent block
+    Repair,
 }
 
 impl CommandLine {
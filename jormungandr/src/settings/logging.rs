@@ -1,5 +1,5 @@
 use crate::log::AsyncableDrain;
-use slog::{Drain, FilterLevel, Logger};
+use slog::{Drain, FilterLevel, Never, SendSyncRefUnwindSafeDrain};
 use slog_async::Async;
 #[cfg(feature = "gelf")]
 use slog_gelf::Gelf;
@@ -13,7 +13,7 @@ use std::fmt::{self, Display};
 use std::io;
 use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LogSettings {
     pub level: FilterLevel,
     pub format: LogFormat,
@@ -84,14 +84,16 @@ impl FromStr for LogOutput {
 }
 
 impl LogSettings {
-    pub fn to_logger(&self) -> Result<Logger, Error> {
+    pub fn to_drain(
+        &self,
+    ) -> Result<impl SendSyncRefUnwindSafeDrain<Ok = (), Err = Never>, Error> {
         let filter_level = self.level;
         let drain = self
             .output
             .to_logger(&self.format)?
             .filter(move |record| filter_level.accepts(record.level()))
             .fuse();
-        Ok(slog::Logger::root(drain, o!()))
+        Ok(drain)
     }
 }
 
@@ -1,7 +1,11 @@
 use crate::{
-    network::p2p::topology::{NEW_BLOCKS_TOPIC, NEW_MESSAGES_TOPIC},
+    network::{
+        p2p::topology::{NEW_BLOCKS_TOPIC, NEW_MESSAGES_TOPIC},
+        tls::Fingerprint,
+    },
     settings::logging::{LogFormat, LogOutput},
     settings::LOG_FILTER_LEVEL_POSSIBLE_VALUES,
+    utils::task::SupervisorPolicy,
 };
 use jormungandr_lib::time::Duration;
 use poldercast;
@@ -15,6 +19,11 @@ pub struct Config {
     #[serde(default)]
     pub secret_files: Vec<PathBuf>,
     pub storage: Option<PathBuf>,
+
+    /// how much of the blockchain to keep in the storage
+    #[serde(default)]
+    pub storage_mode: StorageMode,
+
     pub log: Option<ConfigLogSettings>,
 
     /// setting of the mempool, fragment logs and related data
@@ -24,6 +33,14 @@ pub struct Config {
     #[serde(default)]
     pub leadership: Leadership,
 
+    /// size of the message queues between the tasks of the node
+    #[serde(default)]
+    pub intercom: Intercom,
+
+    /// what to do when a service of the node fails
+    #[serde(default)]
+    pub supervisor: Supervisor,
+
     pub rest: Option<Rest>,
 
     #[serde(default)]
@@ -90,6 +107,58 @@ pub struct P2pConfig {
     /// The default is to not allow advertising non-public IP addresses.
     #[serde(default)]
     pub allow_private_addresses: bool,
+
+    /// Relay the fragments submitted through the REST interface along a
+    /// stem of random peers before diffusing them. Disabled if not set.
+    pub dandelion: Option<Dandelion>,
+
+    /// Mutually authenticated TLS on the connections with the other peers.
+    /// Disabled if not set.
+    pub tls: Option<Tls>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Dandelion {
+    /// probability, in percent, for the node to diffuse a fragment received
+    /// from the network instead of relaying it further along the stem.
+    pub fluff_probability: u8,
+    /// time after which a fragment relayed along the stem is diffused by the
+    /// node if it has not been received back from the network.
+    pub stem_timeout: Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Tls {
+    /// path to the PEM certificate of the node, a self-signed certificate
+    /// is generated at the first start if the file does not exist.
+    pub certificate: PathBuf,
+    /// path to the PEM private key of the certificate of the node,
+    /// generated along with the certificate.
+    pub private_key: PathBuf,
+    /// the certificate fingerprints expected from some of the trusted peers
+    #[serde(default)]
+    pub trusted_peers_fingerprints: Vec<TrustedPeerFingerprint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TrustedPeerFingerprint {
+    pub address: poldercast::Address,
+    /// hexadecimal SHA-256 digest of the certificate of the peer
+    pub fingerprint: Fingerprint,
+}
+
+/// the different ways the node can keep the blocks in its storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum StorageMode {
+    /// keep every block ever received (the default)
+    Archive,
+    /// keep the headers of every block but only the bodies of the
+    /// blocks of the last `keep_epochs` epochs.
+    Pruned { keep_epochs: u32 },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
@@ -113,6 +182,42 @@ pub struct Mempool {
     pub log_ttl: Duration,
     /// interval between 2 garbage collection check of the mempool and the log cache.
     pub garbage_collection_interval: Duration,
+    /// save the fragments pending in the mempool in the storage directory when
+    /// the node shuts down, they are loaded back when the node starts.
+    #[serde(default)]
+    pub persistent: bool,
+    /// interval between 2 saves of the fragments pending in the mempool, on top
+    /// of the save done when the node shuts down. Only used if `persistent` is set.
+    #[serde(default)]
+    pub persistence_interval: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Intercom {
+    /// number of fragments waiting to be processed by the mempool before
+    /// the tasks submitting fragments have to wait
+    pub fragment_queue_len: usize,
+    /// number of messages waiting to be propagated by the network task
+    /// before the other tasks have to wait
+    pub network_queue_len: usize,
+    /// number of inputs waiting to be processed by the other tasks (block
+    /// processing, explorer...) before their producers have to wait
+    pub task_queue_len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case", tag = "policy")]
+pub enum Supervisor {
+    /// shut the node down when a service fails
+    Shutdown,
+    /// restart the services processing inputs after a delay, doubled at
+    /// every consecutive failure of the service up to `max_delay`. The
+    /// failure of any other service shuts the node down
+    Restart {
+        initial_delay: Duration,
+        max_delay: Duration,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -139,15 +244,68 @@ impl Default for P2pConfig {
             topics_of_interest: None,
             max_connections: None,
             allow_private_addresses: false,
+            dandelion: None,
+            tls: None,
+        }
+    }
+}
+
+impl Default for Dandelion {
+    fn default() -> Self {
+        Dandelion {
+            fluff_probability: 25,
+            stem_timeout: Duration::new(30, 0),
         }
     }
 }
+impl Default for StorageMode {
+    fn default() -> Self {
+        StorageMode::Archive
+    }
+}
+
 impl Default for Mempool {
     fn default() -> Self {
         Mempool {
             fragment_ttl: Duration::new(30 * 60, 0),
             log_ttl: Duration::new(3600, 0),
             garbage_collection_interval: Duration::new(3600 / 4, 0),
+            persistent: false,
+            persistence_interval: None,
+        }
+    }
+}
+
+impl Default for Intercom {
+    fn default() -> Self {
+        Intercom {
+            fragment_queue_len: 1024,
+            network_queue_len: 32,
+            task_queue_len: crate::utils::task::MESSAGE_QUEUE_LEN,
+        }
+    }
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Supervisor::Restart {
+            initial_delay: Duration::new(1, 0),
+            max_delay: Duration::new(60, 0),
+        }
+    }
+}
+
+impl From<Supervisor> for SupervisorPolicy {
+    fn from(supervisor: Supervisor) -> Self {
+        match supervisor {
+            Supervisor::Shutdown => SupervisorPolicy::Shutdown,
+            Supervisor::Restart {
+                initial_delay,
+                max_delay,
+            } => SupervisorPolicy::Restart {
+                initial_delay: initial_delay.into(),
+                max_delay: max_delay.into(),
+            },
         }
     }
 }
@@ -1,11 +1,11 @@
 mod config;
 pub mod network;
 
-use self::config::{Config, Leadership, Mempool};
-pub use self::config::{Cors, Rest};
+use self::config::{Config, Intercom, Leadership, Mempool, Supervisor};
+pub use self::config::{Cors, Rest, StorageMode};
 use self::network::Protocol;
 use crate::rest::Error as RestError;
-use crate::settings::logging::{self, LogFormat, LogOutput, LogSettings};
+use crate::settings::logging::{LogFormat, LogOutput, LogSettings};
 use crate::settings::{command_arguments::*, Block0Info};
 use slog::{FilterLevel, Logger};
 
@@ -18,20 +18,142 @@ custom_error! {pub Error
    ExpectedBlock0Info = "Cannot start the node without the information to retrieve the genesis block",
    TooMuchBlock0Info = "Use only `--genesis-block-hash' or `--genesis-block'",
    ListenAddressNotValid = "In the node configuration file, the `p2p.listen_address` value is not a valid address. Use format `/ip4/x.x.x.x/tcp/4920",
+   DandelionFluffProbabilityNotValid = "In the node configuration file, the `p2p.dandelion.fluff_probability` value is not a percentage. Use a value between 0 and 100",
+   MempoolPersistenceIntervalNotValid = "In the node configuration file, the `mempool.persistence_interval` value is zero. Use a non-zero interval or remove the entry",
 }
 
 /// Overall Settings for node
+#[derive(Clone)]
 pub struct Settings {
+    pub log: LogSettings,
     pub network: network::Configuration,
     pub storage: Option<PathBuf>,
+    pub storage_mode: StorageMode,
     pub block_0: Block0Info,
     pub secrets: Vec<PathBuf>,
     pub rest: Option<Rest>,
     pub mempool: Mempool,
     pub leadership: Leadership,
+    pub intercom: Intercom,
+    pub supervisor: Supervisor,
     pub explorer: bool,
 }
 
+/// the subset of the settings which can be modified while the node is running
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuntimeSettings {
+    pub log: LogSettings,
+    pub mempool: Mempool,
+    pub leadership: Leadership,
+    pub trusted_peers: Vec<poldercast::Address>,
+    pub max_connections: usize,
+}
+
+impl Settings {
+    pub fn runtime(&self) -> RuntimeSettings {
+        RuntimeSettings {
+            log: self.log.clone(),
+            mempool: self.mempool.clone(),
+            leadership: self.leadership.clone(),
+            trusted_peers: self.network.trusted_peers.clone(),
+            max_connections: self.network.max_connections,
+        }
+    }
+
+    /// list the entries of the node configuration which differ between
+    /// the two settings and which are only taken into account when the
+    /// node starts.
+    pub fn fields_requiring_restart(&self, other: &Settings) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        let mut check = |field, changed| {
+            if changed {
+                fields.push(field)
+            }
+        };
+        check("secret_files", self.secrets != other.secrets);
+        check("storage", self.storage != other.storage);
+        check("storage_mode", self.storage_mode != other.storage_mode);
+        check("rest", self.rest != other.rest);
+        check(
+            "mempool.persistent",
+            self.mempool.persistent != other.mempool.persistent,
+        );
+        check(
+            "mempool.persistence_interval",
+            self.mempool.persistence_interval != other.mempool.persistence_interval,
+        );
+        check(
+            "p2p.public_address",
+            self.network.public_address != other.network.public_address,
+        );
+        check(
+            "p2p.listen_address",
+            self.network.listen_address != other.network.listen_address,
+        );
+        check(
+            "p2p.topics_of_interest",
+            self.network.subscriptions != other.network.subscriptions,
+        );
+        check(
+            "p2p.allow_private_addresses",
+            self.network.allow_private_addresses != other.network.allow_private_addresses,
+        );
+        check(
+            "p2p.dandelion",
+            self.network.dandelion != other.network.dandelion,
+        );
+        check("p2p.tls", self.network.tls != other.network.tls);
+        check("intercom", self.intercom != other.intercom);
+        check("supervisor", self.supervisor != other.supervisor);
+        check("explorer", self.explorer != other.explorer);
+        fields
+    }
+}
+
+impl RuntimeSettings {
+    /// list the entries of the node configuration which differ between
+    /// the two settings
+    pub fn changed_fields(&self, other: &RuntimeSettings) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        let mut check = |field, changed| {
+            if changed {
+                fields.push(field)
+            }
+        };
+        check("log.level", self.log.level != other.log.level);
+        check("log.format", self.log.format != other.log.format);
+        check("log.output", self.log.output != other.log.output);
+        check(
+            "mempool.fragment_ttl",
+            self.mempool.fragment_ttl != other.mempool.fragment_ttl,
+        );
+        check(
+            "mempool.log_ttl",
+            self.mempool.log_ttl != other.mempool.log_ttl,
+        );
+        check(
+            "mempool.garbage_collection_interval",
+            self.mempool.garbage_collection_interval
+                != other.mempool.garbage_collection_interval,
+        );
+        check(
+            "leadership.log_ttl",
+            self.leadership.log_ttl != other.leadership.log_ttl,
+        );
+        check(
+            "leadership.garbage_collection_interval",
+            self.leadership.garbage_collection_interval
+                != other.leadership.garbage_collection_interval,
+        );
+        check("p2p.trusted_peers", self.trusted_peers != other.trusted_peers);
+        check(
+            "p2p.max_connections",
+            self.max_connections != other.max_connections,
+        );
+        fields
+    }
+}
+
 pub struct RawSettings {
     command_line: CommandLine,
     config: Option<Config>,
@@ -50,13 +172,12 @@ impl RawSettings {
         })
     }
 
-    pub fn to_logger(&self) -> Result<Logger, logging::Error> {
+    pub fn log_settings(&self) -> LogSettings {
         LogSettings {
             level: self.logger_level(),
             format: self.logger_format(),
             output: self.logger_output(),
         }
-        .to_logger()
     }
 
     fn logger_level(&self) -> FilterLevel {
@@ -86,6 +207,7 @@ impl RawSettings {
     ///
     /// This function will print&exit if anything is not as it should be.
     pub fn try_into_settings(self, logger: &Logger) -> Result<Settings, Error> {
+        let log = self.log_settings();
         let RawSettings {
             command_line,
             config,
@@ -131,18 +253,35 @@ impl RawSettings {
                     .map_or(false, |settings| settings.enabled)
             });
 
+        let mempool = config
+            .as_ref()
+            .map_or(Mempool::default(), |cfg| cfg.mempool.clone());
+        if let Some(interval) = mempool.persistence_interval {
+            if std::time::Duration::from(interval) == std::time::Duration::from_secs(0) {
+                return Err(Error::MempoolPersistenceIntervalNotValid);
+            }
+        }
+
         Ok(Settings {
+            log,
             storage: storage,
+            storage_mode: config
+                .as_ref()
+                .map_or(StorageMode::default(), |cfg| cfg.storage_mode),
             block_0: block0_info,
             network: network,
             secrets,
             rest: config.as_ref().map_or(None, |cfg| cfg.rest.clone()),
-            mempool: config
-                .as_ref()
-                .map_or(Mempool::default(), |cfg| cfg.mempool.clone()),
+            mempool,
             leadership: config
                 .as_ref()
                 .map_or(Leadership::default(), |cfg| cfg.leadership.clone()),
+            intercom: config
+                .as_ref()
+                .map_or(Intercom::default(), |cfg| cfg.intercom.clone()),
+            supervisor: config
+                .as_ref()
+                .map_or(Supervisor::default(), |cfg| cfg.supervisor.clone()),
             explorer,
         })
     }
@@ -186,6 +325,27 @@ fn generate_network(
             .unwrap_or(network::DEFAULT_MAX_CONNECTIONS),
         timeout: std::time::Duration::from_secs(15),
         allow_private_addresses: p2p.allow_private_addresses,
+        dandelion: match &p2p.dandelion {
+            None => None,
+            Some(dandelion) => {
+                if dandelion.fluff_probability > 100 {
+                    return Err(Error::DandelionFluffProbabilityNotValid);
+                }
+                Some(network::Dandelion {
+                    fluff_probability: dandelion.fluff_probability,
+                    stem_timeout: dandelion.stem_timeout.into(),
+                })
+            }
+        },
+        tls: p2p.tls.as_ref().map(|tls| network::Tls {
+            certificate: tls.certificate.clone(),
+            private_key: tls.private_key.clone(),
+            trusted_peers_fingerprints: tls
+                .trusted_peers_fingerprints
+                .iter()
+                .map(|peer| (peer.address.clone(), peer.fingerprint))
+                .collect(),
+        }),
     };
 
     Ok(network)
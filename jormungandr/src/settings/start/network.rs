@@ -1,5 +1,6 @@
-use std::{collections::BTreeMap, net::SocketAddr, str, time::Duration};
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, str, time::Duration};
 
+use crate::network::tls::Fingerprint;
 use crate::settings::start::config::{Address, InterestLevel, Topic};
 
 /// Protocol to use for a connection.
@@ -70,6 +71,37 @@ pub struct Configuration {
 
     /// Whether to allow non-public IP addresses in gossip
     pub allow_private_addresses: bool,
+
+    /// private relay of the fragments submitted to the node, if enabled
+    pub dandelion: Option<Dandelion>,
+
+    /// TLS of the connections with the other peers, if enabled
+    pub tls: Option<Tls>,
+}
+
+/// The settings of the private relay of the fragments (Dandelion)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dandelion {
+    /// probability, in percent, to diffuse a fragment received from the
+    /// network instead of relaying it further along the stem.
+    pub fluff_probability: u8,
+
+    /// time after which a fragment relayed along the stem is diffused
+    /// if it has not been received back from the network.
+    pub stem_timeout: Duration,
+}
+
+/// The settings of the TLS of the connections between the nodes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tls {
+    /// path to the certificate of the node, generated if it does not exist
+    pub certificate: PathBuf,
+
+    /// path to the private key of the certificate of the node
+    pub private_key: PathBuf,
+
+    /// the fingerprints pinned for the trusted peers
+    pub trusted_peers_fingerprints: Vec<(poldercast::Address, Fingerprint)>,
 }
 
 impl Peer {
@@ -0,0 +1,378 @@
+//! coordinated shutdown of the node
+//!
+//! The shutdown is requested when the node receives `SIGTERM` or `SIGINT`
+//! (`Ctrl-C`), through the REST API, or when a service fails and the
+//! supervisor policy does not restart it. The node then, in order:
+//!
+//! 1. stops the REST server and the P2P network, so no new fragment or block
+//!    is received;
+//! 2. waits for the leadership task to finish the block it is creating and
+//!    for the block task to process the messages waiting in its queue: a
+//!    marker message is sent through the queue, the block task replies to it
+//!    once the messages sent before it have been processed;
+//! 3. writes the tip of the blockchain in the storage (`MAIN_BRANCH_TAG`)
+//!    and the blocks rejected since the last save of the rejected blocks;
+//! 4. saves the fragments pending in the mempool, if `mempool.persistent`
+//!    is set.
+//!
+//! Every step gives up after `DRAIN_TIMEOUT`. A failed step is logged and the
+//! shutdown carries on with the next steps.
+//! The outcome of every step is reported in the logs before the node exits.
+
+use crate::{
+    blockchain::{Blockchain, RejectedHeaders, Tip, MAIN_BRANCH_TAG},
+    fragment::{self, persistence::Error as PersistenceError},
+    intercom::{self, BlockMsg, NetworkMsg},
+    rest::Server,
+    utils::{async_msg::MessageBox, task::TokioServiceInfo},
+};
+use chain_storage::error::Error as StorageError;
+use slog::Logger;
+use std::{
+    convert::Infallible,
+    fmt, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::{prelude::*, runtime::current_thread};
+
+/// how long the shutdown waits for the leadership task and the block task
+/// to finish their work, for room in the queue of the network task, or for
+/// the tip, the rejected blocks and the mempool to be saved, before carrying
+/// on
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+custom_error! {pub Error
+    Network = "cannot send the shutdown request to the network task",
+    NetworkQueue = "gave up waiting for room in the network task queue to send the shutdown request",
+    Leadership { blocks: usize } = "gave up waiting for {blocks} block(s) being created",
+    BlockTask = "the block task stopped before processing the block messages pending in its queue",
+    BlockQueue = "gave up waiting for the block task to process the block messages pending in its queue",
+    Storage { source: StorageError } = "cannot write the tip of the blockchain in the storage",
+    StorageTimeout = "gave up waiting for the tip of the blockchain to be written in the storage",
+    RejectedHeaders { source: io::Error } = "cannot save the rejected blocks",
+    RejectedHeadersTimeout = "gave up waiting for the rejected blocks to be saved",
+    Mempool { source: PersistenceError } = "cannot save the fragments pending in the mempool",
+    MempoolTimeout = "gave up waiting for the fragments pending in the mempool to be saved",
+}
+
+/// why the node is shut down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// the node received the given signal
+    Signal(&'static str),
+    /// the shutdown has been requested through the REST API
+    Rest,
+    /// the given service failed and the supervisor policy does not
+    /// restart it
+    ServiceFailure(&'static str),
+}
+
+/// handle to request the shutdown of the node
+#[derive(Clone, Default)]
+pub struct Shutdown(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    reason: Mutex<Option<Reason>>,
+    requested: Condvar,
+    blocks_in_progress: AtomicUsize,
+}
+
+/// held by the leadership task while it creates a block: the shutdown
+/// waits for the block to be handed over to the block task
+pub struct BlockInProgress(Shutdown);
+
+/// the parts of the node stopped or saved by the shutdown
+pub struct Handles {
+    pub rest_server: Option<Server>,
+    pub network_msgbox: MessageBox<NetworkMsg>,
+    pub block_msgbox: MessageBox<BlockMsg>,
+    pub blockchain: Blockchain,
+    pub blockchain_tip: Tip,
+    pub rejected_headers: RejectedHeaders,
+    pub fragment_pool: fragment::Pool,
+    /// the file to save the pending fragments in, `None` if the mempool
+    /// is not persistent
+    pub mempool_file: Option<PathBuf>,
+}
+
+/// outcome of the shutdown of the node
+#[derive(Debug)]
+pub struct Report {
+    pub reason: Reason,
+    pub steps: Vec<StepReport>,
+}
+
+/// outcome of a step of the shutdown
+#[derive(Debug)]
+pub struct StepReport {
+    pub step: &'static str,
+    pub duration: Duration,
+    pub result: Result<(), Error>,
+}
+
+impl Shutdown {
+    /// request the shutdown of the node, returns `false` if the shutdown
+    /// has already been requested
+    pub fn request(&self, reason: Reason) -> bool {
+        let mut current = self.0.reason.lock().unwrap();
+        if current.is_some() {
+            return false;
+        }
+        *current = Some(reason);
+        self.0.requested.notify_all();
+        true
+    }
+
+    /// block the current thread until the shutdown is requested
+    pub fn wait(&self) -> Reason {
+        let mut reason = self.0.reason.lock().unwrap();
+        loop {
+            if let Some(reason) = *reason {
+                return reason;
+            }
+            reason = self.0.requested.wait(reason).unwrap();
+        }
+    }
+
+    /// register the creation of a block, returns `None` if the shutdown has
+    /// been requested: no new block is to be created
+    pub fn start_block(&self) -> Option<BlockInProgress> {
+        // the shutdown is checked under the lock so it does not miss a
+        // block started while it is being requested
+        let reason = self.0.reason.lock().unwrap();
+        if reason.is_some() {
+            return None;
+        }
+        self.0.blocks_in_progress.fetch_add(1, Ordering::SeqCst);
+        Some(BlockInProgress(self.clone()))
+    }
+
+    fn blocks_in_progress(&self) -> usize {
+        self.0.blocks_in_progress.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for BlockInProgress {
+    fn drop(&mut self) {
+        (self.0).0.blocks_in_progress.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Report {
+    /// tells if every step of the shutdown succeeded
+    pub fn is_success(&self) -> bool {
+        self.steps.iter().all(|step| step.result.is_ok())
+    }
+
+    pub fn failed_steps(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| step.result.is_err())
+            .count()
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::Signal(signal) => write!(f, "{} received", signal),
+            Reason::Rest => write!(f, "requested through the REST API"),
+            Reason::ServiceFailure(service) => write!(f, "the service {} failed", service),
+        }
+    }
+}
+
+/// run the steps of the shutdown, the report is logged
+pub fn run(shutdown: &Shutdown, reason: Reason, handles: Handles, logger: &Logger) -> Report {
+    info!(logger, "shutting down the node"; "reason" => %reason);
+    let started = Instant::now();
+    let mut steps = Vec::new();
+    let mut step = |name, f: &mut dyn FnMut() -> Result<(), Error>| {
+        let start = Instant::now();
+        let result = f();
+        let duration = start.elapsed();
+        match &result {
+            Ok(()) => {
+                info!(logger, "shutdown step completed"; "step" => name, "duration" => ?duration)
+            }
+            Err(error) => {
+                error!(logger, "shutdown step failed"; "step" => name, "reason" => %error)
+            }
+        }
+        steps.push(StepReport {
+            step: name,
+            duration,
+            result,
+        });
+    };
+
+    step("stop_input", &mut || stop_input(&handles));
+    step("leadership", &mut || wait_leadership(shutdown));
+    step("block_queue", &mut || wait_block_queue(&handles, logger));
+    step("storage_tip", &mut || flush_tip(&handles));
+    step("rejected_headers", &mut || flush_rejected_headers(&handles));
+    if let Some(path) = &handles.mempool_file {
+        step("mempool", &mut || {
+            save_mempool(&handles, path.clone(), logger)
+        });
+    }
+
+    let report = Report { reason, steps };
+    if report.is_success() {
+        info!(logger, "node shut down"; "duration" => ?started.elapsed());
+    } else {
+        error!(
+            logger,
+            "node shut down with errors";
+            "duration" => ?started.elapsed(),
+            "failed_steps" => report.failed_steps(),
+        );
+    }
+    report
+}
+
+fn stop_input(handles: &Handles) -> Result<(), Error> {
+    if let Some(server) = &handles.rest_server {
+        server.stop();
+        server.wait_for_stop();
+    }
+    let sent = handles
+        .network_msgbox
+        .clone()
+        .send(NetworkMsg::Shutdown)
+        .map_err(|_| Error::Network);
+    with_timeout(sent, Error::NetworkQueue).map(|_| ())
+}
+
+fn wait_leadership(shutdown: &Shutdown) -> Result<(), Error> {
+    if wait_until(|| shutdown.blocks_in_progress() == 0) {
+        Ok(())
+    } else {
+        Err(Error::Leadership {
+            blocks: shutdown.blocks_in_progress(),
+        })
+    }
+}
+
+fn wait_block_queue(handles: &Handles, logger: &Logger) -> Result<(), Error> {
+    let (reply, processed) = intercom::unary_reply(logger.clone());
+    let flushed = handles
+        .block_msgbox
+        .clone()
+        .send(BlockMsg::Flush(reply))
+        .map_err(|_| Error::BlockTask)
+        .and_then(|_| processed.map_err(|_: intercom::Error| Error::BlockTask));
+    with_timeout(flushed, Error::BlockQueue)
+}
+
+/// run the future to completion, fails with `timed_out` if it has not
+/// completed after `DRAIN_TIMEOUT`
+fn with_timeout<F>(future: F, timed_out: Error) -> Result<F::Item, Error>
+where
+    F: Future<Error = Error>,
+{
+    current_thread::block_on_all(future.timeout(DRAIN_TIMEOUT))
+        .map_err(|error| error.into_inner().unwrap_or(timed_out))
+}
+
+/// returns `false` if the condition is still not met after `DRAIN_TIMEOUT`
+fn wait_until(condition: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + DRAIN_TIMEOUT;
+    while !condition() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(DRAIN_POLL_INTERVAL);
+    }
+    true
+}
+
+fn flush_tip(handles: &Handles) -> Result<(), Error> {
+    let mut storage = handles.blockchain.storage().clone();
+    let flushed = handles
+        .blockchain_tip
+        .get_ref()
+        .map_err(|_: Infallible| unreachable!())
+        .and_then(move |tip| storage.put_tag(MAIN_BRANCH_TAG.to_owned(), tip.hash()))
+        .map_err(|source| Error::Storage { source });
+    with_timeout(flushed, Error::StorageTimeout)
+}
+
+fn flush_rejected_headers(handles: &Handles) -> Result<(), Error> {
+    let flushed = handles
+        .rejected_headers
+        .flush()
+        .map_err(|source| Error::RejectedHeaders { source });
+    with_timeout(flushed, Error::RejectedHeadersTimeout)
+}
+
+fn save_mempool(handles: &Handles, path: PathBuf, logger: &Logger) -> Result<(), Error> {
+    let saved = handles
+        .fragment_pool
+        .save(path.clone())
+        .map_err(|source| Error::Mempool { source });
+    let saved = with_timeout(saved, Error::MempoolTimeout)?;
+    info!(
+        logger,
+        "pending fragments saved";
+        "fragments" => saved,
+        "path" => path.display().to_string(),
+    );
+    Ok(())
+}
+
+/// request the shutdown of the node when it receives `SIGTERM` or `SIGINT`
+#[cfg(unix)]
+pub fn shutdown_on_signal(
+    service_info: TokioServiceInfo,
+    shutdown: Shutdown,
+) -> impl Future<Item = (), Error = ()> {
+    use tokio_signal::unix::{Signal, SIGINT, SIGTERM};
+
+    let sigterm = Signal::new(SIGTERM).flatten_stream().map(|_| "SIGTERM");
+    let sigint = Signal::new(SIGINT).flatten_stream().map(|_| "SIGINT");
+    request_on_signal(service_info, shutdown, sigterm.select(sigint))
+}
+
+/// request the shutdown of the node on `Ctrl-C`
+#[cfg(not(unix))]
+pub fn shutdown_on_signal(
+    service_info: TokioServiceInfo,
+    shutdown: Shutdown,
+) -> impl Future<Item = (), Error = ()> {
+    let ctrl_c = tokio_signal::ctrl_c().flatten_stream().map(|()| "Ctrl-C");
+    request_on_signal(service_info, shutdown, ctrl_c)
+}
+
+fn request_on_signal<S>(
+    service_info: TokioServiceInfo,
+    shutdown: Shutdown,
+    signals: S,
+) -> impl Future<Item = (), Error = ()>
+where
+    S: Stream<Item = &'static str, Error = std::io::Error>,
+{
+    let logger = service_info.logger().clone();
+    let error_logger = logger.clone();
+    signals
+        .map_err(move |error| {
+            error!(error_logger, "cannot listen to the shutdown signals" ; "reason" => error.to_string());
+        })
+        .for_each(move |signal| {
+            if shutdown.request(Reason::Signal(signal)) {
+                info!(logger, "{} received, shutting down the node", signal);
+            } else {
+                warn!(logger, "{} received, the node is already shutting down", signal);
+            }
+            Ok(())
+        })
+}
@@ -7,7 +7,9 @@ use std::io;
 
 custom_error! {pub ErrorKind
    SQLite = "SQLite file",
-   Block0 = "Block0"
+   BlockBodies = "block bodies directory",
+   Block0 = "Block0",
+   RejectedHeaders = "rejected blocks file"
 }
 
 custom_error! {pub Error
@@ -23,6 +25,10 @@ custom_error! {pub Error
     NodeSecrets { source: secure::NodeSecretFromFileError} = "Error while loading the node's secrets.",
     Block0InFuture = "Block 0 is set to start in the future",
     ExplorerBootstrapError { source: explorer::error::Error } = "Error while loading the explorer from storage",
+    StorageIntegrity { issues: usize } = "Found {issues} issue(s) in the blockchain storage",
+    NetworkTls { source: network::tls::Error } = "Error while loading the TLS certificate of the node",
+    Shutdown { failed_steps: usize } = "The node did not shut down cleanly, {failed_steps} step(s) of the shutdown failed",
+    ServiceFailure { service: &'static str } = "The node has been shut down after the failure of the service {service}",
 }
 
 impl Error {
@@ -41,6 +47,10 @@ impl Error {
             Error::FetchBlock0 { .. } => 9,
             Error::NetworkBootstrapError { .. } => 10,
             Error::ExplorerBootstrapError { .. } => 11,
+            Error::StorageIntegrity { .. } => 12,
+            Error::NetworkTls { .. } => 13,
+            Error::Shutdown { .. } => 14,
+            Error::ServiceFailure { .. } => 15,
         }
     }
 }
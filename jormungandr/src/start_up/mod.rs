@@ -2,19 +2,39 @@ mod error;
 
 pub use self::error::{Error, ErrorKind};
 use crate::{
-    blockcfg::{Block, Leadership},
-    blockchain::{Blockchain, Branch, ErrorKind as BlockchainError, Tip},
+    blockcfg::{Block, Header, HeaderHash, Leadership},
+    blockchain::{
+        integrity, Blockchain, Branch, ErrorKind as BlockchainError, PrunedBlockStore,
+        RejectedHeaders, Tip, PRUNED_TAG, REJECTED_HEADERS_CAPACITY, REJECTED_HEADERS_FILE,
+    },
+    fragment::persistence::MEMPOOL_FILE,
     leadership::NewEpochToSchedule,
-    network,
-    settings::start::Settings,
+    network::{self, tls::TlsContext},
+    settings::{
+        start::{Settings, StorageMode},
+        StorageCommand,
+    },
 };
-use chain_storage::{memory::MemoryBlockStore, store::BlockStore};
+use chain_storage::{error::Error as StorageError, memory::MemoryBlockStore, store::BlockStore};
 use chain_storage_sqlite::SQLiteBlockStore;
 use slog::Logger;
-use std::{sync::Arc, time::Duration};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::mpsc;
 
-pub type NodeStorage = Box<dyn BlockStore<Block = Block> + Send + Sync>;
+pub type NodeStorage = Box<dyn NodeBlockStore + Send + Sync>;
+
+/// the block stores of the node, the headers of the blocks stay available
+/// when their body has been pruned
+pub trait NodeBlockStore: BlockStore<Block = Block> {
+    /// get the header of a block, even if its body has been pruned
+    fn get_header(&self, block_hash: &HeaderHash) -> Result<Header, StorageError> {
+        self.get_block(block_hash).map(|(block, _)| block.header)
+    }
+}
+
+impl NodeBlockStore for MemoryBlockStore<Block> {}
+
+impl NodeBlockStore for SQLiteBlockStore<Block> {}
 
 /// prepare the block storage from the given settings
 ///
@@ -22,6 +42,12 @@ pub fn prepare_storage(setting: &Settings, logger: &Logger) -> Result<NodeStorag
     match &setting.storage {
         None => {
             info!(logger, "storing blockchain in memory");
+            if let StorageMode::Pruned { .. } = setting.storage_mode {
+                warn!(
+                    logger,
+                    "storage mode `pruned' is ignored when storing the blockchain in memory"
+                );
+            }
             Ok(Box::new(MemoryBlockStore::new()))
         }
         Some(dir) => {
@@ -32,7 +58,48 @@ pub fn prepare_storage(setting: &Settings, logger: &Logger) -> Result<NodeStorag
             let mut sqlite = dir.clone();
             sqlite.push("blocks.sqlite");
             info!(logger, "storing blockchain in '{:?}'", sqlite);
-            Ok(Box::new(SQLiteBlockStore::new(sqlite)))
+            let sqlite = SQLiteBlockStore::new(sqlite);
+
+            match setting.storage_mode {
+                StorageMode::Archive => Ok(Box::new(sqlite)),
+                StorageMode::Pruned { keep_epochs } => {
+                    let mut bodies = dir.clone();
+                    bodies.push("bodies");
+                    info!(
+                        logger,
+                        "keeping the block bodies of the last {} epochs in '{:?}'",
+                        keep_epochs,
+                        bodies
+                    );
+                    let pruned = PrunedBlockStore::new(sqlite, bodies, keep_epochs).map_err(
+                        |err| Error::IO {
+                            source: err,
+                            reason: ErrorKind::BlockBodies,
+                        },
+                    )?;
+                    Ok(Box::new(pruned))
+                }
+            }
+        }
+    }
+}
+
+/// load the TLS certificate of the node, generating it at the first start,
+/// if TLS is enabled on the connections with the other peers.
+pub fn prepare_network_tls(
+    settings: &Settings,
+    logger: &Logger,
+) -> Result<Option<TlsContext>, Error> {
+    match &settings.network.tls {
+        None => Ok(None),
+        Some(config) => {
+            let tls = TlsContext::new(config)?;
+            info!(
+                logger,
+                "TLS enabled on the P2P connections";
+                "fingerprint" => tls.fingerprint().to_string(),
+            );
+            Ok(Some(tls))
         }
     }
 }
@@ -47,6 +114,7 @@ pub fn prepare_storage(setting: &Settings, logger: &Logger) -> Result<NodeStorag
 pub fn prepare_block_0(
     settings: &Settings,
     storage: &NodeStorage,
+    tls: Option<&TlsContext>,
     logger: &Logger,
 ) -> Result<Block, Error> {
     use crate::settings::Block0Info;
@@ -77,7 +145,8 @@ pub fn prepare_block_0(
                     logger,
                     "retrieving block0 from network with hash {}", block0_id
                 );
-                network::fetch_block(&settings.network, &block0_id, logger).map_err(|e| e.into())
+                network::fetch_block(&settings.network, tls, &block0_id, logger)
+                    .map_err(|e| e.into())
             }
         }
     }
@@ -95,7 +164,20 @@ pub fn load_blockchain(
 
     let main_branch: Branch = match blockchain.load_from_block0(block0.clone()).wait() {
         Err(error) => match error.kind() {
-            BlockchainError::Block0AlreadyInStorage => blockchain.load_from_storage(block0).wait(),
+            BlockchainError::Block0AlreadyInStorage => {
+                let pruned = blockchain
+                    .storage()
+                    .get_tag(PRUNED_TAG.to_owned())
+                    .wait()?
+                    .is_some();
+                if pruned {
+                    // the bodies of the old blocks are no longer in the storage,
+                    // the blockchain will be recovered from the network
+                    blockchain.reload_from_block0(block0).wait()
+                } else {
+                    blockchain.load_from_storage(block0).wait()
+                }
+            }
             _ => Err(error),
         },
         Ok(branch) => Ok(branch),
@@ -132,3 +214,80 @@ pub fn load_blockchain(
 
     Ok((blockchain, Tip::new(main_branch)))
 }
+
+/// load the cache of the rejected blocks
+///
+/// the cache is persisted in the storage directory, if any, so the
+/// blocks rejected before a restart are not processed again.
+pub fn load_rejected_headers(
+    settings: &Settings,
+    logger: &Logger,
+) -> Result<RejectedHeaders, Error> {
+    let path = settings
+        .storage
+        .as_ref()
+        .map(|dir| dir.join(REJECTED_HEADERS_FILE));
+    if let Some(path) = &path {
+        debug!(logger, "loading the rejected blocks"; "path" => path.display().to_string());
+    }
+    RejectedHeaders::load(REJECTED_HEADERS_CAPACITY, path).map_err(|source| Error::IO {
+        source,
+        reason: ErrorKind::RejectedHeaders,
+    })
+}
+
+/// the file the fragments pending in the mempool are saved in when the node
+/// shuts down, `None` if the mempool is not persistent
+pub fn mempool_file(settings: &Settings, logger: &Logger) -> Option<PathBuf> {
+    if !settings.mempool.persistent {
+        return None;
+    }
+    match &settings.storage {
+        Some(dir) => Some(dir.join(MEMPOOL_FILE)),
+        None => {
+            warn!(
+                logger,
+                "the mempool is persistent but the node has no storage directory, \
+                 the pending fragments will not be saved nor restored"
+            );
+            None
+        }
+    }
+}
+
+/// run the offline maintenance command on the blockchain storage
+///
+/// the storage is verified from the `HEAD` down to the block0 and the
+/// report is printed on the standard output. On `repair` the `HEAD` of
+/// the storage is reset to the last consistent block.
+pub fn run_storage_command(
+    command: StorageCommand,
+    block0: Block,
+    storage: NodeStorage,
+    block_cache_ttl: Duration,
+    logger: &Logger,
+) -> Result<(), Error> {
+    let mut blockchain = Blockchain::new(storage, block_cache_ttl);
+
+    info!(logger, "verifying the blockchain storage");
+    let report = integrity::verify(&mut blockchain, block0)?;
+    println!("{}", report);
+
+    if report.is_consistent() {
+        return Ok(());
+    }
+
+    match command {
+        StorageCommand::Verify => Err(Error::StorageIntegrity {
+            issues: report.issues.len(),
+        }),
+        StorageCommand::Repair => {
+            integrity::repair(&blockchain, &report)?;
+            info!(
+                logger,
+                "storage repaired, HEAD reset to block {}", report.last_consistent
+            );
+            Ok(())
+        }
+    }
+}
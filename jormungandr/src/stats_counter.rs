@@ -14,6 +14,8 @@ pub struct StatsCounter {
 struct StatsCounterImpl {
     tx_recv_cnt: AtomicUsize,
     block_recv_cnt: AtomicUsize,
+    block_rejected_cnt: AtomicUsize,
+    block_rejected_replay_cnt: AtomicUsize,
     start_time: Instant,
     slot_start_time: AtomicU64,
 }
@@ -23,6 +25,8 @@ impl Default for StatsCounterImpl {
         Self {
             tx_recv_cnt: AtomicUsize::default(),
             block_recv_cnt: AtomicUsize::default(),
+            block_rejected_cnt: AtomicUsize::default(),
+            block_rejected_replay_cnt: AtomicUsize::default(),
             start_time: Instant::now(),
             slot_start_time: AtomicU64::new(SLOT_START_TIME_UNDEFINED),
         }
@@ -49,6 +53,29 @@ impl StatsCounter {
         self.stats.block_recv_cnt.load(Ordering::Relaxed) as u64
     }
 
+    /// count the blocks rejected by the validation
+    pub fn add_block_rejected_cnt(&self, count: usize) {
+        self.stats
+            .block_rejected_cnt
+            .fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn block_rejected_cnt(&self) -> u64 {
+        self.stats.block_rejected_cnt.load(Ordering::Relaxed) as u64
+    }
+
+    /// count the blocks received again after they, or their parent,
+    /// have been rejected
+    pub fn add_block_rejected_replay_cnt(&self, count: usize) {
+        self.stats
+            .block_rejected_replay_cnt
+            .fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn block_rejected_replay_cnt(&self) -> u64 {
+        self.stats.block_rejected_replay_cnt.load(Ordering::Relaxed) as u64
+    }
+
     pub fn uptime_sec(&self) -> u64 {
         self.stats.start_time.elapsed().as_secs()
     }
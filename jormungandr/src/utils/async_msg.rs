@@ -3,21 +3,132 @@
 
 use futures::prelude::*;
 use futures::sync::mpsc::{self, Receiver, SendError, Sender, TrySendError};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 /// The output end of an in-memory FIFO channel.
-pub struct MessageBox<Msg>(Sender<Msg>);
+pub struct MessageBox<Msg> {
+    sender: Sender<Msg>,
+    metrics: QueueMetrics,
+    /// when the last message sent through this end started waiting for
+    /// room in the channel
+    blocked_since: Option<Instant>,
+}
 
 /// The input end of an in-memory FIFO channel.
 /// This can be read asynchronously in a Tokio task using its
 /// Stream implementation.
-pub struct MessageQueue<Msg>(Receiver<Msg>);
+pub struct MessageQueue<Msg>(Receiver<Msg>, QueueMetrics);
+
+/// The metrics of a channel, shared by the ends of the channel.
+#[derive(Clone, Default)]
+pub struct QueueMetrics(Arc<Counters>);
+
+/// The depth of the channel is not counted by itself: the consumer may take
+/// a message out of the channel before the producer records it as sent.
+/// It is computed from the messages sent and received instead.
+#[derive(Default)]
+struct Counters {
+    capacity: Option<usize>,
+    sent: AtomicU64,
+    received: AtomicU64,
+    dropped: AtomicU64,
+    blocked_sends: AtomicU64,
+    send_wait_micros: AtomicU64,
+}
+
+/// A snapshot of the metrics of a channel
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStats {
+    /// the number of messages pending in the channel
+    pub depth: usize,
+    /// the number of messages the channel can hold before the producers
+    /// have to wait, `None` for the unbounded channels
+    pub capacity: Option<usize>,
+    /// the number of messages sent through the channel
+    pub sent: u64,
+    /// the number of messages which could not be sent: the channel was full
+    /// or the receiving end has been dropped
+    pub dropped: u64,
+    /// the number of messages which had to wait for room in the channel,
+    /// or which could not be sent with `MessageBox::try_send` because the
+    /// channel was full (they are counted as dropped as well)
+    pub blocked_sends: u64,
+    /// the total time spent by the producers waiting for room in the
+    /// channel, in milliseconds
+    pub send_wait_ms: u64,
+}
 
 /// Constructs an in-memory channel and returns the output and input halves.
 /// The parameter specifies the number of messages that are allowed
 /// to be pending in the channel.
 pub fn channel<Msg>(buffer: usize) -> (MessageBox<Msg>, MessageQueue<Msg>) {
     let (tx, rx) = mpsc::channel(buffer);
-    (MessageBox(tx), MessageQueue(rx))
+    let metrics = QueueMetrics::bounded(buffer);
+    let msg_box = MessageBox {
+        sender: tx,
+        metrics: metrics.clone(),
+        blocked_since: None,
+    };
+    (msg_box, MessageQueue(rx, metrics))
+}
+
+impl QueueMetrics {
+    /// the metrics of a channel holding at most `capacity` messages
+    pub fn bounded(capacity: usize) -> Self {
+        QueueMetrics(Arc::new(Counters {
+            capacity: Some(capacity),
+            ..Counters::default()
+        }))
+    }
+
+    /// the number of messages pending in the channel
+    pub fn depth(&self) -> usize {
+        let received = self.0.received.load(Ordering::Relaxed);
+        let sent = self.0.sent.load(Ordering::Relaxed);
+        sent.saturating_sub(received) as usize
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        QueueStats {
+            depth: self.depth(),
+            capacity: self.0.capacity,
+            sent: self.0.sent.load(Ordering::Relaxed),
+            dropped: self.0.dropped.load(Ordering::Relaxed),
+            blocked_sends: self.0.blocked_sends.load(Ordering::Relaxed),
+            send_wait_ms: self.0.send_wait_micros.load(Ordering::Relaxed) / 1000,
+        }
+    }
+
+    /// a message has been added to the channel
+    pub fn sent(&self) {
+        self.0.sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// a message has been taken out of the channel
+    pub fn received(&self) {
+        self.0.received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// a message could not be added to the channel
+    pub fn dropped(&self) {
+        self.0.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn blocked(&self) {
+        self.0.blocked_sends.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn waited(&self, duration: Duration) {
+        let micros = duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros());
+        self.0.send_wait_micros.fetch_add(micros, Ordering::Relaxed);
+    }
 }
 
 impl<Msg> MessageBox<Msg> {
@@ -29,9 +140,27 @@ impl<Msg> MessageBox<Msg> {
     /// # Errors
     ///
     /// If the channel is full or the receiving MessageQueue has been dropped,
-    /// an error is returned in `Err`.
+    /// an error is returned in `Err`. The message is counted as dropped, and
+    /// as a blocked send if the channel is full.
     pub fn try_send(&mut self, a: Msg) -> Result<(), TrySendError<Msg>> {
-        self.0.try_send(a)
+        match self.sender.try_send(a) {
+            Ok(()) => {
+                self.metrics.sent();
+                Ok(())
+            }
+            Err(err) => {
+                if err.is_full() {
+                    self.metrics.blocked();
+                }
+                self.metrics.dropped();
+                Err(err)
+            }
+        }
+    }
+
+    /// the metrics of the channel
+    pub fn metrics(&self) -> QueueMetrics {
+        self.metrics.clone()
     }
 }
 
@@ -40,15 +169,29 @@ impl<Msg> Sink for MessageBox<Msg> {
     type SinkError = SendError<Msg>;
 
     fn start_send(&mut self, msg: Msg) -> StartSend<Msg, SendError<Msg>> {
-        self.0.start_send(msg)
+        let res = self.sender.start_send(msg).map_err(|err| {
+            self.blocked_since = None;
+            self.metrics.dropped();
+            err
+        })?;
+        if res.is_ready() {
+            if let Some(since) = self.blocked_since.take() {
+                self.metrics.waited(since.elapsed());
+            }
+            self.metrics.sent();
+        } else if self.blocked_since.is_none() {
+            self.blocked_since = Some(Instant::now());
+            self.metrics.blocked();
+        }
+        Ok(res)
     }
 
     fn poll_complete(&mut self) -> Poll<(), SendError<Msg>> {
-        self.0.poll_complete()
+        self.sender.poll_complete()
     }
 
     fn close(&mut self) -> Poll<(), SendError<Msg>> {
-        self.0.close()
+        self.sender.close()
     }
 }
 
@@ -56,12 +199,53 @@ impl<Msg> Stream for MessageQueue<Msg> {
     type Item = Msg;
     type Error = ();
     fn poll(&mut self) -> Poll<Option<Msg>, ()> {
-        self.0.poll()
+        let res = self.0.poll()?;
+        if let Async::Ready(Some(_)) = &res {
+            self.1.received();
+        }
+        Ok(res)
     }
 }
 
 impl<Msg> Clone for MessageBox<Msg> {
     fn clone(&self) -> Self {
-        MessageBox(self.0.clone())
+        MessageBox {
+            sender: self.sender.clone(),
+            metrics: self.metrics.clone(),
+            blocked_since: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_does_not_underflow_when_received_before_sent() {
+        let metrics = QueueMetrics::bounded(1);
+        metrics.received();
+        assert_eq!(metrics.depth(), 0);
+        metrics.sent();
+        assert_eq!(metrics.depth(), 0);
+        metrics.sent();
+        assert_eq!(metrics.depth(), 1);
+    }
+
+    #[test]
+    fn try_send_on_a_full_channel_is_a_dropped_blocked_send() {
+        let (mut msg_box, mut msg_queue) = channel(0);
+        msg_box.try_send(()).unwrap();
+        assert!(msg_box.try_send(()).unwrap_err().is_full());
+
+        let stats = msg_box.metrics().stats();
+        assert_eq!(stats.depth, 1);
+        assert_eq!(stats.sent, 1);
+        assert_eq!(stats.dropped, 1);
+        assert_eq!(stats.blocked_sends, 1);
+
+        let received = future::poll_fn(|| msg_queue.poll()).wait().unwrap();
+        assert_eq!(received, Some(()));
+        assert_eq!(msg_box.metrics().depth(), 0);
     }
 }
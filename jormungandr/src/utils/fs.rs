@@ -0,0 +1,9 @@
+use std::{fs, io, path::Path};
+
+/// write in a temporary file first so an interrupted write does not
+/// leave a truncated file behind
+pub fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(tmp, path)
+}
@@ -1,4 +1,6 @@
 pub mod async_msg;
 pub mod borrow;
 pub mod drop_watchdog;
+pub mod fs;
+pub mod reloadable;
 pub mod task;
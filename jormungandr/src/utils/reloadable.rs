@@ -0,0 +1,59 @@
+//! values shared between the tasks of the node which may be updated
+//! while the node is running (see the reloading of the node configuration)
+
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::{
+    prelude::{Async, Future, Poll, Stream},
+    timer::{self, Delay},
+};
+
+#[derive(Clone)]
+pub struct Reloadable<T>(Arc<RwLock<T>>);
+
+/// stream yielding every time the period elapses, the period being
+/// read again from the [`Reloadable`] value after every tick.
+///
+/// [`Reloadable`]: ./struct.Reloadable.html
+pub struct ReloadableInterval {
+    period: Reloadable<Duration>,
+    delay: Delay,
+}
+
+impl<T: Clone> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Reloadable(Arc::new(RwLock::new(value)))
+    }
+
+    pub fn get(&self) -> T {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = value;
+    }
+}
+
+impl ReloadableInterval {
+    /// create a new interval, the first tick happens after `period`.
+    ///
+    /// A new period is taken into account from the next tick onward.
+    pub fn new(period: Reloadable<Duration>) -> Self {
+        let delay = Delay::new(Instant::now() + period.get());
+        ReloadableInterval { period, delay }
+    }
+}
+
+impl Stream for ReloadableInterval {
+    type Item = Instant;
+    type Error = timer::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        try_ready!(self.delay.poll());
+        let now = Instant::now();
+        self.delay.reset(now + self.period.get());
+        Ok(Async::Ready(Some(now)))
+    }
+}
@@ -5,23 +5,130 @@
 //! modules utilized in jormungandr.
 //!
 
-use crate::utils::async_msg::{self, MessageBox};
+use crate::{
+    shutdown::{Reason as ShutdownReason, Shutdown},
+    utils::async_msg::{self, MessageBox, QueueMetrics, QueueStats},
+};
+use jormungandr_lib::time::SystemTime;
 use slog::Logger;
 use std::{
-    sync::mpsc::{self, Sender},
+    any::Any,
+    collections::BTreeMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
     thread,
     time::{Duration, Instant},
 };
 use tokio::prelude::*;
 use tokio::runtime;
 
-// Limit on the length of a task message queue
-const MESSAGE_QUEUE_LEN: usize = 1000;
+/// default limit on the length of a task message queue
+pub const MESSAGE_QUEUE_LEN: usize = 1000;
 
 /// hold onto the different services created
 pub struct Services {
     logger: Logger,
     services: Vec<Service>,
+    health: ServicesHealth,
+    policy: SupervisorPolicy,
+    queue_len: usize,
+    shutdown: Shutdown,
+}
+
+/// what to do when a service fails: its function panics or its future
+/// returns an error
+///
+/// The node is shut down by requesting its shutdown: the failed service
+/// stops and the shutdown of the other services runs as for a signal.
+///
+/// Only the services processing inputs can be restarted: the failed input
+/// is dropped along with the state of the service, the state is built
+/// again once the restart delay has elapsed. The inputs received while the
+/// service waits to be restarted are dropped, the queue of the service keeps
+/// being read. The failure of any other service shuts the node down.
+#[derive(Debug, Clone, Copy)]
+pub enum SupervisorPolicy {
+    /// shut the node down
+    Shutdown,
+    /// restart the service after a delay, doubled at every consecutive
+    /// failure of the service up to `max_delay`
+    Restart {
+        initial_delay: Duration,
+        max_delay: Duration,
+    },
+}
+
+/// the state of a service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceState {
+    Running,
+    /// the service failed and is waiting to be restarted
+    Restarting,
+    /// the service failed and has not been restarted
+    Failed,
+    /// the service has returned
+    Stopped,
+}
+
+/// the health of a service, as reported by `ServicesHealth::status`
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatus {
+    name: &'static str,
+    state: ServiceState,
+    started_at: SystemTime,
+    /// `false` for the services shutting the node down when they fail,
+    /// whatever the supervisor policy: only the services processing inputs
+    /// can be restarted
+    restartable: bool,
+    restarts: u32,
+    last_error: Option<String>,
+    /// the number of inputs dropped while the service was waiting to be
+    /// restarted
+    dropped_inputs: u64,
+    /// the number of inputs pending in the queue of the service, for the
+    /// services with a queue
+    queue_len: Option<usize>,
+}
+
+/// the health of all the services, shared with the services to report
+/// their state
+#[derive(Clone, Default)]
+pub struct ServicesHealth(Arc<Mutex<Vec<ServiceHealth>>>);
+
+struct ServiceHealth {
+    status: ServiceStatus,
+    queue: Option<QueueMetrics>,
+    /// the number of failures since the last input processed successfully
+    consecutive_failures: u32,
+}
+
+/// handle given to a service to report its state
+#[derive(Clone)]
+struct HealthHandle {
+    health: ServicesHealth,
+    index: usize,
+}
+
+/// applies the supervisor policy on the failures of a service
+#[derive(Clone)]
+struct Supervisor {
+    name: &'static str,
+    policy: SupervisorPolicy,
+    health: HealthHandle,
+    logger: Logger,
+    shutdown: Shutdown,
+}
+
+/// the state of a service processing inputs: dropped when the service
+/// fails, built again with `start` once the restart delay has elapsed
+struct Restartable<S, F> {
+    start: S,
+    state: Option<F>,
+    restart_at: Instant,
 }
 
 /// wrap up a service
@@ -62,7 +169,7 @@ pub struct TokioServiceInfo {
     executor: runtime::TaskExecutor,
 }
 
-pub struct TaskMessageBox<Msg>(Sender<Msg>);
+pub struct TaskMessageBox<Msg>(Sender<Msg>, QueueMetrics);
 
 /// Input for the different task with input service
 ///
@@ -82,11 +189,51 @@ enum Inner {
 }
 
 impl Services {
-    /// create a new set of services
-    pub fn new(logger: Logger) -> Self {
+    /// create a new set of services, the failure of a service which
+    /// cannot be restarted requests the given shutdown
+    pub fn new(logger: Logger, shutdown: Shutdown) -> Self {
         Services {
             logger: logger,
             services: Vec::new(),
+            health: ServicesHealth::default(),
+            policy: SupervisorPolicy::default(),
+            queue_len: MESSAGE_QUEUE_LEN,
+            shutdown,
+        }
+    }
+
+    /// set the length of the message queues of the services spawned
+    /// from now on with `spawn_future_with_inputs`
+    pub fn set_queue_len(&mut self, queue_len: usize) {
+        self.queue_len = queue_len;
+    }
+
+    /// set the supervisor policy of the services spawned from now on
+    pub fn set_policy(&mut self, policy: SupervisorPolicy) {
+        self.policy = policy;
+    }
+
+    /// the health of the services, kept up to date by the services
+    pub fn health(&self) -> ServicesHealth {
+        self.health.clone()
+    }
+
+    /// report the metrics of the given queue as the queue of the given
+    /// service, for the services reading a queue they have not created
+    pub fn monitor_queue(&mut self, name: &'static str, queue: QueueMetrics) {
+        let mut services = self.health.0.lock().unwrap();
+        if let Some(service) = services.iter_mut().find(|s| s.status.name == name) {
+            service.queue = Some(queue);
+        }
+    }
+
+    fn supervisor(&self, name: &'static str, restartable: bool) -> Supervisor {
+        Supervisor {
+            name,
+            policy: self.policy,
+            health: self.health.register(name, restartable),
+            logger: self.logger.new(o!(::log::KEY_TASK => name)),
+            shutdown: self.shutdown.clone(),
         }
     }
 
@@ -94,10 +241,27 @@ impl Services {
     /// given function does not return. As soon as the function return
     /// the service stop
     ///
+    /// If the function panics, the node is shut down.
     pub fn spawn<F>(&mut self, name: &'static str, f: F)
     where
         F: FnOnce(ThreadServiceInfo) -> (),
         F: Send + 'static,
+    {
+        let supervisor = self.supervisor(name, false);
+        self.spawn_supervised(
+            name,
+            supervisor,
+            move |info, supervisor| match panic::catch_unwind(AssertUnwindSafe(|| f(info))) {
+                Ok(()) => supervisor.stopped(),
+                Err(panic) => supervisor.fatal(panic_message(&panic)),
+            },
+        )
+    }
+
+    fn spawn_supervised<F>(&mut self, name: &'static str, supervisor: Supervisor, f: F)
+    where
+        F: FnOnce(ThreadServiceInfo, Supervisor) -> (),
+        F: Send + 'static,
     {
         let now = Instant::now();
         let thread_service_info = ThreadServiceInfo {
@@ -111,7 +275,7 @@ impl Services {
             // .stack_size(2 * 1024 * 1024)
             .spawn(move || {
                 info!(thread_service_info.logger, "starting task");
-                f(thread_service_info)
+                f(thread_service_info, supervisor)
             })
             .unwrap_or_else(|err| panic!("Cannot spawn thread: {}", err));
 
@@ -121,39 +285,98 @@ impl Services {
 
     /// spawn a service that will be launched for every given inputs
     ///
-    /// the service will stop once there is no more input to read: the function
-    /// will be called one last time with `Input::Shutdown` and then will return
+    /// `start` builds the state of the service: the function processing
+    /// the inputs. The service will stop once there is no more input to
+    /// read: the function will be called one last time with
+    /// `Input::Shutdown` and then will return
     ///
-    pub fn spawn_with_inputs<F, Msg>(&mut self, name: &'static str, mut f: F) -> TaskMessageBox<Msg>
+    /// If the function panics, the supervisor policy is applied.
+    pub fn spawn_with_inputs<S, F, Msg>(
+        &mut self,
+        name: &'static str,
+        start: S,
+    ) -> TaskMessageBox<Msg>
     where
+        S: FnMut() -> F,
+        S: Send + 'static,
         F: FnMut(&ThreadServiceInfo, Input<Msg>) -> (),
-        F: Send + 'static,
         Msg: Send + 'static,
     {
         let (tx, rx) = mpsc::channel::<Msg>();
-
-        self.spawn(name, move |info| loop {
-            match rx.recv() {
-                Ok(msg) => f(&info, Input::Input(msg)),
-                Err(err) => {
-                    warn!(
-                        info.logger,
-                        "Shutting down service {} (up since {}): {}",
-                        name,
-                        humantime::format_duration(info.up_time()),
-                        err
-                    );
-                    f(&info, Input::Shutdown);
-                    break;
+        let metrics = QueueMetrics::default();
+        let supervisor = self.supervisor(name, true);
+        supervisor.health.set_queue(metrics.clone());
+
+        let queue_metrics = metrics.clone();
+        self.spawn_supervised(name, supervisor, move |info, supervisor| {
+            let mut service = Restartable::new(start);
+            loop {
+                match rx.recv() {
+                    Ok(msg) => {
+                        queue_metrics.received();
+                        let f = match service.state(&supervisor) {
+                            Some(f) => f,
+                            None => {
+                                supervisor.dropped_input();
+                                continue;
+                            }
+                        };
+                        match panic::catch_unwind(AssertUnwindSafe(|| f(&info, Input::Input(msg))))
+                        {
+                            Ok(()) => supervisor.succeeded(),
+                            Err(panic) => match supervisor.failed(panic_message(&panic)) {
+                                Some(delay) => service.failed(delay),
+                                None => {
+                                    supervisor.shutdown_node();
+                                    break;
+                                }
+                            },
+                        }
+                    }
+                    Err(err) => {
+                        warn!(
+                            info.logger,
+                            "Shutting down service {} (up since {}): {}",
+                            name,
+                            humantime::format_duration(info.up_time()),
+                            err
+                        );
+                        if let Some(f) = service.state.as_mut() {
+                            f(&info, Input::Shutdown);
+                        }
+                        supervisor.stopped();
+                        break;
+                    }
                 }
             }
         });
 
-        TaskMessageBox(tx)
+        TaskMessageBox(tx, metrics)
     }
 
     /// Spawn the given Future in a new dedicated runtime
+    ///
+    /// If the future panics or returns an error, the node is shut down.
     pub fn spawn_future<F, T>(&mut self, name: &'static str, f: F)
+    where
+        F: FnOnce(TokioServiceInfo) -> T,
+        T: Future<Item = (), Error = ()> + Send + 'static,
+    {
+        let supervisor = self.supervisor(name, false);
+        self.spawn_future_supervised(name, move |info| {
+            AssertUnwindSafe(f(info))
+                .catch_unwind()
+                .then(move |outcome| match outcome {
+                    Ok(Ok(())) => Ok(supervisor.stopped()),
+                    Ok(Err(())) => {
+                        Err(supervisor.fatal("the service returned an error".to_owned()))
+                    }
+                    Err(panic) => Err(supervisor.fatal(panic_message(&panic))),
+                })
+        })
+    }
+
+    fn spawn_future_supervised<F, T>(&mut self, name: &'static str, f: F)
     where
         F: FnOnce(TokioServiceInfo) -> T,
         T: Future<Item = (), Error = ()> + Send + 'static,
@@ -174,19 +397,7 @@ impl Services {
             executor: executor,
         };
 
-        use std::panic::AssertUnwindSafe;
-
-        let future = AssertUnwindSafe(f(future_service_info))
-            .catch_unwind()
-            .map(|_| ())
-            .map_err(|err| {
-                if let Some(string) = err.downcast_ref::<String>() {
-                    eprintln!("{}", string);
-                }
-                std::process::exit(66);
-            });
-
-        runtime.spawn(future);
+        runtime.spawn(f(future_service_info));
 
         let task = Service::new_runtime(name, runtime, now);
         self.services.push(task);
@@ -194,24 +405,69 @@ impl Services {
 
     /// Spawn a tokio service that will await messages and will be executed
     /// sequentially for every received inputs
-    pub fn spawn_future_with_inputs<F, Msg, T>(
+    ///
+    /// `start` builds the state of the service: the function processing
+    /// the inputs. If the processing of an input panics or returns an
+    /// error, the supervisor policy is applied.
+    pub fn spawn_future_with_inputs<S, F, Msg, T>(
         &mut self,
         name: &'static str,
-        mut f: F,
+        start: S,
     ) -> MessageBox<Msg>
     where
+        S: FnMut() -> F,
+        S: Send + 'static,
         F: FnMut(&TokioServiceInfo, Input<Msg>) -> T,
         F: Send + 'static,
         Msg: Send + 'static,
         T: IntoFuture<Item = (), Error = ()> + Send + 'static,
         <T as futures::IntoFuture>::Future: Send,
     {
-        let (msg_box, msg_queue) = async_msg::channel(MESSAGE_QUEUE_LEN);
-        self.spawn_future(name, move |future_service_info| {
+        let (msg_box, msg_queue) = async_msg::channel(self.queue_len);
+        let supervisor = self.supervisor(name, true);
+        supervisor.health.set_queue(msg_box.metrics());
+        self.spawn_future_supervised(name, move |future_service_info| {
+            let stopped = supervisor.clone();
             msg_queue
                 .map(Input::Input)
                 .chain(stream::once(Ok(Input::Shutdown)))
-                .for_each(move |input| f(&future_service_info, input))
+                .fold(Restartable::new(start), move |mut service, input| {
+                    let supervisor = supervisor.clone();
+                    let f = match service.state(&supervisor) {
+                        Some(f) => f,
+                        None => {
+                            if let Input::Input(_) = input {
+                                supervisor.dropped_input();
+                            }
+                            return future::Either::A(future::ok(service));
+                        }
+                    };
+                    let processed = panic::catch_unwind(AssertUnwindSafe(|| {
+                        f(&future_service_info, input).into_future()
+                    }));
+                    let outcome = match processed {
+                        Ok(future) => future::Either::A(AssertUnwindSafe(future).catch_unwind()),
+                        Err(panic) => future::Either::B(future::err(panic)),
+                    };
+                    future::Either::B(outcome.then(move |outcome| {
+                        let error = match outcome {
+                            Ok(Ok(())) => {
+                                supervisor.succeeded();
+                                return Ok(service);
+                            }
+                            Ok(Err(())) => "the service failed to process an input".to_owned(),
+                            Err(panic) => panic_message(&panic),
+                        };
+                        match supervisor.failed(error) {
+                            Some(delay) => {
+                                service.failed(delay);
+                                Ok(service)
+                            }
+                            None => Err(supervisor.shutdown_node()),
+                        }
+                    }))
+                })
+                .map(move |_| stopped.stopped())
         });
         msg_box
     }
@@ -315,14 +571,280 @@ impl Service {
     }
 }
 
+impl<S, F> Restartable<S, F>
+where
+    S: FnMut() -> F,
+{
+    fn new(mut start: S) -> Self {
+        let state = Some(start());
+        Restartable {
+            start,
+            state,
+            restart_at: Instant::now(),
+        }
+    }
+
+    /// the state of the service, built again if the service has failed and
+    /// its restart delay has elapsed. `None` while the service waits to be
+    /// restarted
+    fn state(&mut self, supervisor: &Supervisor) -> Option<&mut F> {
+        if self.state.is_none() {
+            if Instant::now() < self.restart_at {
+                return None;
+            }
+            self.state = Some((self.start)());
+            supervisor.restarted();
+        }
+        self.state.as_mut()
+    }
+
+    /// drop the state of the failed service, it is not restarted before
+    /// the delay has elapsed
+    fn failed(&mut self, delay: Duration) {
+        self.state = None;
+        self.restart_at = Instant::now() + delay;
+    }
+}
+
 impl<Msg> Clone for TaskMessageBox<Msg> {
     fn clone(&self) -> Self {
-        TaskMessageBox(self.0.clone())
+        TaskMessageBox(self.0.clone(), self.1.clone())
     }
 }
 
 impl<Msg> TaskMessageBox<Msg> {
     pub fn send_to(&self, a: Msg) {
-        self.0.send(a).unwrap()
+        if let Err(err) = self.0.send(a) {
+            self.1.dropped();
+            panic!("{}", err)
+        }
+        self.1.sent();
+    }
+}
+
+impl Default for SupervisorPolicy {
+    fn default() -> Self {
+        SupervisorPolicy::Restart {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ServicesHealth {
+    /// the current status of every service, in the order the services
+    /// have been spawned
+    pub fn status(&self) -> Vec<ServiceStatus> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|service| ServiceStatus {
+                queue_len: service.queue.as_ref().map(QueueMetrics::depth),
+                ..service.status.clone()
+            })
+            .collect()
+    }
+
+    /// the metrics of the message queues of the services, by service name
+    pub fn queues(&self) -> BTreeMap<&'static str, QueueStats> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|service| {
+                let queue = service.queue.as_ref()?;
+                Some((service.status.name, queue.stats()))
+            })
+            .collect()
+    }
+
+    fn register(&self, name: &'static str, restartable: bool) -> HealthHandle {
+        let mut services = self.0.lock().unwrap();
+        services.push(ServiceHealth {
+            status: ServiceStatus {
+                name,
+                state: ServiceState::Running,
+                started_at: SystemTime::now(),
+                restartable,
+                restarts: 0,
+                last_error: None,
+                dropped_inputs: 0,
+                queue_len: None,
+            },
+            queue: None,
+            consecutive_failures: 0,
+        });
+        HealthHandle {
+            health: self.clone(),
+            index: services.len() - 1,
+        }
+    }
+}
+
+impl HealthHandle {
+    fn update<R>(&self, f: impl FnOnce(&mut ServiceHealth) -> R) -> R {
+        f(&mut self.health.0.lock().unwrap()[self.index])
+    }
+
+    fn set_queue(&self, queue: QueueMetrics) {
+        self.update(|service| service.queue = Some(queue))
+    }
+}
+
+impl Supervisor {
+    /// an input has been processed
+    fn succeeded(&self) {
+        self.health
+            .update(|service| service.consecutive_failures = 0)
+    }
+
+    /// the service has returned
+    fn stopped(&self) {
+        self.health
+            .update(|service| service.status.state = ServiceState::Stopped)
+    }
+
+    /// record a failure of the service, returns the delay before restarting
+    /// the service or `None` if the node has to be shut down
+    fn failed(&self, error: String) -> Option<Duration> {
+        error!(self.logger, "service failed"; "reason" => &error);
+        self.health.update(|service| {
+            service.status.last_error = Some(error);
+            service.consecutive_failures += 1;
+            match self.policy {
+                SupervisorPolicy::Shutdown => {
+                    service.status.state = ServiceState::Failed;
+                    None
+                }
+                SupervisorPolicy::Restart {
+                    initial_delay,
+                    max_delay,
+                } => {
+                    service.status.state = ServiceState::Restarting;
+                    let exponent = (service.consecutive_failures - 1).min(16);
+                    Some((initial_delay * 2u32.pow(exponent)).min(max_delay))
+                }
+            }
+        })
+    }
+
+    /// an input has been received while the service waits to be restarted
+    fn dropped_input(&self) {
+        debug!(
+            self.logger,
+            "dropping an input, the service waits to be restarted"
+        );
+        self.health
+            .update(|service| service.status.dropped_inputs += 1)
+    }
+
+    /// the service has been restarted after a failure
+    fn restarted(&self) {
+        info!(self.logger, "restarting service");
+        self.health.update(|service| {
+            service.status.state = ServiceState::Running;
+            service.status.restarts += 1;
+        })
+    }
+
+    /// the service failed and cannot be restarted
+    fn fatal(&self, error: String) {
+        error!(self.logger, "service failed"; "reason" => &error);
+        self.health.update(|service| {
+            service.status.state = ServiceState::Failed;
+            service.status.last_error = Some(error);
+        });
+        self.shutdown_node()
+    }
+
+    /// request the shutdown of the node, the service stops
+    fn shutdown_node(&self) {
+        crit!(
+            self.logger,
+            "shutting down the node after the failure of the service {}",
+            self.name
+        );
+        self.shutdown
+            .request(ShutdownReason::ServiceFailure(self.name));
+    }
+}
+
+fn panic_message(panic: &Box<dyn Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<String>() {
+        format!("panicked: {}", message)
+    } else if let Some(message) = panic.downcast_ref::<&str>() {
+        format!("panicked: {}", message)
+    } else {
+        "panicked".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// a service panicking on `true` inputs, counting the times its state
+    /// has been built
+    fn spawn_failing_service(
+        policy: SupervisorPolicy,
+        inputs: Vec<bool>,
+    ) -> (ServiceStatus, usize) {
+        let shutdown = Shutdown::default();
+        let mut services = Services::new(Logger::root(slog::Discard, o!()), shutdown);
+        services.set_policy(policy);
+        let starts = Arc::new(AtomicUsize::new(0));
+        let msg_box = {
+            let starts = starts.clone();
+            services.spawn_with_inputs("failing", move || {
+                starts.fetch_add(1, Ordering::SeqCst);
+                |_: &ThreadServiceInfo, input: Input<bool>| {
+                    if let Input::Input(true) = input {
+                        panic!("failing input")
+                    }
+                }
+            })
+        };
+        for input in inputs {
+            msg_box.send_to(input);
+        }
+        drop(msg_box);
+        let health = services.health();
+        services.wait_all();
+        let status = health.status().remove(0);
+        (status, starts.load(Ordering::SeqCst))
+    }
+
+    #[test]
+    fn failed_service_is_restarted_with_a_new_state() {
+        let policy = SupervisorPolicy::Restart {
+            initial_delay: Duration::from_secs(0),
+            max_delay: Duration::from_secs(0),
+        };
+        let (status, starts) = spawn_failing_service(policy, vec![true, false, true, false]);
+
+        assert_eq!(starts, 3);
+        assert!(status.restartable);
+        assert_eq!(status.restarts, 2);
+        assert_eq!(status.dropped_inputs, 0);
+        assert_eq!(status.state, ServiceState::Stopped);
+        assert_eq!(
+            status.last_error.as_ref().unwrap(),
+            "panicked: failing input"
+        );
+    }
+
+    #[test]
+    fn inputs_are_dropped_until_the_service_is_restarted() {
+        let policy = SupervisorPolicy::Restart {
+            initial_delay: Duration::from_secs(3600),
+            max_delay: Duration::from_secs(3600),
+        };
+        let (status, starts) = spawn_failing_service(policy, vec![false, true, false, false]);
+
+        assert_eq!(starts, 1);
+        assert_eq!(status.restarts, 0);
+        assert_eq!(status.dropped_inputs, 2);
     }
 }